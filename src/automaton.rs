@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+//One square of a Game of Life board. The grid owns every cell through an Rc, and the cells only
+// point at their neighbours through Weak. Neighbours point at each other in both directions, so
+// with Rc there every pair of neighbours would be a reference cycle.
+pub struct Cell {
+    alive: bool,
+    next: bool,
+    neighbors: Vec<Weak<RefCell<Cell>>>,
+}
+
+impl Cell {
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+}
+
+//Builds a `width` by `height` grid in row-major order with the listed (row, column) cells alive.
+// The edges do not wrap around, so corner cells have three neighbours.
+pub fn grid(width: usize, height: usize, alive: &[(usize, usize)]) -> Vec<Rc<RefCell<Cell>>> {
+    let cells: Vec<Rc<RefCell<Cell>>> = (0..width * height)
+        .map(|index| {
+            Rc::new(RefCell::new(Cell {
+                alive: alive.contains(&(index / width, index % width)),
+                next: false,
+                neighbors: Vec::new(),
+            }))
+        })
+        .collect();
+
+    for row in 0..height {
+        for column in 0..width {
+            let mut cell = cells[row * width + column].borrow_mut();
+            for neighbor_row in row.saturating_sub(1)..=(row + 1).min(height - 1) {
+                for neighbor_column in column.saturating_sub(1)..=(column + 1).min(width - 1) {
+                    if (neighbor_row, neighbor_column) != (row, column) {
+                        let neighbor = &cells[neighbor_row * width + neighbor_column];
+                        cell.neighbors.push(Rc::downgrade(neighbor));
+                    }
+                }
+            }
+        }
+    }
+
+    cells
+}
+
+//Advances every cell by one generation. Updating the cells in place one after another would let
+// later cells see the new state of earlier ones, so the update happens in two passes: the first
+// only reads `alive` and writes `next`, the second copies `next` over `alive`.
+pub fn step(cells: &[Rc<RefCell<Cell>>]) {
+    for cell in cells {
+        //The neighbour list is cloned out so this cell is not borrowed while its neighbours are.
+        let (alive, neighbors) = {
+            let cell = cell.borrow();
+            (cell.alive, cell.neighbors.clone())
+        };
+        let live_neighbors = neighbors
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|neighbor| neighbor.borrow().alive)
+            .count();
+        cell.borrow_mut().next = matches!((alive, live_neighbors), (true, 2) | (_, 3));
+    }
+
+    for cell in cells {
+        let mut cell = cell.borrow_mut();
+        cell.alive = cell.next;
+    }
+}
+
+//One line per row, with # for a live cell and . for a dead one.
+pub fn render(cells: &[Rc<RefCell<Cell>>], width: usize) -> String {
+    cells
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|cell| if cell.borrow().is_alive() { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blinker() -> Vec<Rc<RefCell<Cell>>> {
+        grid(5, 5, &[(2, 1), (2, 2), (2, 3)])
+    }
+
+    const HORIZONTAL: &str = ".....\n.....\n.###.\n.....\n.....";
+    const VERTICAL: &str = ".....\n..#..\n..#..\n..#..\n.....";
+
+    #[test]
+    fn a_blinker_flips_every_generation() {
+        let cells = blinker();
+        assert_eq!(render(&cells, 5), HORIZONTAL);
+        step(&cells);
+        assert_eq!(render(&cells, 5), VERTICAL);
+        step(&cells);
+        assert_eq!(render(&cells, 5), HORIZONTAL);
+    }
+
+    #[test]
+    fn corners_have_three_neighbours_and_the_middle_eight() {
+        let cells = grid(3, 3, &[]);
+        assert_eq!(cells[0].borrow().neighbors.len(), 3);
+        assert_eq!(cells[4].borrow().neighbors.len(), 8);
+        assert_eq!(cells[5].borrow().neighbors.len(), 5);
+    }
+
+    #[test]
+    fn dropping_the_grid_frees_every_cell() {
+        let cells = blinker();
+        //Only the Vec owns a cell, the neighbours all hold Weak pointers.
+        assert!(cells.iter().all(|cell| Rc::strong_count(cell) == 1));
+        assert_eq!(Rc::weak_count(&cells[12]), 8);
+
+        let weak: Vec<Weak<RefCell<Cell>>> = cells.iter().map(Rc::downgrade).collect();
+        drop(cells);
+        assert!(weak.iter().all(|cell| cell.upgrade().is_none()));
+    }
+}
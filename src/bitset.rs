@@ -0,0 +1,96 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const BITS_PER_WORD: usize = 64;
+
+//A bit set that several owners can share and modify. This is the Rc<RefCell<T>> pattern from
+// refcell_and_the_interior_mutability_pattern() applied to a Vec<u64> of words. Every clone of a
+// SharedBitSet is another handle to the same words.
+#[derive(Clone)]
+pub struct SharedBitSet {
+    words: Rc<RefCell<Vec<u64>>>,
+}
+
+impl SharedBitSet {
+    pub fn new() -> SharedBitSet {
+        SharedBitSet {
+            words: Rc::new(RefCell::new(vec![0])),
+        }
+    }
+
+    //Setting a bit past the end grows the vec. The length check and the growth both happen inside
+    // the same borrow_mut, so no other handle can see a half grown vec.
+    pub fn set(&self, bit: usize) {
+        let mut words = self.words.borrow_mut();
+        let word = bit / BITS_PER_WORD;
+        if word >= words.len() {
+            words.resize(word + 1, 0);
+        }
+        words[word] |= 1 << (bit % BITS_PER_WORD);
+    }
+
+    //Clearing a bit that was never set does not need to grow anything.
+    pub fn clear(&self, bit: usize) {
+        let mut words = self.words.borrow_mut();
+        if let Some(word) = words.get_mut(bit / BITS_PER_WORD) {
+            *word &= !(1 << (bit % BITS_PER_WORD));
+        }
+    }
+
+    pub fn test(&self, bit: usize) -> bool {
+        self.words
+            .borrow()
+            .get(bit / BITS_PER_WORD)
+            .map(|word| word & (1 << (bit % BITS_PER_WORD)) != 0)
+            .unwrap_or(false)
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.words.borrow().len()
+    }
+}
+
+impl Default for SharedBitSet {
+    fn default() -> Self {
+        SharedBitSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_set_through_one_handle_are_seen_through_another() {
+        let writer = SharedBitSet::new();
+        let reader = writer.clone();
+        writer.set(0);
+        writer.set(63);
+        assert!(reader.test(0));
+        assert!(reader.test(63));
+        assert!(!reader.test(1));
+
+        reader.clear(63);
+        assert!(!writer.test(63));
+    }
+
+    #[test]
+    fn setting_past_the_first_word_grows_the_vec() {
+        let bits = SharedBitSet::new();
+        assert_eq!(bits.word_count(), 1);
+        bits.set(64);
+        assert_eq!(bits.word_count(), 2);
+        bits.set(1_000);
+        assert_eq!(bits.word_count(), 1_000 / 64 + 1);
+        assert!(bits.test(64) && bits.test(1_000));
+        assert!(!bits.test(999));
+    }
+
+    #[test]
+    fn reading_or_clearing_past_the_end_does_not_grow() {
+        let bits = SharedBitSet::new();
+        assert!(!bits.test(10_000));
+        bits.clear(10_000);
+        assert_eq!(bits.word_count(), 1);
+    }
+}
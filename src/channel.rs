@@ -0,0 +1,281 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+
+//A bounded queue for passing values between parts of a single threaded program. The channel
+// itself is the only strong owner of the queue. Senders and receivers only hold Weak pointers, so
+// once the channel is dropped every handle finds out the next time it tries to upgrade.
+//Dropping the channel also drops whatever is still queued. Nobody can receive it any more, since
+// every receiver is left with a Weak that fails to upgrade.
+pub struct LocalChannel<T> {
+    shared: Rc<Shared<T>>,
+}
+
+//The Weak pointers cannot tell the two sides apart, so each side keeps a count of its live handles
+// here. That is how a sender notices there is nobody left to receive, and the other way around.
+struct Shared<T> {
+    items: RefCell<VecDeque<T>>,
+    capacity: usize,
+    closed: Cell<bool>,
+    senders: Cell<usize>,
+    receivers: Cell<usize>,
+}
+
+//Senders can be cloned freely. Values from all of them end up in one queue in the order the
+// try_send() calls happened.
+pub struct Sender<T> {
+    shared: Weak<Shared<T>>,
+}
+
+pub struct Receiver<T> {
+    shared: Weak<Shared<T>>,
+}
+
+//A failed send hands the value back, so nothing is lost when the queue is full, closed or has
+// nobody left to receive it.
+#[derive(Debug, PartialEq)]
+pub enum TrySendError<T> {
+    Full(T),
+    //close() was called, or the channel itself was dropped.
+    Closed(T),
+    //Every receiver has been dropped.
+    Disconnected(T),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TryRecvError {
+    //Nothing is waiting right now, but more may arrive later.
+    Empty,
+    //Nothing is waiting and nothing more can arrive. The channel was closed or dropped, or every
+    // sender has been dropped.
+    Disconnected,
+}
+
+impl<T> LocalChannel<T> {
+    pub fn new(capacity: usize) -> LocalChannel<T> {
+        LocalChannel {
+            shared: Rc::new(Shared {
+                items: RefCell::new(VecDeque::with_capacity(capacity)),
+                capacity,
+                closed: Cell::new(false),
+                senders: Cell::new(0),
+                receivers: Cell::new(0),
+            }),
+        }
+    }
+
+    //The channel can hand out new handles at any time, so a side that has no handles left is only
+    // disconnected until the next sender() or receiver() call. Making the handles before using them
+    // avoids seeing that in between.
+    pub fn sender(&self) -> Sender<T> {
+        increment(&self.shared.senders);
+        Sender {
+            shared: Rc::downgrade(&self.shared),
+        }
+    }
+
+    pub fn receiver(&self) -> Receiver<T> {
+        increment(&self.shared.receivers);
+        Receiver {
+            shared: Rc::downgrade(&self.shared),
+        }
+    }
+
+    //No more values are accepted after this. The ones already queued can still be received.
+    pub fn close(&self) {
+        self.shared.closed.set(true);
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.items.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.items.borrow().is_empty()
+    }
+}
+
+impl<T> Sender<T> {
+    //A sender whose channel was dropped sees the same thing as a closed channel.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let shared = match self.shared.upgrade() {
+            Some(shared) => shared,
+            None => return Err(TrySendError::Closed(value)),
+        };
+        if shared.closed.get() {
+            return Err(TrySendError::Closed(value));
+        }
+        if shared.receivers.get() == 0 {
+            return Err(TrySendError::Disconnected(value));
+        }
+
+        let mut items = shared.items.borrow_mut();
+        if items.len() >= shared.capacity {
+            return Err(TrySendError::Full(value));
+        }
+        items.push_back(value);
+        Ok(())
+    }
+}
+
+impl<T> Receiver<T> {
+    //Values that were queued before close(), or before the last sender was dropped, still come out
+    // first. Disconnected is only returned once they are gone.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let shared = self.shared.upgrade().ok_or(TryRecvError::Disconnected)?;
+        let value = shared.items.borrow_mut().pop_front();
+        match value {
+            Some(value) => Ok(value),
+            None if shared.closed.get() || shared.senders.get() == 0 => {
+                Err(TryRecvError::Disconnected)
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        if let Some(shared) = self.shared.upgrade() {
+            increment(&shared.senders);
+        }
+        Sender {
+            shared: Weak::clone(&self.shared),
+        }
+    }
+}
+
+//Once the channel is gone there is no count left to update.
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            decrement(&shared.senders);
+        }
+    }
+}
+
+//Values that are still queued when the last receiver drops stay in the queue. A receiver made
+// later can still get them, otherwise they are dropped together with the channel.
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            decrement(&shared.receivers);
+        }
+    }
+}
+
+fn increment(count: &Cell<usize>) {
+    count.set(count.get() + 1);
+}
+
+fn decrement(count: &Cell<usize>) {
+    count.set(count.get() - 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn full_channel_hands_back_the_rejected_value() {
+        let channel = LocalChannel::new(2);
+        let (sender, _receiver) = (channel.sender(), channel.receiver());
+        assert_eq!(sender.try_send(1), Ok(()));
+        assert_eq!(sender.try_send(2), Ok(()));
+        assert_eq!(sender.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(channel.len(), 2);
+    }
+
+    #[test]
+    fn queued_values_drain_after_close() {
+        let channel = LocalChannel::new(4);
+        let (sender, receiver) = (channel.sender(), channel.receiver());
+        sender.try_send(1).unwrap();
+        sender.try_send(2).unwrap();
+        channel.close();
+
+        assert_eq!(sender.try_send(3), Err(TrySendError::Closed(3)));
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn open_and_empty_is_not_disconnected() {
+        let channel = LocalChannel::<i32>::new(1);
+        let (_sender, receiver) = (channel.sender(), channel.receiver());
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn sender_is_disconnected_once_every_receiver_is_gone() {
+        let channel = LocalChannel::new(4);
+        let sender = channel.sender();
+        let receiver = channel.receiver();
+        sender.try_send(1).unwrap();
+        drop(receiver);
+
+        assert_eq!(sender.try_send(2), Err(TrySendError::Disconnected(2)));
+        //The value queued before the receiver went away is still there for a new receiver.
+        let receiver = channel.receiver();
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(sender.try_send(3), Ok(()));
+    }
+
+    #[test]
+    fn receiver_drains_then_sees_disconnected_once_every_sender_is_gone() {
+        let channel = LocalChannel::new(4);
+        let receiver = channel.receiver();
+        let sender = channel.sender();
+        let clone = sender.clone();
+        sender.try_send(1).unwrap();
+        drop(sender);
+        clone.try_send(2).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(1));
+        drop(clone);
+
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn dropping_the_channel_disconnects_both_sides() {
+        let channel = LocalChannel::new(4);
+        let (sender, receiver) = (channel.sender(), channel.receiver());
+        drop(channel);
+        assert_eq!(sender.try_send(1), Err(TrySendError::Closed(1)));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn values_from_several_senders_come_out_in_send_order() {
+        let channel = LocalChannel::new(8);
+        let receiver = channel.receiver();
+        let a = channel.sender();
+        let b = a.clone();
+        for value in 0..6 {
+            let sender = if value % 2 == 0 { &a } else { &b };
+            sender.try_send(value).unwrap();
+        }
+        let received: Vec<i32> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(received, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn no_payload_outlives_the_channel() {
+        let payload = Rc::new(());
+        let channel = LocalChannel::new(3);
+        let (sender, receiver) = (channel.sender(), channel.receiver());
+        for _ in 0..5 {
+            //The rejected clones come back inside the error and are dropped right here.
+            let _ = sender.try_send(Rc::clone(&payload));
+        }
+        drop(receiver.try_recv());
+        assert_eq!(Rc::strong_count(&payload), 3);
+
+        drop(channel);
+        assert_eq!(Rc::strong_count(&payload), 1);
+    }
+}
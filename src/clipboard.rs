@@ -0,0 +1,114 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+//A clipboard that can be shared between several owners by cloning it. Every clone points at the
+// same history and the same position in that history. The entries are Rc<str>, so pasting only
+// bumps a reference count instead of copying the text.
+#[derive(Clone)]
+pub struct Clipboard {
+    history: Rc<RefCell<Vec<Rc<str>>>>,
+    current: Rc<Cell<Option<usize>>>,
+}
+
+impl Clipboard {
+    pub fn new() -> Clipboard {
+        Clipboard {
+            history: Rc::new(RefCell::new(Vec::new())),
+            current: Rc::new(Cell::new(None)),
+        }
+    }
+
+    //Copying always makes the new text the current entry.
+    pub fn copy(&self, text: &str) {
+        let mut history = self.history.borrow_mut();
+        history.push(Rc::from(text));
+        self.current.set(Some(history.len() - 1));
+    }
+
+    pub fn paste(&self) -> Option<Rc<str>> {
+        let index = self.current.get()?;
+        self.history.borrow().get(index).cloned()
+    }
+
+    //Moves to the entry copied before the current one. Stays on the oldest entry once it is
+    // reached.
+    pub fn previous(&self) -> Option<Rc<str>> {
+        let index = self.current.get()?;
+        self.current.set(Some(index.saturating_sub(1)));
+        self.paste()
+    }
+
+    //Moves to the entry copied after the current one. Stays on the newest entry once it is
+    // reached.
+    pub fn next(&self) -> Option<Rc<str>> {
+        let index = self.current.get()?;
+        let last = self.history.borrow().len() - 1;
+        self.current.set(Some((index + 1).min(last)));
+        self.paste()
+    }
+
+    pub fn history(&self) -> Vec<Rc<str>> {
+        self.history.borrow().clone()
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Clipboard::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(entry: Option<Rc<str>>) -> Option<String> {
+        entry.map(|entry| entry.to_string())
+    }
+
+    #[test]
+    fn an_empty_clipboard_has_nothing_to_paste_or_navigate() {
+        let clipboard = Clipboard::new();
+        assert_eq!(clipboard.paste(), None);
+        assert_eq!(clipboard.previous(), None);
+        assert_eq!(clipboard.next(), None);
+    }
+
+    #[test]
+    fn paste_returns_the_latest_copy_from_any_clone() {
+        let editor = Clipboard::new();
+        let terminal = editor.clone();
+        editor.copy("first");
+        terminal.copy("second");
+        assert_eq!(text(editor.paste()), Some("second".to_string()));
+        assert_eq!(text(terminal.paste()), Some("second".to_string()));
+    }
+
+    #[test]
+    fn pasting_shares_the_history_entry() {
+        let clipboard = Clipboard::new();
+        clipboard.copy("shared");
+        let pasted = clipboard.paste().unwrap();
+        assert!(Rc::ptr_eq(&pasted, &clipboard.history()[0]));
+    }
+
+    #[test]
+    fn navigation_stops_at_both_ends() {
+        let clipboard = Clipboard::new();
+        for entry in ["a", "b", "c"] {
+            clipboard.copy(entry);
+        }
+        assert_eq!(text(clipboard.previous()), Some("b".to_string()));
+        assert_eq!(text(clipboard.previous()), Some("a".to_string()));
+        assert_eq!(text(clipboard.previous()), Some("a".to_string()));
+        assert_eq!(text(clipboard.next()), Some("b".to_string()));
+        assert_eq!(text(clipboard.next()), Some("c".to_string()));
+        assert_eq!(text(clipboard.next()), Some("c".to_string()));
+
+        //Copying jumps back to the newest entry wherever the position was.
+        clipboard.previous();
+        clipboard.copy("d");
+        assert_eq!(text(clipboard.paste()), Some("d".to_string()));
+        assert_eq!(clipboard.history().len(), 4);
+    }
+}
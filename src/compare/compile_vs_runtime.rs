@@ -0,0 +1,203 @@
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::Once;
+
+//The same four aliasing mistakes, once as plain references where the compiler rejects them and
+// once through RefCell<T>/Rc<T> where the check moves to runtime and becomes a panic. The compiler
+// version of each is a compile_fail doc test, so `cargo test` checks that it really is rejected.
+// The error code after compile_fail is only verified by nightly rustdoc, on stable it just says
+// which error to expect.
+
+//Two mutable borrows at the same time.
+/// ```compile_fail,E0499
+/// let mut items = vec![1];
+/// let first = &mut items;
+/// let second = &mut items;
+/// first.push(2);
+/// second.push(3);
+/// ```
+pub fn two_mutable_borrows() {
+    let items = RefCell::new(vec![1]);
+    let mut first = items.borrow_mut();
+    let mut second = items.borrow_mut();
+    first.push(2);
+    second.push(3);
+}
+
+//A mutable borrow while a shared borrow is still in use.
+/// ```compile_fail,E0502
+/// let mut items = vec![1];
+/// let writer = &mut items;
+/// let reader = &items;
+/// writer.push(reader[0]);
+/// ```
+pub fn mutable_plus_shared() {
+    let items = RefCell::new(vec![1]);
+    let mut writer = items.borrow_mut();
+    let reader = items.borrow();
+    writer.push(reader[0]);
+}
+
+//Keeping a reference to a value after the scope that owned it has ended. With Rc<T> the closest
+// thing is a Weak<T>, which compiles fine but has nothing to upgrade to.
+/// ```compile_fail,E0597
+/// let reference;
+/// {
+///     let value = String::from("short lived");
+///     reference = &value;
+/// }
+/// println!("{}", reference);
+/// ```
+pub fn reference_outliving_its_scope() {
+    let reference;
+    {
+        let value = Rc::new(String::from("short lived"));
+        reference = Rc::downgrade(&value);
+    }
+    let value = reference.upgrade().expect("the value was dropped at the end of its scope");
+    println!("{}", value);
+}
+
+//Changing a collection while iterating over it.
+/// ```compile_fail,E0502
+/// let mut items = vec![1, 2];
+/// for item in &items {
+///     items.push(*item);
+/// }
+/// ```
+pub fn iterator_invalidation() {
+    let items = RefCell::new(vec![1, 2]);
+    for item in items.borrow().iter() {
+        items.borrow_mut().push(*item);
+    }
+}
+
+pub struct MistakePair {
+    pub name: &'static str,
+    pub compiler_error: &'static str,
+    pub runtime: fn(),
+}
+
+pub fn pairs() -> Vec<MistakePair> {
+    vec![
+        MistakePair {
+            name: "two mutable borrows",
+            compiler_error: "E0499 cannot borrow as mutable more than once at a time",
+            runtime: two_mutable_borrows,
+        },
+        MistakePair {
+            name: "mutable plus shared",
+            compiler_error: "E0502 cannot borrow as immutable because it is also borrowed as mutable",
+            runtime: mutable_plus_shared,
+        },
+        MistakePair {
+            name: "reference outliving its scope",
+            compiler_error: "E0597 borrowed value does not live long enough",
+            runtime: reference_outliving_its_scope,
+        },
+        MistakePair {
+            name: "iterator invalidation",
+            compiler_error: "E0502 cannot borrow as mutable because it is also borrowed as immutable",
+            runtime: iterator_invalidation,
+        },
+    ]
+}
+
+thread_local! {
+    //Set while catch_unwind_quietly() is running `f` on this thread.
+    static SILENCED: Cell<bool> = const { Cell::new(false) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+//Puts SILENCED back to what it was before, even if `f` panicked.
+struct Unsilence(bool);
+
+impl Drop for Unsilence {
+    fn drop(&mut self) {
+        SILENCED.with(|silenced| silenced.set(self.0));
+    }
+}
+
+//Like panic::catch_unwind, except the panic message is not printed to stderr. The panic hook is
+// global to the whole process, so swapping it in and out around every call would race with any
+// other thread doing the same, and tests run on several threads at once. Instead one hook is
+// installed the first time this runs and stays. It skips panics on threads that are currently
+// silenced and hands every other panic to the hook that was there before.
+pub fn catch_unwind_quietly<R>(f: impl FnOnce() -> R) -> std::thread::Result<R> {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !SILENCED.with(Cell::get) {
+                previous(info);
+            }
+        }));
+    });
+
+    let _unsilence = Unsilence(SILENCED.with(|silenced| silenced.replace(true)));
+    panic::catch_unwind(AssertUnwindSafe(f))
+}
+
+//Runs `f` and returns the panic message if it panicked. The expected panic is not printed.
+pub fn panic_message(f: fn()) -> Option<String> {
+    let result = catch_unwind_quietly(f);
+
+    let payload = result.err()?;
+    let message = match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| String::from("<non-string panic>")),
+    };
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_runtime_variant_panics_with_the_expected_message() {
+        let expected = [
+            "already borrowed",
+            "already mutably borrowed",
+            "dropped at the end of its scope",
+            "already borrowed",
+        ];
+        for (pair, fragment) in pairs().into_iter().zip(expected) {
+            let message = panic_message(pair.runtime)
+                .unwrap_or_else(|| panic!("{} did not panic", pair.name));
+            assert!(message.contains(fragment), "{}: {}", pair.name, message);
+        }
+    }
+
+    #[test]
+    fn panic_message_is_none_without_a_panic() {
+        assert_eq!(panic_message(|| {}), None);
+    }
+
+    #[test]
+    fn catch_unwind_quietly_unsilences_the_thread_afterwards() {
+        assert!(catch_unwind_quietly(|| panic!("quiet")).is_err());
+        assert!(!SILENCED.with(Cell::get));
+
+        //A nested call puts back the outer call's setting, not false.
+        let inner = catch_unwind_quietly(|| {
+            let _ = catch_unwind_quietly(|| panic!("inner"));
+            SILENCED.with(Cell::get)
+        });
+        assert_eq!(inner.ok(), Some(true));
+        assert_eq!(catch_unwind_quietly(|| 5).ok(), Some(5));
+    }
+
+    #[test]
+    fn overlapping_calls_on_several_threads_all_catch_their_panic() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| (0..50).all(|_| panic_message(two_mutable_borrows).is_some())))
+            .collect();
+        assert!(threads.into_iter().all(|thread| thread.join().unwrap()));
+        assert!(!SILENCED.with(Cell::get));
+    }
+}
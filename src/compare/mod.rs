@@ -0,0 +1,3 @@
+//Side by side comparisons of the same idea written in different ways.
+pub mod compile_vs_runtime;
+pub mod ownership_styles;
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//The Rc section says lifetimes are better than Rc<T> when they are possible. This is a small
+// address book written both ways so the trade-offs can actually be seen. Contacts are stored in a
+// central list and can be members of several groups at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contact {
+    pub name: String,
+    pub email: String,
+}
+
+impl Contact {
+    pub fn new(name: &str, email: &str) -> Contact {
+        Contact {
+            name: name.to_string(),
+            email: email.to_string(),
+        }
+    }
+}
+
+//Both versions implement this trait so the exact same code can be run against either of them.
+pub trait AddressBook {
+    //Adds the contact with the given name to a group, returning false if there is no such contact.
+    fn add_to_group(&mut self, group: &str, name: &str) -> bool;
+
+    fn members(&self, group: &str) -> Vec<String>;
+
+    //How many heap allocations were made purely so that contacts could be shared between groups.
+    fn sharing_allocations(&self) -> usize;
+}
+
+//The borrowed version. The contacts are owned by somebody else and the groups hold plain
+// references into that list, so no extra allocations are needed for sharing.
+//It cannot outlive its contacts though. This does not compile because `contacts` is dropped while
+// `book` still holds references into it, which is exactly what remove_central_list() on RcBook is
+// allowed to do.
+/// ```compile_fail,E0597
+/// use rust_book_chapter_15::compare::ownership_styles::{AddressBook, BorrowedBook, Contact};
+///
+/// let book = {
+///     let contacts = vec![Contact::new("Ann", "ann@example.com")];
+///     let mut book = BorrowedBook::new(&contacts);
+///     book.add_to_group("friends", "Ann");
+///     book
+/// };
+/// println!("{:?}", book.members("friends"));
+/// ```
+//It also cannot add a new contact to the central list while any group is borrowing from it.
+/// ```compile_fail,E0502
+/// use rust_book_chapter_15::compare::ownership_styles::{AddressBook, BorrowedBook, Contact};
+///
+/// let mut contacts = vec![Contact::new("Ann", "ann@example.com")];
+/// let mut book = BorrowedBook::new(&contacts);
+/// contacts.push(Contact::new("Bob", "bob@example.com"));
+/// book.add_to_group("friends", "Ann");
+/// ```
+pub struct BorrowedBook<'a> {
+    contacts: &'a [Contact],
+    groups: HashMap<String, Vec<&'a Contact>>,
+}
+
+impl<'a> BorrowedBook<'a> {
+    pub fn new(contacts: &'a [Contact]) -> BorrowedBook<'a> {
+        BorrowedBook {
+            contacts,
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl AddressBook for BorrowedBook<'_> {
+    fn add_to_group(&mut self, group: &str, name: &str) -> bool {
+        match self.contacts.iter().find(|contact| contact.name == name) {
+            Some(contact) => {
+                self.groups.entry(group.to_string()).or_default().push(contact);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn members(&self, group: &str) -> Vec<String> {
+        self.groups
+            .get(group)
+            .map(|members| members.iter().map(|contact| contact.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn sharing_allocations(&self) -> usize {
+        0
+    }
+}
+
+//The Rc version. Every contact is allocated inside its own Rc<T>, and the central list and the
+// groups all hold clones of those Rcs.
+pub struct RcBook {
+    contacts: Vec<Rc<Contact>>,
+    groups: HashMap<String, Vec<Rc<Contact>>>,
+    allocations: usize,
+}
+
+impl RcBook {
+    pub fn new(contacts: Vec<Contact>) -> RcBook {
+        let allocations = contacts.len();
+        RcBook {
+            contacts: contacts.into_iter().map(Rc::new).collect(),
+            groups: HashMap::new(),
+            allocations,
+        }
+    }
+
+    //Something the borrowed version cannot do, the central list can grow at any time.
+    pub fn add_contact(&mut self, contact: Contact) {
+        self.contacts.push(Rc::new(contact));
+        self.allocations += 1;
+    }
+
+    //The central list can even be thrown away completely. Any contact that is still in a group is
+    // kept alive by that group's Rc.
+    pub fn remove_central_list(&mut self) {
+        self.contacts.clear();
+    }
+
+    pub fn contact_count(&self) -> usize {
+        self.contacts.len()
+    }
+}
+
+impl AddressBook for RcBook {
+    fn add_to_group(&mut self, group: &str, name: &str) -> bool {
+        match self.contacts.iter().find(|contact| contact.name == name) {
+            Some(contact) => {
+                self.groups
+                    .entry(group.to_string())
+                    .or_default()
+                    .push(Rc::clone(contact));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn members(&self, group: &str) -> Vec<String> {
+        self.groups
+            .get(group)
+            .map(|members| members.iter().map(|contact| contact.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn sharing_allocations(&self) -> usize {
+        self.allocations
+    }
+}
+
+//The shared scenario that is run against both versions. It returns the members of each group so
+// the results can be compared.
+pub fn run_scenario(book: &mut dyn AddressBook) -> Vec<Vec<String>> {
+    book.add_to_group("friends", "Ann");
+    book.add_to_group("friends", "Bob");
+    book.add_to_group("work", "Bob");
+    book.add_to_group("work", "Cat");
+    let missing = book.add_to_group("work", "Nobody");
+    assert!(!missing, "a missing contact should not be added");
+
+    vec![book.members("friends"), book.members("work"), book.members("empty")]
+}
+
+pub fn sample_contacts() -> Vec<Contact> {
+    vec![
+        Contact::new("Ann", "ann@example.com"),
+        Contact::new("Bob", "bob@example.com"),
+        Contact::new("Cat", "cat@example.com"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected_groups() -> Vec<Vec<String>> {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        vec![names(&["Ann", "Bob"]), names(&["Bob", "Cat"]), Vec::new()]
+    }
+
+    #[test]
+    fn the_shared_scenario_passes_on_both_books() {
+        let contacts = sample_contacts();
+        let mut borrowed = BorrowedBook::new(&contacts);
+        let mut shared = RcBook::new(sample_contacts());
+        assert_eq!(run_scenario(&mut borrowed), expected_groups());
+        assert_eq!(run_scenario(&mut shared), expected_groups());
+    }
+
+    #[test]
+    fn groups_of_the_rc_book_survive_removing_the_central_list() {
+        let mut book = RcBook::new(sample_contacts());
+        run_scenario(&mut book);
+        book.remove_central_list();
+        assert_eq!(book.contact_count(), 0);
+        assert_eq!(book.members("friends"), ["Ann", "Bob"]);
+        //With the central list gone, there is nobody left to add to a group.
+        assert!(!book.add_to_group("friends", "Cat"));
+    }
+
+    #[test]
+    fn only_the_rc_book_allocates_for_sharing() {
+        let contacts = sample_contacts();
+        let mut borrowed = BorrowedBook::new(&contacts);
+        let mut shared = RcBook::new(sample_contacts());
+        run_scenario(&mut borrowed);
+        run_scenario(&mut shared);
+        assert_eq!(borrowed.sharing_allocations(), 0);
+        assert_eq!(shared.sharing_allocations(), contacts.len());
+
+        shared.add_contact(Contact::new("Dan", "dan@example.com"));
+        assert_eq!(shared.sharing_allocations(), contacts.len() + 1);
+    }
+}
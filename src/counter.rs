@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+//A threshold together with the callback to run when the count goes past it.
+type Threshold = (usize, Rc<dyn Fn(usize)>);
+
+//A counter that can be handed out to several owners. Cloning a Counter gives another handle to the
+// same count, so a bump through one handle is seen through all of them. The thresholds are shared
+// the same way, like the LimitTracker from the chapter but with any number of limits.
+#[derive(Clone, Default)]
+pub struct Counter {
+    count: Rc<RefCell<usize>>,
+    thresholds: Rc<RefCell<Vec<Threshold>>>,
+}
+
+//A handle that does not keep the counter alive. A threshold callback that needs to reach its own
+// counter should hold one of these, a Counter clone inside the callback would be an Rc cycle.
+#[derive(Clone)]
+pub struct WeakCounter {
+    count: Weak<RefCell<usize>>,
+    thresholds: Weak<RefCell<Vec<Threshold>>>,
+}
+
+impl Counter {
+    pub fn new() -> Counter {
+        Counter::default()
+    }
+
+    pub fn downgrade(&self) -> WeakCounter {
+        WeakCounter {
+            count: Rc::downgrade(&self.count),
+            thresholds: Rc::downgrade(&self.thresholds),
+        }
+    }
+
+    pub fn get(&self) -> usize {
+        *self.count.borrow()
+    }
+
+    pub fn bump(&self) {
+        self.add(1);
+    }
+
+    //Runs the callback with the new count whenever the count goes from below `at` to `at` or
+    // above. The count only ever goes up, so every callback fires at most once.
+    pub fn on_threshold(&self, at: usize, callback: Rc<dyn Fn(usize)>) {
+        self.thresholds.borrow_mut().push((at, callback));
+    }
+
+    //The callbacks are cloned out and both borrows are released before any of them run, so a
+    // callback is free to read or bump the counter itself.
+    pub fn add(&self, amount: usize) {
+        let (before, after) = {
+            let mut count = self.count.borrow_mut();
+            let before = *count;
+            *count += amount;
+            (before, *count)
+        };
+
+        let crossed: Vec<Rc<dyn Fn(usize)>> = self
+            .thresholds
+            .borrow()
+            .iter()
+            .filter(|(at, _)| before < *at && *at <= after)
+            .map(|(_, callback)| Rc::clone(callback))
+            .collect();
+        for callback in crossed {
+            callback(after);
+        }
+    }
+
+    pub fn same_counter(&self, other: &Counter) -> bool {
+        Rc::ptr_eq(&self.count, &other.count)
+    }
+}
+
+impl WeakCounter {
+    pub fn upgrade(&self) -> Option<Counter> {
+        Some(Counter {
+            count: self.count.upgrade()?,
+            thresholds: self.thresholds.upgrade()?,
+        })
+    }
+}
+
+//Bumps every distinct counter exactly once. Two handles that share an allocation are the same
+// counter, so without the ptr_eq check a counter passed in twice would go up by two.
+pub fn bump_all(counters: &[&Counter]) {
+    let mut bumped: Vec<&Counter> = Vec::new();
+    for counter in counters {
+        if bumped.iter().any(|done| done.same_counter(counter)) {
+            continue;
+        }
+        counter.bump();
+        bumped.push(counter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fired = Rc<RefCell<Vec<usize>>>;
+
+    //Records the count each time the callback runs.
+    fn recorder() -> (Fired, Rc<dyn Fn(usize)>) {
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&fired);
+        (fired, Rc::new(move |count| log.borrow_mut().push(count)))
+    }
+
+    #[test]
+    fn each_threshold_fires_once_when_it_is_crossed() {
+        let counter = Counter::new();
+        let (at_five, five) = recorder();
+        let (at_ten, ten) = recorder();
+        counter.on_threshold(5, five);
+        counter.on_threshold(10, ten);
+
+        for step in 1..=12 {
+            counter.bump();
+            let expected_five: &[usize] = if step >= 5 { &[5] } else { &[] };
+            let expected_ten: &[usize] = if step >= 10 { &[10] } else { &[] };
+            assert_eq!(*at_five.borrow(), expected_five);
+            assert_eq!(*at_ten.borrow(), expected_ten);
+        }
+    }
+
+    #[test]
+    fn one_big_add_can_cross_several_thresholds() {
+        let counter = Counter::new();
+        let (fired, callback) = recorder();
+        counter.on_threshold(5, Rc::clone(&callback));
+        counter.on_threshold(10, callback);
+        counter.add(3);
+        counter.add(9);
+        assert_eq!(*fired.borrow(), [12, 12]);
+    }
+
+    #[test]
+    fn a_callback_can_bump_its_own_counter() {
+        let counter = Counter::new();
+        let handle = counter.downgrade();
+        counter.on_threshold(
+            2,
+            Rc::new(move |_| {
+                if let Some(counter) = handle.upgrade() {
+                    counter.add(10);
+                }
+            }),
+        );
+        counter.add(2);
+        assert_eq!(counter.get(), 12);
+
+        //The callback only holds a WeakCounter, so nothing but `counter` keeps the count alive and
+        // dropping it frees everything, callback included.
+        assert_eq!(Rc::strong_count(&counter.count), 1);
+        assert_eq!(Rc::strong_count(&counter.thresholds), 1);
+        let watcher = counter.downgrade();
+        drop(counter);
+        assert!(watcher.upgrade().is_none());
+    }
+
+    #[test]
+    fn bump_all_counts_a_shared_counter_once() {
+        let a = Counter::new();
+        let b = Counter::new();
+        bump_all(&[&a, &a.clone(), &b]);
+        assert_eq!((a.get(), b.get()), (1, 1));
+    }
+
+    #[test]
+    fn bump_all_with_aliased_and_distinct_counters() {
+        let a = Counter::new();
+        let also_a = a.clone();
+        let b = Counter::new();
+        let c = Counter::new();
+        c.add(5);
+
+        bump_all(&[&a, &b, &also_a, &b, &c, &a]);
+        assert_eq!((a.get(), b.get(), c.get()), (1, 1, 6));
+        assert_eq!(also_a.get(), 1);
+
+        //A second call is a separate bump, the dedup only applies within one slice.
+        bump_all(&[&also_a, &c]);
+        assert_eq!((a.get(), b.get(), c.get()), (2, 1, 7));
+    }
+
+    #[test]
+    fn bump_all_on_no_counters_does_nothing() {
+        bump_all(&[]);
+        let a = Counter::new();
+        bump_all(&[&a]);
+        assert_eq!(a.get(), 1);
+    }
+}
@@ -0,0 +1,103 @@
+use std::rc::Rc;
+
+//A copy-on-write vector. Cloning a CowVec only clones the Rc, so the clones share one Vec<T>. The
+// first time a shared CowVec is changed, Rc::make_mut copies the Vec so the other clones keep
+// seeing the old contents. If nothing else shares the Vec, make_mut changes it in place.
+#[derive(Clone)]
+pub struct CowVec<T: Clone> {
+    items: Rc<Vec<T>>,
+}
+
+impl<T: Clone> CowVec<T> {
+    pub fn new() -> CowVec<T> {
+        CowVec {
+            items: Rc::new(Vec::new()),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        Rc::make_mut(&mut self.items).push(item);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn shares_backing_with(&self, other: &CowVec<T>) -> bool {
+        Rc::ptr_eq(&self.items, &other.items)
+    }
+
+    //The address of the backing Vec, which makes it possible to tell whether a push copied it.
+    pub fn backing_ptr(&self) -> *const Vec<T> {
+        Rc::as_ptr(&self.items)
+    }
+}
+
+impl<T: Clone> Default for CowVec<T> {
+    fn default() -> Self {
+        CowVec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(items: &[i32]) -> CowVec<i32> {
+        let mut vec = CowVec::new();
+        for &item in items {
+            vec.push(item);
+        }
+        vec
+    }
+
+    #[test]
+    fn clones_share_the_same_backing() {
+        let original = filled(&[1, 2]);
+        let copy = original.clone();
+        assert!(original.shares_backing_with(&copy));
+        assert_eq!(original.backing_ptr(), copy.backing_ptr());
+    }
+
+    #[test]
+    fn pushing_onto_a_shared_vec_copies_it() {
+        let original = filled(&[1, 2]);
+        let mut copy = original.clone();
+        copy.push(3);
+
+        assert!(!original.shares_backing_with(&copy));
+        assert_eq!((original.len(), original.get(2)), (2, None));
+        assert_eq!((copy.len(), copy.get(2)), (3, Some(&3)));
+        assert_eq!(copy.get(0), Some(&1));
+    }
+
+    #[test]
+    fn pushing_onto_a_sole_owner_changes_it_in_place() {
+        let mut vec = filled(&[1]);
+        let before = vec.backing_ptr();
+        vec.push(2);
+        assert_eq!(vec.backing_ptr(), before);
+
+        //Once the other clone is gone the Vec is not shared any more.
+        let copy = vec.clone();
+        drop(copy);
+        vec.push(3);
+        assert_eq!(vec.backing_ptr(), before);
+        assert_eq!(vec.len(), 3);
+    }
+
+    #[test]
+    fn a_new_vec_is_empty() {
+        let vec: CowVec<i32> = CowVec::default();
+        assert!(vec.is_empty());
+        assert_eq!(vec.get(0), None);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::freeze::FrozenNode;
+
+//Frozen trees can never change, so two subtrees with the same shape and values are
+// interchangeable. This file finds those subtrees by hashing their contents and rebuilds the tree
+// so each distinct subtree is only allocated once.
+
+type NodePtr<T> = *const FrozenNode<T>;
+
+#[derive(Debug, PartialEq)]
+pub struct DedupStats {
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+}
+
+//Every distinct allocation reachable from `root`, children before their parents. A node that is
+// already shared between several parents is only listed once. An explicit stack is used so a very
+// deep tree cannot overflow the call stack.
+fn post_order<T>(root: &Rc<FrozenNode<T>>) -> Vec<Rc<FrozenNode<T>>> {
+    let mut seen: HashSet<NodePtr<T>> = HashSet::new();
+    let mut order = Vec::new();
+    let mut pending = vec![(Rc::clone(root), false)];
+
+    while let Some((node, children_done)) = pending.pop() {
+        if children_done {
+            order.push(node);
+            continue;
+        }
+        if !seen.insert(Rc::as_ptr(&node)) {
+            continue;
+        }
+        pending.push((Rc::clone(&node), true));
+        for child in node.children.iter().rev() {
+            pending.push((Rc::clone(child), false));
+        }
+    }
+
+    order
+}
+
+//A node's hash covers its value and the hashes of its children in order, so it only depends on
+// the contents of the subtree and not on where it was allocated.
+fn hash_node<T: Hash>(value: &T, child_hashes: impl Iterator<Item = u64>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    for child_hash in child_hashes {
+        child_hash.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+//The hashes are worked out bottom up and remembered per node, so a subtree that is shared between
+// several parents is only hashed once.
+pub fn content_hash<T: Hash>(node: &Rc<FrozenNode<T>>) -> u64 {
+    let mut hashes: HashMap<NodePtr<T>, u64> = HashMap::new();
+    for current in post_order(node) {
+        let hash = hash_node(
+            &current.value,
+            current.children.iter().map(|child| hashes[&Rc::as_ptr(child)]),
+        );
+        hashes.insert(Rc::as_ptr(&current), hash);
+    }
+    hashes[&Rc::as_ptr(node)]
+}
+
+//Rebuilds the tree so that equal subtrees share one allocation. Nodes are handled children first,
+// which means by the time a node is looked at its children have already been replaced by their
+// shared copies. Two nodes with the same hash are then only treated as equal if their values are
+// equal and their children are the very same allocations, so a hash collision can never merge two
+// different subtrees.
+pub fn deduplicate<T: Clone + Hash + PartialEq>(
+    root: Rc<FrozenNode<T>>,
+) -> (Rc<FrozenNode<T>>, DedupStats) {
+    deduplicate_with(root, |hash| hash)
+}
+
+//`narrow` is applied to every node hash. deduplicate() leaves the hashes alone, and the tests cut
+// them down to a few bits so that different subtrees are forced to collide.
+fn deduplicate_with<T: Clone + Hash + PartialEq>(
+    root: Rc<FrozenNode<T>>,
+    narrow: impl Fn(u64) -> u64,
+) -> (Rc<FrozenNode<T>>, DedupStats) {
+    let order = post_order(&root);
+    let nodes_before = order.len();
+
+    let mut hashes: HashMap<NodePtr<T>, u64> = HashMap::new();
+    let mut replacements: HashMap<NodePtr<T>, Rc<FrozenNode<T>>> = HashMap::new();
+    let mut by_hash: HashMap<u64, Vec<Rc<FrozenNode<T>>>> = HashMap::new();
+
+    for node in &order {
+        let children: Vec<Rc<FrozenNode<T>>> = node
+            .children
+            .iter()
+            .map(|child| Rc::clone(&replacements[&Rc::as_ptr(child)]))
+            .collect();
+        let hash = narrow(hash_node(
+            &node.value,
+            node.children.iter().map(|child| hashes[&Rc::as_ptr(child)]),
+        ));
+        hashes.insert(Rc::as_ptr(node), hash);
+
+        let candidates = by_hash.entry(hash).or_default();
+        let existing = candidates.iter().find(|candidate| {
+            candidate.value == node.value
+                && candidate.children.len() == children.len()
+                && candidate.children.iter().zip(&children).all(|(a, b)| Rc::ptr_eq(a, b))
+        });
+
+        let replacement = match existing {
+            Some(existing) => Rc::clone(existing),
+            None => {
+                //A node whose children did not change can be kept as it is.
+                let unchanged = node.children.iter().zip(&children).all(|(a, b)| Rc::ptr_eq(a, b));
+                let replacement = if unchanged {
+                    Rc::clone(node)
+                } else {
+                    Rc::new(FrozenNode {
+                        value: node.value.clone(),
+                        children,
+                    })
+                };
+                candidates.push(Rc::clone(&replacement));
+                replacement
+            }
+        };
+        replacements.insert(Rc::as_ptr(node), replacement);
+    }
+
+    let new_root = Rc::clone(&replacements[&Rc::as_ptr(&root)]);
+    let nodes_after = post_order(&new_root).len();
+    (
+        new_root,
+        DedupStats {
+            nodes_before,
+            nodes_after,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc_tree::Node;
+    use crate::tree;
+
+    fn frozen(root: Rc<Node<&'static str>>) -> Rc<FrozenNode<&'static str>> {
+        Node::freeze(&root)
+    }
+
+    fn same_shape<T: PartialEq>(a: &FrozenNode<T>, b: &FrozenNode<T>) -> bool {
+        a.value == b.value
+            && a.children.len() == b.children.len()
+            && a.children.iter().zip(&b.children).all(|(x, y)| same_shape(x, y))
+    }
+
+    fn corpus() -> Vec<Rc<FrozenNode<&'static str>>> {
+        vec![
+            frozen(tree!("a")),
+            frozen(tree!("b")),
+            frozen(tree!("a" => ["b"])),
+            frozen(tree!("b" => ["a"])),
+            frozen(tree!("a" => ["b", "c"])),
+            frozen(tree!("a" => ["c", "b"])),
+            frozen(tree!("a" => ["b" => ["c"]])),
+            frozen(tree!("a" => ["b" => ["c"]])),
+            frozen(tree!("a" => ["b", "c"])),
+        ]
+    }
+
+    #[test]
+    fn equal_hashes_only_for_equal_trees_in_the_corpus() {
+        let corpus = corpus();
+        for a in &corpus {
+            for b in &corpus {
+                assert_eq!(content_hash(a) == content_hash(b), same_shape(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_subtrees_end_up_shared() {
+        let root = frozen(tree!("root" => ["x" => ["y", "z"], "x" => ["y", "z"], "y"]));
+        let (shared, stats) = deduplicate(Rc::clone(&root));
+
+        assert_eq!(stats, DedupStats { nodes_before: 8, nodes_after: 4 });
+        assert!(Rc::ptr_eq(&shared.children[0], &shared.children[1]));
+        assert!(Rc::ptr_eq(&shared.children[0].children[0], &shared.children[2]));
+        assert!(same_shape(&root, &shared));
+    }
+
+    #[test]
+    fn collisions_are_told_apart_by_the_equality_check() {
+        let root = frozen(tree!("root" => ["a" => ["b"], "b" => ["a"], "a" => ["b"], "c", "d"]));
+        //With every hash narrowed to zero, all nodes land in the same bucket.
+        let (shared, stats) = deduplicate_with(Rc::clone(&root), |_| 0);
+
+        assert_eq!(stats, DedupStats { nodes_before: 9, nodes_after: 7 });
+        assert!(same_shape(&root, &shared));
+        assert!(Rc::ptr_eq(&shared.children[0], &shared.children[2]));
+        assert!(!Rc::ptr_eq(&shared.children[0], &shared.children[1]));
+
+        let (two_bits, _) = deduplicate_with(Rc::clone(&root), |hash| hash & 0b11);
+        assert!(same_shape(&root, &two_bits));
+    }
+
+    #[test]
+    fn deduplicating_twice_changes_nothing() {
+        let root = Node::freeze(&tree!(0 => [1 => [2], 1 => [2], 2]));
+        let (once, first) = deduplicate(root);
+        let (twice, second) = deduplicate(Rc::clone(&once));
+
+        assert!(Rc::ptr_eq(&once, &twice));
+        assert_eq!(second.nodes_before, first.nodes_after);
+        assert_eq!(second.nodes_after, first.nodes_after);
+    }
+
+    #[test]
+    fn a_tree_without_repeats_keeps_every_node() {
+        let root = frozen(tree!("a" => ["b", "c" => ["d"]]));
+        let (shared, stats) = deduplicate(Rc::clone(&root));
+        assert!(Rc::ptr_eq(&root, &shared));
+        assert_eq!(stats, DedupStats { nodes_before: 4, nodes_after: 4 });
+    }
+
+    #[test]
+    fn a_fifty_thousand_deep_tree_is_processed_iteratively() {
+        //Every node stays in the Vec, and the Vec is emptied from the root down, so the chain is
+        // freed one node at a time.
+        let mut nodes = vec![Rc::new(FrozenNode { value: 0, children: Vec::new() })];
+        for _ in 1..50_000 {
+            let child = Rc::clone(nodes.last().unwrap());
+            nodes.push(Rc::new(FrozenNode { value: 0, children: vec![child] }));
+        }
+        let root = Rc::clone(nodes.last().unwrap());
+
+        content_hash(&root);
+        let (shared, stats) = deduplicate(Rc::clone(&root));
+        assert!(Rc::ptr_eq(&root, &shared));
+        assert_eq!(stats, DedupStats { nodes_before: 50_000, nodes_after: 50_000 });
+
+        drop((root, shared));
+        while nodes.pop().is_some() {}
+    }
+}
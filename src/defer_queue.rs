@@ -0,0 +1,174 @@
+use std::cell::{Cell, RefCell};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskId(u64);
+
+#[derive(Debug, PartialEq)]
+pub struct RunSummary {
+    pub executed: usize,
+    pub cancelled: usize,
+}
+
+type Task = (TaskId, Box<dyn FnOnce()>);
+
+//A queue of deferred work. Each task is a Box<dyn FnOnce()>, so it can take ownership of whatever
+// it captured and is guaranteed to run at most once.
+pub struct TaskQueue {
+    tasks: RefCell<Vec<Task>>,
+    next_id: Cell<u64>,
+    cancelled: Cell<usize>,
+}
+
+impl TaskQueue {
+    pub fn new() -> TaskQueue {
+        TaskQueue {
+            tasks: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+            cancelled: Cell::new(0),
+        }
+    }
+
+    pub fn push(&self, task: impl FnOnce() + 'static) -> TaskId {
+        let id = TaskId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.tasks.borrow_mut().push((id, Box::new(task)));
+        id
+    }
+
+    //Removes a task that has not run yet. Returns false if the task already ran or was already
+    // cancelled.
+    pub fn cancel(&self, id: TaskId) -> bool {
+        let mut tasks = self.tasks.borrow_mut();
+        match tasks.iter().position(|(task_id, _)| *task_id == id) {
+            Some(index) => {
+                drop(tasks.remove(index));
+                self.cancelled.set(self.cancelled.get() + 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    //Runs every queued task in the order they were pushed. The Vec is taken out of the RefCell
+    // before anything runs, so a task is free to push more tasks. Those new tasks wait for the
+    // next call to run_all().
+    pub fn run_all(&self) -> RunSummary {
+        let tasks = self.tasks.take();
+        let executed = tasks.len();
+        for (_, task) in tasks {
+            task();
+        }
+
+        RunSummary {
+            executed,
+            cancelled: self.cancelled.replace(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.borrow().is_empty()
+    }
+}
+
+impl Default for TaskQueue {
+    fn default() -> Self {
+        TaskQueue::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    type Order = Rc<RefCell<Vec<&'static str>>>;
+
+    fn recording(queue: &TaskQueue, order: &Order, name: &'static str) -> TaskId {
+        let order = Rc::clone(order);
+        queue.push(move || order.borrow_mut().push(name))
+    }
+
+    #[test]
+    fn tasks_run_in_the_order_they_were_pushed() {
+        let queue = TaskQueue::new();
+        let order = Order::default();
+        for name in ["a", "b", "c"] {
+            recording(&queue, &order, name);
+        }
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.run_all(), RunSummary { executed: 3, cancelled: 0 });
+        assert_eq!(*order.borrow(), ["a", "b", "c"]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn cancel_skips_exactly_that_task() {
+        let queue = TaskQueue::new();
+        let order = Order::default();
+        recording(&queue, &order, "a");
+        let b = recording(&queue, &order, "b");
+        recording(&queue, &order, "c");
+
+        assert!(queue.cancel(b));
+        assert!(!queue.cancel(b));
+        assert_eq!(queue.run_all(), RunSummary { executed: 2, cancelled: 1 });
+        assert_eq!(*order.borrow(), ["a", "c"]);
+        assert!(!queue.cancel(b));
+    }
+
+    #[test]
+    fn a_task_pushed_while_running_waits_for_the_next_run() {
+        let queue = Rc::new(TaskQueue::new());
+        let order = Order::default();
+        let handle = Rc::downgrade(&queue);
+        let inner_order = Rc::clone(&order);
+        queue.push(move || {
+            if let Some(queue) = handle.upgrade() {
+                recording(&queue, &inner_order, "pushed");
+            }
+        });
+
+        assert_eq!(queue.run_all().executed, 1);
+        assert!(order.borrow().is_empty());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.run_all().executed, 1);
+        assert_eq!(*order.borrow(), ["pushed"]);
+    }
+
+    #[test]
+    fn running_an_empty_queue_does_nothing() {
+        let queue = TaskQueue::default();
+        assert_eq!(queue.run_all(), RunSummary { executed: 0, cancelled: 0 });
+        assert_eq!(queue.run_all(), RunSummary { executed: 0, cancelled: 0 });
+    }
+
+    #[test]
+    fn a_moved_string_is_used_exactly_once() {
+        let queue = TaskQueue::new();
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&received);
+        let message = String::from("moved");
+        queue.push(move || sink.borrow_mut().push(message));
+
+        queue.run_all();
+        queue.run_all();
+        assert_eq!(*received.borrow(), ["moved"]);
+    }
+
+    #[test]
+    fn the_queue_is_reusable_after_run_all() {
+        let queue = TaskQueue::new();
+        let order = Order::default();
+        recording(&queue, &order, "first");
+        queue.run_all();
+        let id = recording(&queue, &order, "second");
+        assert_eq!(queue.run_all(), RunSummary { executed: 1, cancelled: 0 });
+        assert_eq!(*order.borrow(), ["first", "second"]);
+        assert!(!queue.cancel(id));
+    }
+}
@@ -0,0 +1,77 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+//A double-ended queue shared between several owners, the same Rc<RefCell<T>> pattern as
+// SharedPQueue. Every clone pushes to and pops from the same VecDeque.
+#[derive(Clone)]
+pub struct SharedDeque<T> {
+    items: Rc<RefCell<VecDeque<T>>>,
+}
+
+impl<T> SharedDeque<T> {
+    pub fn new() -> SharedDeque<T> {
+        SharedDeque {
+            items: Rc::new(RefCell::new(VecDeque::new())),
+        }
+    }
+
+    pub fn push_front(&self, item: T) {
+        self.items.borrow_mut().push_front(item);
+    }
+
+    pub fn push_back(&self, item: T) {
+        self.items.borrow_mut().push_back(item);
+    }
+
+    pub fn pop_front(&self) -> Option<T> {
+        self.items.borrow_mut().pop_front()
+    }
+
+    pub fn pop_back(&self) -> Option<T> {
+        self.items.borrow_mut().pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+}
+
+impl<T> Default for SharedDeque<T> {
+    fn default() -> Self {
+        SharedDeque::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_handles_see_the_same_deque() {
+        let front = SharedDeque::new();
+        let back = front.clone();
+        front.push_front(2);
+        back.push_back(3);
+        front.push_front(1);
+        assert_eq!((front.len(), back.len()), (3, 3));
+
+        assert_eq!(back.pop_front(), Some(1));
+        assert_eq!(front.pop_back(), Some(3));
+        assert_eq!(back.len(), 1);
+        assert_eq!(front.pop_front(), Some(2));
+        assert!(back.is_empty());
+    }
+
+    #[test]
+    fn popping_an_empty_deque_gives_none() {
+        let deque: SharedDeque<i32> = SharedDeque::default();
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+        assert_eq!(deque.len(), 0);
+    }
+}
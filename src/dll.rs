@@ -0,0 +1,614 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+type Link<T> = Option<Rc<DllNode<T>>>;
+
+//Each node owns the node after it through an Rc and only points back at the node before it
+// through a Weak. If both directions were Rc, every pair of neighbours would be a reference cycle
+// and no node would ever be freed.
+struct DllNode<T> {
+    value: T,
+    next: RefCell<Link<T>>,
+    prev: RefCell<Weak<DllNode<T>>>,
+}
+
+//The list owns the first node, which owns the rest of the chain. The tail is only a Weak pointer
+// since the node before it already owns it. The length is kept up to date by every operation
+// instead of being counted, so split_after() has to work out the length of both halves itself.
+pub struct DoublyLinkedList<T> {
+    head: Link<T>,
+    tail: Weak<DllNode<T>>,
+    len: usize,
+}
+
+//A handle to one node that can be kept after the list is gone. It only ever hands out Options for
+// the neighbours, since they may have been unlinked or freed by then.
+pub struct NodeRef<T>(Rc<DllNode<T>>);
+
+//A position in a list that can also cut the list in two. Besides the nodes it has an extra "ghost"
+// position between the tail and the head, where `current` is None. An empty list only has the
+// ghost position. The index of the current node is tracked so a split knows both new lengths
+// without walking either half.
+pub struct CursorMut<'a, T> {
+    list: &'a mut DoublyLinkedList<T>,
+    current: Link<T>,
+    index: usize,
+}
+
+impl<T> DoublyLinkedList<T> {
+    pub fn new() -> DoublyLinkedList<T> {
+        DoublyLinkedList {
+            head: None,
+            tail: Weak::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn front_node(&self) -> Option<NodeRef<T>> {
+        self.head.clone().map(NodeRef)
+    }
+
+    pub fn back_node(&self) -> Option<NodeRef<T>> {
+        self.tail.upgrade().map(NodeRef)
+    }
+
+    //A cursor on the head, or on the ghost position if the list is empty.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        let current = self.head.clone();
+        CursorMut {
+            list: self,
+            current,
+            index: 0,
+        }
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        let node = Rc::new(DllNode {
+            value,
+            next: RefCell::new(None),
+            prev: RefCell::new(self.tail.clone()),
+        });
+        let new_tail = Rc::downgrade(&node);
+        match self.tail.upgrade() {
+            Some(old_tail) => *old_tail.next.borrow_mut() = Some(node),
+            None => self.head = Some(node),
+        }
+        self.tail = new_tail;
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        let node = Rc::new(DllNode {
+            value,
+            next: RefCell::new(self.head.take()),
+            prev: RefCell::new(Weak::new()),
+        });
+        match node.next.borrow().as_ref() {
+            Some(old_head) => *old_head.prev.borrow_mut() = Rc::downgrade(&node),
+            None => self.tail = Rc::downgrade(&node),
+        }
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    //Moves every node of `other` onto the end of this list. Only the two nodes where the lists meet
+    // are relinked, so this takes the same time however long either list is. `other` is taken by
+    // value, which is why a list can never be appended to itself.
+    /// ```compile_fail,E0505
+    /// use rust_book_chapter_15::dll::DoublyLinkedList;
+    ///
+    /// let mut list = DoublyLinkedList::new();
+    /// list.push_back(1);
+    /// list.append(list);
+    /// ```
+    pub fn append(&mut self, mut other: DoublyLinkedList<T>) {
+        let Some(other_head) = other.head.take() else {
+            return;
+        };
+        match self.tail.upgrade() {
+            Some(tail) => {
+                *other_head.prev.borrow_mut() = Rc::downgrade(&tail);
+                *tail.next.borrow_mut() = Some(other_head);
+            }
+            None => self.head = Some(other_head),
+        }
+        self.tail = std::mem::take(&mut other.tail);
+        self.len += std::mem::take(&mut other.len);
+    }
+
+    //Walks the list checking every link it has: each prev points at the node before it, the tail is
+    // the last node, and the number of nodes matches the stored length.
+    pub fn is_consistent(&self) -> bool {
+        let mut count = 0;
+        let mut previous: Option<Rc<DllNode<T>>> = None;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let prev = node.prev.borrow().upgrade();
+            let prev_matches = match (&prev, &previous) {
+                (Some(prev), Some(previous)) => Rc::ptr_eq(prev, previous),
+                (None, None) => true,
+                _ => false,
+            };
+            if !prev_matches {
+                return false;
+            }
+            count += 1;
+            current = node.next.borrow().clone();
+            previous = Some(node);
+        }
+        let tail_matches = match (self.tail.upgrade(), &previous) {
+            (Some(tail), Some(last)) => Rc::ptr_eq(&tail, last),
+            (None, None) => true,
+            _ => false,
+        };
+        tail_matches && count == self.len
+    }
+
+    //Reverses the list by relinking the nodes it already has. Each node's strong next pointer is
+    // taken out before anything else is changed, which keeps the rest of the chain alive. The
+    // node then gets the already reversed part as its next and a Weak to its old next as its prev,
+    // so the strong pointers still only ever run from the head towards the tail.
+    pub fn reverse(&mut self) {
+        let old_head = self.head.take();
+        let new_tail = old_head.as_ref().map(Rc::downgrade).unwrap_or_default();
+
+        let mut reversed: Link<T> = None;
+        let mut current = old_head;
+        while let Some(node) = current {
+            let next = node.next.borrow_mut().take();
+            *node.prev.borrow_mut() = next.as_ref().map(Rc::downgrade).unwrap_or_default();
+            *node.next.borrow_mut() = reversed;
+            reversed = Some(node);
+            current = next;
+        }
+
+        self.head = reversed;
+        self.tail = new_tail;
+    }
+
+    //Walks from the head following the strong next pointers.
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            f(&node.value);
+            current = node.next.borrow().clone();
+        }
+    }
+
+    //Walks from the tail following the Weak prev pointers. Every upgrade succeeds because the
+    // nodes are all still owned through the head.
+    pub fn for_each_backward(&self, mut f: impl FnMut(&T)) {
+        let mut current = self.tail.upgrade();
+        while let Some(node) = current {
+            f(&node.value);
+            current = node.prev.borrow().upgrade();
+        }
+    }
+}
+
+impl<T> NodeRef<T> {
+    pub fn value(&self) -> &T {
+        &self.0.value
+    }
+
+    pub fn next(&self) -> Option<NodeRef<T>> {
+        self.0.next.borrow().clone().map(NodeRef)
+    }
+
+    pub fn prev(&self) -> Option<NodeRef<T>> {
+        self.0.prev.borrow().upgrade().map(NodeRef)
+    }
+}
+
+impl<T> Clone for NodeRef<T> {
+    fn clone(&self) -> Self {
+        NodeRef(Rc::clone(&self.0))
+    }
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    pub fn current(&self) -> Option<&T> {
+        self.current.as_ref().map(|node| &node.value)
+    }
+
+    pub fn current_node(&self) -> Option<NodeRef<T>> {
+        self.current.clone().map(NodeRef)
+    }
+
+    //None on the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.current.as_ref().map(|_| self.index)
+    }
+
+    //From the tail this goes to the ghost position, and from the ghost position to the head.
+    pub fn move_next(&mut self) {
+        match self.current.take() {
+            Some(node) => {
+                self.current = node.next.borrow().clone();
+                self.index += 1;
+            }
+            None => {
+                self.current = self.list.head.clone();
+                self.index = 0;
+            }
+        }
+    }
+
+    //Cuts the list after the current node and returns everything that came after it as a list of
+    // its own. The new head gets an empty prev, and the current node becomes the tail of the list
+    // the cursor is still in. On the ghost position the whole list is split off.
+    pub fn split_after(&mut self) -> DoublyLinkedList<T> {
+        let Some(node) = &self.current else {
+            return std::mem::take(self.list);
+        };
+        let kept = self.index + 1;
+        let Some(rest_head) = node.next.borrow_mut().take() else {
+            return DoublyLinkedList::new();
+        };
+        *rest_head.prev.borrow_mut() = Weak::new();
+
+        let rest = DoublyLinkedList {
+            head: Some(rest_head),
+            tail: std::mem::replace(&mut self.list.tail, Rc::downgrade(node)),
+            len: self.list.len - kept,
+        };
+        self.list.len = kept;
+        rest
+    }
+}
+
+impl<T> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
+        DoublyLinkedList::new()
+    }
+}
+
+//Same idea as the Drop for Stack: unlink the nodes one at a time so that dropping a long list does
+// not recurse once per node. The prev pointers are cleared as well. A node kept alive through a
+// NodeRef then ends up on its own, instead of still pointing back at a neighbour that is also
+// being kept alive but no longer points forward to it.
+impl<T> Drop for DoublyLinkedList<T> {
+    fn drop(&mut self) {
+        let mut current = self.head.take();
+        while let Some(node) = current {
+            *node.prev.borrow_mut() = Weak::new();
+            current = node.next.borrow_mut().take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drop_log::{DropLog, Logged};
+
+    fn forward<T: Clone>(list: &DoublyLinkedList<T>) -> Vec<T> {
+        let mut values = Vec::new();
+        list.for_each(|value| values.push(value.clone()));
+        values
+    }
+
+    fn backward<T: Clone>(list: &DoublyLinkedList<T>) -> Vec<T> {
+        let mut values = Vec::new();
+        list.for_each_backward(|value| values.push(value.clone()));
+        values
+    }
+
+    #[test]
+    fn reverse_flips_both_directions() {
+        let mut list = DoublyLinkedList::new();
+        for value in 1..=4 {
+            list.push_back(value);
+        }
+        list.reverse();
+        assert_eq!(forward(&list), [4, 3, 2, 1]);
+        assert_eq!(backward(&list), [1, 2, 3, 4]);
+
+        //Pushing at both ends still works on the relinked nodes.
+        list.push_front(5);
+        list.push_back(0);
+        assert_eq!(forward(&list), [5, 4, 3, 2, 1, 0]);
+        assert_eq!(backward(&list), [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn reversing_an_empty_or_single_list_changes_nothing() {
+        let mut empty: DoublyLinkedList<i32> = DoublyLinkedList::default();
+        empty.reverse();
+        assert!(forward(&empty).is_empty());
+        assert!(backward(&empty).is_empty());
+
+        let mut single = DoublyLinkedList::new();
+        single.push_back(1);
+        single.reverse();
+        assert_eq!((forward(&single), backward(&single)), (vec![1], vec![1]));
+    }
+
+    #[test]
+    fn a_reversed_list_still_frees_every_node() {
+        let tracker = Rc::new(());
+        let mut list = DoublyLinkedList::new();
+        for _ in 0..5 {
+            list.push_back(Rc::clone(&tracker));
+        }
+        list.reverse();
+        list.reverse();
+        list.reverse();
+        assert_eq!(Rc::strong_count(&tracker), 6);
+        drop(list);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn a_long_reversed_list_drops_without_overflowing() {
+        let mut list = DoublyLinkedList::new();
+        for value in 0..100_000 {
+            list.push_back(value);
+        }
+        list.reverse();
+        let mut first = None;
+        list.for_each(|&value| {
+            first.get_or_insert(value);
+        });
+        assert_eq!(first, Some(99_999));
+    }
+
+    fn filled(values: std::ops::RangeInclusive<i32>) -> DoublyLinkedList<i32> {
+        let mut list = DoublyLinkedList::new();
+        for value in values {
+            list.push_back(value);
+        }
+        list
+    }
+
+    //Splits after the node at `index`, or on the ghost position when that is None.
+    fn split_at(list: &mut DoublyLinkedList<i32>, index: Option<usize>) -> DoublyLinkedList<i32> {
+        let mut cursor = list.cursor_mut();
+        match index {
+            Some(index) => {
+                for _ in 0..index {
+                    cursor.move_next();
+                }
+            }
+            None => {
+                while cursor.index().is_some() {
+                    cursor.move_next();
+                }
+            }
+        }
+        cursor.split_after()
+    }
+
+    #[test]
+    fn split_in_the_middle_gives_two_working_lists() {
+        let mut list = filled(1..=6);
+        let rest = split_at(&mut list, Some(2));
+        assert_eq!((forward(&list), backward(&list)), (vec![1, 2, 3], vec![3, 2, 1]));
+        assert_eq!((forward(&rest), backward(&rest)), (vec![4, 5, 6], vec![6, 5, 4]));
+        assert_eq!((list.len(), rest.len()), (3, 3));
+        assert!(list.is_consistent() && rest.is_consistent());
+    }
+
+    #[test]
+    fn split_at_the_head_and_at_the_tail() {
+        let mut list = filled(1..=4);
+        let rest = split_at(&mut list, Some(0));
+        assert_eq!((forward(&list), forward(&rest)), (vec![1], vec![2, 3, 4]));
+        assert_eq!((list.len(), rest.len()), (1, 3));
+        assert!(list.is_consistent() && rest.is_consistent());
+
+        let mut list = filled(1..=4);
+        let rest = split_at(&mut list, Some(3));
+        assert_eq!(forward(&list), [1, 2, 3, 4]);
+        assert!(rest.is_empty());
+        assert!(list.is_consistent() && rest.is_consistent());
+    }
+
+    #[test]
+    fn split_on_the_ghost_position_takes_the_whole_list() {
+        let mut empty: DoublyLinkedList<i32> = DoublyLinkedList::new();
+        let rest = split_at(&mut empty, None);
+        assert!(empty.is_empty() && rest.is_empty());
+        assert!(empty.is_consistent() && rest.is_consistent());
+
+        let mut list = filled(1..=3);
+        let rest = split_at(&mut list, None);
+        assert!(list.is_empty());
+        assert_eq!((forward(&rest), rest.len()), (vec![1, 2, 3], 3));
+        assert!(list.is_consistent() && rest.is_consistent());
+    }
+
+    #[test]
+    fn the_cursor_wraps_through_the_ghost_position() {
+        let mut list = filled(1..=2);
+        let mut cursor = list.cursor_mut();
+        assert_eq!((cursor.current(), cursor.index()), (Some(&1), Some(0)));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!((cursor.current(), cursor.index()), (None, None));
+        cursor.move_next();
+        assert_eq!((cursor.current(), cursor.index()), (Some(&1), Some(0)));
+    }
+
+    #[test]
+    fn append_joins_two_lists_and_adds_their_lengths() {
+        let mut list = filled(1..=3);
+        list.append(filled(4..=5));
+        assert_eq!((forward(&list), backward(&list)), (vec![1, 2, 3, 4, 5], vec![5, 4, 3, 2, 1]));
+        assert_eq!(list.len(), 5);
+        assert!(list.is_consistent());
+
+        list.append(DoublyLinkedList::new());
+        assert_eq!(list.len(), 5);
+
+        let mut empty = DoublyLinkedList::new();
+        empty.append(list);
+        assert_eq!((forward(&empty), empty.len()), (vec![1, 2, 3, 4, 5], 5));
+        assert!(empty.is_consistent());
+    }
+
+    #[test]
+    fn splitting_and_appending_back_swapped_loses_and_duplicates_nothing() {
+        let tracker = Rc::new(());
+        let mut list = DoublyLinkedList::new();
+        for _ in 0..6 {
+            list.push_back(Rc::clone(&tracker));
+        }
+        //The cursor holds an Rc to the node it is on, so it has to be gone before the final count.
+        let mut second_half = {
+            let mut cursor = list.cursor_mut();
+            cursor.move_next();
+            cursor.move_next();
+            cursor.split_after()
+        };
+        assert_eq!(Rc::strong_count(&tracker), 7);
+
+        second_half.append(list);
+        assert_eq!((second_half.len(), Rc::strong_count(&tracker)), (6, 7));
+        assert!(second_half.is_consistent());
+        drop(second_half);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn push_and_reverse_keep_the_list_consistent() {
+        let mut list = filled(2..=4);
+        list.push_front(1);
+        list.reverse();
+        assert_eq!(list.len(), 4);
+        assert!(list.is_consistent());
+    }
+
+    //Teardown: each of these keeps a NodeRef to a node of a list, drops the list, and then uses the
+    // node. None of them may panic, and every neighbour accessor has to answer with a plain None.
+    fn node_at<T>(list: &DoublyLinkedList<T>, index: usize) -> NodeRef<T> {
+        let mut node = list.front_node().unwrap();
+        for _ in 0..index {
+            node = node.next().unwrap();
+        }
+        node
+    }
+
+    #[test]
+    fn teardown_a_held_middle_node_can_still_be_read() {
+        let list = filled(1..=5);
+        let middle = node_at(&list, 2);
+        drop(list);
+        assert_eq!(*middle.value(), 3);
+    }
+
+    #[test]
+    fn teardown_a_held_node_has_no_next() {
+        let list = filled(1..=5);
+        let middle = node_at(&list, 2);
+        drop(list);
+        assert!(middle.next().is_none());
+    }
+
+    #[test]
+    fn teardown_a_held_node_whose_neighbour_was_freed_has_no_prev() {
+        let list = filled(1..=5);
+        let middle = node_at(&list, 2);
+        drop(list);
+        assert!(middle.prev().is_none());
+    }
+
+    //Before Drop cleared the prev pointers, the second node here still upgraded its prev to the
+    // first one, which had already lost its next. Walking back and then forward again did not
+    // return to where it started.
+    #[test]
+    fn teardown_two_held_neighbours_are_fully_detached() {
+        let list = filled(1..=4);
+        let first = node_at(&list, 1);
+        let second = node_at(&list, 2);
+        drop(list);
+        assert!(second.prev().is_none());
+        assert!(first.next().is_none());
+        assert_eq!((*first.value(), *second.value()), (2, 3));
+    }
+
+    #[test]
+    fn teardown_walking_back_from_a_held_tail_ends_right_away() {
+        let list = filled(1..=3);
+        let tail = list.back_node().unwrap();
+        drop(list);
+        let mut walked = vec![*tail.value()];
+        let mut current = tail.prev();
+        while let Some(node) = current {
+            walked.push(*node.value());
+            current = node.prev();
+        }
+        assert_eq!(walked, [3]);
+    }
+
+    #[test]
+    fn teardown_holding_one_node_frees_every_other_node() {
+        let log = DropLog::new();
+        let mut list = DoublyLinkedList::new();
+        for name in ["a", "b", "c", "d"] {
+            list.push_back(Logged::new(&log, name));
+        }
+        let c = node_at(&list, 2);
+        drop(list);
+        assert_eq!(log.events(), ["drop:a", "drop:b", "drop:d"]);
+        assert_eq!(c.value().name, "c");
+        drop(c);
+        assert_eq!(log.events(), ["drop:a", "drop:b", "drop:d", "drop:c"]);
+    }
+
+    #[test]
+    fn teardown_a_held_node_of_a_reversed_list_is_detached() {
+        let mut list = filled(1..=4);
+        let second = node_at(&list, 1);
+        list.reverse();
+        assert_eq!(second.prev().map(|node| *node.value()), Some(3));
+        drop(list);
+        assert!(second.prev().is_none() && second.next().is_none());
+        assert_eq!(*second.value(), 2);
+    }
+
+    #[test]
+    fn teardown_a_node_split_off_survives_the_list_it_came_from() {
+        let mut list = filled(1..=6);
+        let mut rest = split_at(&mut list, Some(2));
+        let fourth = rest.front_node().unwrap();
+        drop(list);
+
+        //The split-off list is still whole, and its head knows it has nothing before it.
+        assert!(fourth.prev().is_none());
+        assert_eq!(fourth.next().map(|node| *node.value()), Some(5));
+        rest.push_front(0);
+        assert_eq!(fourth.prev().map(|node| *node.value()), Some(0));
+        drop(rest);
+        assert!(fourth.prev().is_none() && fourth.next().is_none());
+    }
+
+    #[test]
+    fn teardown_a_cursor_node_outlives_an_append_and_a_drop() {
+        let mut list = filled(1..=3);
+        let held = list.cursor_mut().current_node().unwrap();
+        let mut other = filled(4..=5);
+        other.append(list);
+        assert_eq!(held.prev().map(|node| *node.value()), Some(5));
+        assert!(other.is_consistent());
+        drop(other);
+        assert!(held.prev().is_none() && held.next().is_none());
+        assert_eq!(*held.value(), 1);
+    }
+
+    #[test]
+    fn teardown_an_empty_list_hands_out_no_nodes() {
+        let mut list: DoublyLinkedList<i32> = DoublyLinkedList::new();
+        assert!(list.front_node().is_none() && list.back_node().is_none());
+        assert!(list.cursor_mut().current_node().is_none());
+        drop(list);
+    }
+}
@@ -0,0 +1,237 @@
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::rc::{Rc, Weak};
+use std::thread;
+
+use crate::compare::compile_vs_runtime::catch_unwind_quietly;
+use crate::drop_log::{DropLog, Logged};
+
+//Like temporaries.rs, every scenario here returns a DropLog so the order things happened in can
+// be printed or checked afterwards.
+
+//A struct that owns another struct with its own Drop. The outer drop() runs first, while the
+// inner value is still fully usable, and only after it returns are the fields dropped.
+struct Outer {
+    inner: Logged,
+    log: DropLog,
+}
+
+impl Drop for Outer {
+    fn drop(&mut self) {
+        self.log.record(format!("drop:outer (inner {} still alive)", self.inner.name));
+    }
+}
+
+pub fn nested_drop() -> DropLog {
+    let log = DropLog::new();
+    {
+        let _outer = Outer {
+            inner: Logged::new(&log, "inner"),
+            log: log.clone(),
+        };
+        log.record("leaving block");
+    }
+    log
+}
+
+//A value whose drop() panics. Panicking inside drop() while the thread is already unwinding from
+// another panic aborts the whole process, so the guarded version checks thread::panicking() first
+// and skips the panic in that case.
+struct PanicsOnDrop {
+    guarded: bool,
+    log: DropLog,
+}
+
+impl Drop for PanicsOnDrop {
+    fn drop(&mut self) {
+        if self.guarded && thread::panicking() {
+            self.log.record("drop: already unwinding, not panicking again");
+            return;
+        }
+        self.log.record("drop: panicking");
+        panic!("panic inside drop");
+    }
+}
+
+//The original panic unwinds through the guarded value, which notices and stays quiet. The
+// expected panic is caught without being printed to stderr.
+pub fn guarded_panic_while_unwinding() -> DropLog {
+    let log = DropLog::new();
+    let result = catch_unwind_quietly(|| {
+        let _value = PanicsOnDrop {
+            guarded: true,
+            log: log.clone(),
+        };
+        panic!("original panic");
+    });
+
+    log.record(format!("caught original panic: {}", result.is_err()));
+    log
+}
+
+//The unguarded version. This aborts the process, so it only runs when the binary is started with the
+// hidden --crash-demo flag.
+pub fn crash_demo() {
+    let _value = PanicsOnDrop {
+        guarded: false,
+        log: DropLog::new(),
+    };
+    panic!("original panic");
+}
+
+//Starts the given binary with --crash-demo and waits for it. The whole output comes back so the
+// caller can look at both the exit status and what the child printed before it aborted.
+pub fn run_crash_demo_in_subprocess(program: &Path) -> io::Result<Output> {
+    Command::new(program).arg("--crash-demo").output()
+}
+
+//drop() only gets &mut self, so a field cannot be moved out of directly. Wrapping it in an Option
+// lets drop() take() it and hand it to something that wants it by value.
+struct Consumer {
+    pending: Option<Logged>,
+    log: DropLog,
+}
+
+fn consume(value: Logged, log: &DropLog) {
+    log.record(format!("consumed:{}", value.name));
+}
+
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        if let Some(value) = self.pending.take() {
+            consume(value, &self.log);
+        }
+        self.log.record("drop:consumer done");
+    }
+}
+
+pub fn take_field_in_drop() -> DropLog {
+    let log = DropLog::new();
+    {
+        let _consumer = Consumer {
+            pending: Some(Logged::new(&log, "pending")),
+            log: log.clone(),
+        };
+    }
+    log
+}
+
+//A member removes its own name from the team it belongs to when it is dropped. That means drop()
+// touches the same RefCell that may already be borrowed by whoever is dropping the member.
+//The team owns its members and each member points back at the team, so the back-pointer is a Weak.
+// With an Rc there the two would keep each other alive and the whole team would leak.
+struct Team {
+    names: Vec<&'static str>,
+    members: Vec<Member>,
+}
+
+struct Member {
+    name: &'static str,
+    team: Weak<RefCell<Team>>,
+    log: DropLog,
+}
+
+impl Member {
+    fn join(team: &Rc<RefCell<Team>>, name: &'static str, log: &DropLog) {
+        let member = Member {
+            name,
+            team: Rc::downgrade(team),
+            log: log.clone(),
+        };
+        let mut team = team.borrow_mut();
+        team.names.push(name);
+        team.members.push(member);
+    }
+}
+
+//Using borrow_mut() here would panic whenever the team is already borrowed, and a panic in drop()
+// is exactly what should be avoided. try_borrow_mut() lets the member record that it could not
+// unregister instead. When the team itself is being dropped the upgrade fails, because its strong
+// count already reached zero, and there is nothing left to unregister from.
+impl Drop for Member {
+    fn drop(&mut self) {
+        let Some(team) = self.team.upgrade() else {
+            self.log.record(format!("team gone, {} dropped with it", self.name));
+            return;
+        };
+        match team.try_borrow_mut() {
+            Ok(mut team) => {
+                team.names.retain(|name| *name != self.name);
+                self.log.record(format!("unregistered:{}", self.name));
+            }
+            Err(_) => self.log.record(format!("team busy, {} still listed", self.name)),
+        };
+    }
+}
+
+pub fn reentrant_drop() -> DropLog {
+    let log = DropLog::new();
+    let team = Rc::new(RefCell::new(Team {
+        names: Vec::new(),
+        members: Vec::new(),
+    }));
+    Member::join(&team, "a", &log);
+    Member::join(&team, "b", &log);
+    Member::join(&team, "c", &log);
+
+    //Binding the popped member first ends the borrow before the member is dropped.
+    let leaving = team.borrow_mut().members.pop();
+    drop(leaving);
+
+    //Here the popped member is a temporary created after the RefMut, so it is dropped first while
+    // the team is still mutably borrowed.
+    team.borrow_mut().members.pop();
+
+    log.record(format!("names left:{:?}", team.borrow().names));
+
+    //"a" is still a member, so dropping the team drops it too. Nothing else holds a strong count.
+    let watcher = Rc::downgrade(&team);
+    drop(team);
+    log.record(format!("team freed:{}", watcher.upgrade().is_none()));
+    log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_drop_runs_the_outer_drop_before_its_fields() {
+        assert_eq!(
+            nested_drop().events(),
+            ["leaving block", "drop:outer (inner inner still alive)", "drop:inner"]
+        );
+    }
+
+    #[test]
+    fn guarded_drop_does_not_panic_while_unwinding() {
+        assert_eq!(
+            guarded_panic_while_unwinding().events(),
+            ["drop: already unwinding, not panicking again", "caught original panic: true"]
+        );
+    }
+
+    #[test]
+    fn take_moves_a_field_out_in_drop() {
+        assert_eq!(
+            take_field_in_drop().events(),
+            ["consumed:pending", "drop:pending", "drop:consumer done"]
+        );
+    }
+
+    #[test]
+    fn reentrant_drop_backs_off_and_frees_the_team() {
+        assert_eq!(
+            reentrant_drop().events(),
+            [
+                "unregistered:c",
+                "team busy, b still listed",
+                "names left:[\"a\", \"b\"]",
+                "team gone, a dropped with it",
+                "team freed:true",
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+//A shared list of events. Clones of a DropLog all record into the same list, so values can log
+// their own drops while the code that created them logs what it was doing at the time.
+#[derive(Clone, Default)]
+pub struct DropLog {
+    events: Rc<RefCell<Vec<String>>>,
+}
+
+impl DropLog {
+    pub fn new() -> DropLog {
+        DropLog::default()
+    }
+
+    pub fn record(&self, event: impl Into<String>) {
+        self.events.borrow_mut().push(event.into());
+    }
+
+    pub fn events(&self) -> Vec<String> {
+        self.events.borrow().clone()
+    }
+
+    //Records `enter:<name>` right away and `exit:<name>` when the returned guard is dropped. Since
+    // the guard is an ordinary value, the scope ends wherever the guard ends up being dropped.
+    pub fn scope(&self, name: &str) -> ScopeGuard {
+        self.record(format!("enter:{}", name));
+        ScopeGuard {
+            name: name.to_string(),
+            log: self.clone(),
+        }
+    }
+
+    //Renders the log as a tree with everything recorded inside a scope indented below it. A scope
+    // whose guard never dropped (for example because of mem::forget) is marked as unclosed.
+    pub fn render_tree(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        //The name of each open scope together with the index of the line that opened it.
+        let mut open: Vec<(String, usize)> = Vec::new();
+
+        for event in self.events.borrow().iter() {
+            if let Some(name) = event.strip_prefix("enter:") {
+                lines.push(format!("{}scope {}", "  ".repeat(open.len()), name));
+                open.push((name.to_string(), lines.len() - 1));
+            } else if let Some(name) = event.strip_prefix("exit:") {
+                //Any scope opened after this one that has not exited yet was never closed.
+                while let Some((open_name, line)) = open.pop() {
+                    if open_name == name {
+                        break;
+                    }
+                    lines[line].push_str(" (unclosed)");
+                }
+            } else {
+                lines.push(format!("{}{}", "  ".repeat(open.len()), event));
+            }
+        }
+
+        for (_, line) in open {
+            lines[line].push_str(" (unclosed)");
+        }
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+pub struct ScopeGuard {
+    name: String,
+    log: DropLog,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        self.log.record(format!("exit:{}", self.name));
+    }
+}
+
+//A value that writes `drop:<name>` into its DropLog when it is dropped.
+pub struct Logged {
+    pub name: &'static str,
+    log: DropLog,
+}
+
+impl Logged {
+    pub fn new(log: &DropLog, name: &'static str) -> Logged {
+        Logged {
+            name,
+            log: log.clone(),
+        }
+    }
+
+    //Borrows from self, so whatever this is called on has to stay alive while the result is used.
+    pub fn peek(&self) -> Option<&str> {
+        Some(self.name)
+    }
+
+    pub fn and(&self, other: &Logged) -> String {
+        format!("{}{}", self.name, other.name)
+    }
+}
+
+impl Drop for Logged {
+    fn drop(&mut self) {
+        self.log.record(format!("drop:{}", self.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_scope(log: &DropLog, name: &str) -> ScopeGuard {
+        log.scope(name)
+    }
+
+    #[test]
+    fn three_nested_scopes_exit_in_reverse_order() {
+        let log = DropLog::new();
+        {
+            let _outer = log.scope("outer");
+            let _middle = log.scope("middle");
+            let _inner = log.scope("inner");
+        }
+        assert_eq!(
+            log.events(),
+            ["enter:outer", "enter:middle", "enter:inner", "exit:inner", "exit:middle", "exit:outer"]
+        );
+    }
+
+    #[test]
+    fn drops_are_recorded_inside_the_scope_they_happened_in() {
+        let log = DropLog::new();
+        let _before = Logged::new(&log, "outside");
+        {
+            let _scope = log.scope("block");
+            let _first = Logged::new(&log, "first");
+            let _second = Logged::new(&log, "second");
+        }
+        assert_eq!(log.events(), ["enter:block", "drop:second", "drop:first", "exit:block"]);
+    }
+
+    #[test]
+    fn a_moved_guard_closes_its_scope_where_it_is_dropped() {
+        let log = DropLog::new();
+        let guard = open_scope(&log, "moved");
+        log.record("after the function returned");
+        drop(guard);
+        assert_eq!(log.events(), ["enter:moved", "after the function returned", "exit:moved"]);
+    }
+
+    #[test]
+    fn render_tree_indents_by_scope() {
+        let log = DropLog::new();
+        {
+            let _outer = log.scope("outer");
+            let _value = Logged::new(&log, "a");
+            {
+                let _inner = log.scope("inner");
+                log.record("working");
+            }
+        }
+        assert_eq!(log.render_tree(), "scope outer\n  scope inner\n    working\n  drop:a\n");
+    }
+
+    #[test]
+    fn a_forgotten_guard_is_rendered_as_unclosed() {
+        let log = DropLog::new();
+        {
+            let _outer = log.scope("outer");
+            std::mem::forget(log.scope("leaked"));
+            log.record("still inside");
+        }
+        assert_eq!(log.render_tree(), "scope outer\n  scope leaked (unclosed)\n    still inside\n");
+
+        let open = DropLog::new();
+        std::mem::forget(open.scope("never closed"));
+        assert_eq!(open.render_tree(), "scope never closed (unclosed)\n");
+    }
+}
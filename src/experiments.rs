@@ -0,0 +1,332 @@
+use std::cell::{Cell, RefCell};
+use std::mem::size_of;
+use std::rc::{Rc, Weak};
+
+//Runs the same parent and child workload against three designs and records how many objects are
+// still alive after every round. Create a parent, attach a few children that point back at it, drop
+// the parent, repeat. With strong back pointers every round leaks, with Weak back pointers or plain
+// indices into an arena nothing is left over.
+
+pub const CHILDREN_PER_PARENT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DesignFlavor {
+    StrongCycle,
+    WeakBackRef,
+    ArenaIndices,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ChurnReport {
+    pub flavor: DesignFlavor,
+    //Sampled after each round, once the parent has been dropped.
+    pub live_objects: Vec<usize>,
+    pub live_bytes: Vec<usize>,
+    pub children_processed: usize,
+}
+
+//Counts objects that have been created and not yet dropped, together with their size. It works
+// like the CloseCounter in fd.rs, clones share the same counts.
+#[derive(Clone, Default)]
+struct LiveCount {
+    objects: Rc<Cell<usize>>,
+    bytes: Rc<Cell<usize>>,
+}
+
+//Each object in the workload carries one of these. Creating it adds to the LiveCount and dropping
+// it takes the same amount back off, so a leaked object stays counted.
+struct Tracked {
+    live: LiveCount,
+    bytes: usize,
+}
+
+impl Tracked {
+    fn new(live: &LiveCount, bytes: usize) -> Tracked {
+        live.objects.set(live.objects.get() + 1);
+        live.bytes.set(live.bytes.get() + bytes);
+        Tracked {
+            live: live.clone(),
+            bytes,
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.live.objects.set(self.live.objects.get() - 1);
+        self.live.bytes.set(self.live.bytes.get() - self.bytes);
+    }
+}
+
+//The one workload every design has to support. run_workload() is the only code that decides what
+// happens in a round, so no design can do less work than the others.
+trait Design {
+    type Parent;
+
+    fn new_parent(&mut self, live: &LiveCount) -> Self::Parent;
+    fn attach_child(&mut self, parent: &Self::Parent, live: &LiveCount);
+    fn child_count(&self, parent: &Self::Parent) -> usize;
+    fn release(&mut self, parent: Self::Parent);
+}
+
+fn run_workload<D: Design>(mut design: D, flavor: DesignFlavor, iterations: usize) -> ChurnReport {
+    let live = LiveCount::default();
+    let mut report = ChurnReport {
+        flavor,
+        live_objects: Vec::with_capacity(iterations),
+        live_bytes: Vec::with_capacity(iterations),
+        children_processed: 0,
+    };
+
+    for _ in 0..iterations {
+        let parent = design.new_parent(&live);
+        for _ in 0..CHILDREN_PER_PARENT {
+            design.attach_child(&parent, &live);
+        }
+        report.children_processed += design.child_count(&parent);
+        design.release(parent);
+
+        report.live_objects.push(live.objects.get());
+        report.live_bytes.push(live.bytes.get());
+    }
+
+    report
+}
+
+pub fn churn(iterations: usize, flavor: DesignFlavor) -> ChurnReport {
+    match flavor {
+        DesignFlavor::StrongCycle => run_workload(StrongCycle, flavor, iterations),
+        DesignFlavor::WeakBackRef => run_workload(WeakBackRef, flavor, iterations),
+        DesignFlavor::ArenaIndices => run_workload(Arena::default(), flavor, iterations),
+    }
+}
+
+impl ChurnReport {
+    //One bar per round, scaled to the largest live count in the report.
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = self.live_objects.iter().copied().max().unwrap_or(0).max(1);
+        self.live_objects
+            .iter()
+            .map(|&live| BARS[live * (BARS.len() - 1) / max])
+            .collect()
+    }
+}
+
+//Children own a strong Rc to their parent and the parent owns its children, so every round builds
+// a cycle that is never freed.
+struct StrongCycle;
+
+struct StrongParent {
+    children: RefCell<Vec<Rc<StrongChild>>>,
+    _tracked: Tracked,
+}
+
+struct StrongChild {
+    _parent: Rc<StrongParent>,
+    _tracked: Tracked,
+}
+
+impl Design for StrongCycle {
+    type Parent = Rc<StrongParent>;
+
+    fn new_parent(&mut self, live: &LiveCount) -> Rc<StrongParent> {
+        Rc::new(StrongParent {
+            children: RefCell::new(Vec::new()),
+            _tracked: Tracked::new(live, size_of::<StrongParent>()),
+        })
+    }
+
+    fn attach_child(&mut self, parent: &Rc<StrongParent>, live: &LiveCount) {
+        let child = Rc::new(StrongChild {
+            _parent: Rc::clone(parent),
+            _tracked: Tracked::new(live, size_of::<StrongChild>()),
+        });
+        parent.children.borrow_mut().push(child);
+    }
+
+    fn child_count(&self, parent: &Rc<StrongParent>) -> usize {
+        parent.children.borrow().len()
+    }
+
+    fn release(&mut self, parent: Rc<StrongParent>) {
+        drop(parent);
+    }
+}
+
+//The same shape, but the children only hold a Weak to their parent, like the tree at the end of the
+// chapter.
+struct WeakBackRef;
+
+struct WeakParent {
+    children: RefCell<Vec<Rc<WeakChild>>>,
+    _tracked: Tracked,
+}
+
+struct WeakChild {
+    _parent: Weak<WeakParent>,
+    _tracked: Tracked,
+}
+
+impl Design for WeakBackRef {
+    type Parent = Rc<WeakParent>;
+
+    fn new_parent(&mut self, live: &LiveCount) -> Rc<WeakParent> {
+        Rc::new(WeakParent {
+            children: RefCell::new(Vec::new()),
+            _tracked: Tracked::new(live, size_of::<WeakParent>()),
+        })
+    }
+
+    fn attach_child(&mut self, parent: &Rc<WeakParent>, live: &LiveCount) {
+        let child = Rc::new(WeakChild {
+            _parent: Rc::downgrade(parent),
+            _tracked: Tracked::new(live, size_of::<WeakChild>()),
+        });
+        parent.children.borrow_mut().push(child);
+    }
+
+    fn child_count(&self, parent: &Rc<WeakParent>) -> usize {
+        parent.children.borrow().len()
+    }
+
+    fn release(&mut self, parent: Rc<WeakParent>) {
+        drop(parent);
+    }
+}
+
+//No Rc at all. Every node lives in one Vec and points at others by index, and freed slots are
+// reused by the next round.
+#[derive(Default)]
+struct Arena {
+    slots: Vec<Option<ArenaNode>>,
+    free: Vec<usize>,
+}
+
+struct ArenaNode {
+    _parent: Option<usize>,
+    children: Vec<usize>,
+    _tracked: Tracked,
+}
+
+impl Arena {
+    fn allocate(&mut self, node: ArenaNode) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = Some(node);
+                index
+            }
+            None => {
+                self.slots.push(Some(node));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn node(&self, index: usize) -> &ArenaNode {
+        self.slots[index].as_ref().expect("only live indices are handed out")
+    }
+}
+
+impl Design for Arena {
+    type Parent = usize;
+
+    fn new_parent(&mut self, live: &LiveCount) -> usize {
+        self.allocate(ArenaNode {
+            _parent: None,
+            children: Vec::new(),
+            _tracked: Tracked::new(live, size_of::<ArenaNode>()),
+        })
+    }
+
+    fn attach_child(&mut self, parent: &usize, live: &LiveCount) {
+        let child = self.allocate(ArenaNode {
+            _parent: Some(*parent),
+            children: Vec::new(),
+            _tracked: Tracked::new(live, size_of::<ArenaNode>()),
+        });
+        self.slots[*parent]
+            .as_mut()
+            .expect("only live indices are handed out")
+            .children
+            .push(child);
+    }
+
+    fn child_count(&self, parent: &usize) -> usize {
+        self.node(*parent).children.len()
+    }
+
+    //Nothing points at a node except by index, so dropping the parent has to free its children too.
+    fn release(&mut self, parent: usize) {
+        let node = self.slots[parent].take().expect("only live indices are handed out");
+        for child in node.children {
+            self.slots[child] = None;
+            self.free.push(child);
+        }
+        self.free.push(parent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAVORS: [DesignFlavor; 3] = [
+        DesignFlavor::StrongCycle,
+        DesignFlavor::WeakBackRef,
+        DesignFlavor::ArenaIndices,
+    ];
+
+    #[test]
+    fn strong_cycles_leak_every_round() {
+        let report = churn(50, DesignFlavor::StrongCycle);
+        let per_round = 1 + CHILDREN_PER_PARENT;
+        for (round, &live) in report.live_objects.iter().enumerate() {
+            assert_eq!(live, (round + 1) * per_round);
+        }
+        let bytes_per_round = size_of::<StrongParent>() + CHILDREN_PER_PARENT * size_of::<StrongChild>();
+        assert_eq!(report.live_bytes[49], 50 * bytes_per_round);
+    }
+
+    #[test]
+    fn weak_back_references_and_arena_indices_stay_flat() {
+        for flavor in [DesignFlavor::WeakBackRef, DesignFlavor::ArenaIndices] {
+            let report = churn(50, flavor);
+            assert!(report.live_objects.iter().all(|&live| live == 0), "{:?}", flavor);
+            assert!(report.live_bytes.iter().all(|&bytes| bytes == 0), "{:?}", flavor);
+        }
+    }
+
+    #[test]
+    fn reports_are_deterministic() {
+        for flavor in FLAVORS {
+            assert_eq!(churn(20, flavor), churn(20, flavor));
+        }
+    }
+
+    #[test]
+    fn every_flavor_processes_the_same_children() {
+        let counts: Vec<usize> = FLAVORS.iter().map(|&flavor| churn(20, flavor).children_processed).collect();
+        assert_eq!(counts, [20 * CHILDREN_PER_PARENT; 3]);
+    }
+
+    #[test]
+    fn the_arena_reuses_its_slots() {
+        let mut arena = Arena::default();
+        let live = LiveCount::default();
+        for _ in 0..10 {
+            let parent = arena.new_parent(&live);
+            arena.attach_child(&parent, &live);
+            arena.release(parent);
+        }
+        assert_eq!(arena.slots.len(), 2);
+        assert_eq!(live.objects.get(), 0);
+    }
+
+    #[test]
+    fn sparkline_has_one_bar_per_round() {
+        assert_eq!(churn(4, DesignFlavor::StrongCycle).sparkline(), "▂▄▆█");
+        assert_eq!(churn(4, DesignFlavor::WeakBackRef).sparkline(), "▁▁▁▁");
+        assert_eq!(churn(0, DesignFlavor::WeakBackRef).sparkline(), "");
+    }
+}
@@ -0,0 +1,264 @@
+use std::ops::{Add, Mul, Sub};
+
+//An arithmetic expression tree. Just like the cons list, the recursive variants need their
+// children inside of a Box so the compiler knows how large an Expr is.
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+}
+
+//The instructions of a tiny stack machine. Push puts a number on the stack, the others pop their
+// operands off the stack and push the result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instr {
+    Push(f64),
+    Add,
+    Mul,
+    Neg,
+}
+
+//The operators build a new node with both sides boxed, so (2 + 3) * 4 can be written out almost the
+// way it reads.
+impl Add for Expr {
+    type Output = Expr;
+
+    fn add(self, right: Expr) -> Expr {
+        Expr::Add(Box::new(self), Box::new(right))
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, right: Expr) -> Expr {
+        Expr::Mul(Box::new(self), Box::new(right))
+    }
+}
+
+impl Sub for Expr {
+    type Output = Expr;
+
+    fn sub(self, right: Expr) -> Expr {
+        Expr::Sub(Box::new(self), Box::new(right))
+    }
+}
+
+impl Expr {
+    pub fn num(value: f64) -> Expr {
+        Expr::Num(value)
+    }
+
+    pub fn eval(&self) -> f64 {
+        match self {
+            Expr::Num(value) => *value,
+            Expr::Add(left, right) => left.eval() + right.eval(),
+            Expr::Mul(left, right) => left.eval() * right.eval(),
+            Expr::Sub(left, right) => left.eval() - right.eval(),
+        }
+    }
+
+    //Turns the tree into a program for run(). The children are compiled before their operator, so
+    // by the time the operator runs both of its operands are on the stack. There is no subtract
+    // instruction, a - b is compiled as a + (-b).
+    pub fn compile(&self) -> Vec<Instr> {
+        let mut program = Vec::new();
+        self.compile_into(&mut program);
+        program
+    }
+
+    fn compile_into(&self, program: &mut Vec<Instr>) {
+        match self {
+            Expr::Num(value) => program.push(Instr::Push(*value)),
+            Expr::Add(left, right) => {
+                left.compile_into(program);
+                right.compile_into(program);
+                program.push(Instr::Add);
+            }
+            Expr::Mul(left, right) => {
+                left.compile_into(program);
+                right.compile_into(program);
+                program.push(Instr::Mul);
+            }
+            Expr::Sub(left, right) => {
+                left.compile_into(program);
+                right.compile_into(program);
+                program.push(Instr::Neg);
+                program.push(Instr::Add);
+            }
+        }
+    }
+
+    //Returns the first subexpression matching `pred` in pre-order. That means a node is checked
+    // before its children and the left child is fully searched before the right one.
+    pub fn find_subexpr(&self, pred: impl Fn(&Expr) -> bool) -> Option<&Expr> {
+        self.find_with(&pred)
+    }
+
+    fn find_with(&self, pred: &dyn Fn(&Expr) -> bool) -> Option<&Expr> {
+        if pred(self) {
+            return Some(self);
+        }
+
+        match self {
+            Expr::Num(_) => None,
+            Expr::Add(left, right) | Expr::Mul(left, right) | Expr::Sub(left, right) => {
+                left.find_with(pred).or_else(|| right.find_with(pred))
+            }
+        }
+    }
+
+    //Compares two trees treating Add and Mul as commutative, so 2 + 3 is equivalent to 3 + 2. They
+    // are treated as associative as well, otherwise (2 * 3) * 4 and (4 * 3) * 2 would not match
+    // since neither pair of subtrees lines up. A run of the same operator is flattened into a list
+    // of operands and the two lists are matched up in any order. Sub is compared in order.
+    pub fn equivalent(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (Expr::Num(a), Expr::Num(b)) => a == b,
+            (Expr::Sub(a_left, a_right), Expr::Sub(b_left, b_right)) => {
+                a_left.equivalent(b_left) && a_right.equivalent(b_right)
+            }
+            (Expr::Add(..), Expr::Add(..)) | (Expr::Mul(..), Expr::Mul(..)) => {
+                let mut unmatched = other.operands();
+                for operand in self.operands() {
+                    match unmatched.iter().position(|candidate| operand.equivalent(candidate)) {
+                        Some(index) => {
+                            unmatched.swap_remove(index);
+                        }
+                        None => return false,
+                    }
+                }
+                unmatched.is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    //The operands of a run of the same commutative operator, for example 2, 3 and 4 for
+    // (2 * 3) * 4. Anything else is a single operand.
+    fn operands(&self) -> Vec<&Expr> {
+        let mut operands = Vec::new();
+        let mut pending = vec![self];
+        while let Some(expr) = pending.pop() {
+            match (self, expr) {
+                (Expr::Add(..), Expr::Add(left, right)) | (Expr::Mul(..), Expr::Mul(left, right)) => {
+                    pending.push(right);
+                    pending.push(left);
+                }
+                _ => operands.push(expr),
+            }
+        }
+        operands
+    }
+}
+
+//Runs a program produced by Expr::compile() and returns what is left on the stack. A program that
+// pops more than it pushed is a bug in the compiler, so that panics.
+pub fn run(program: &[Instr]) -> f64 {
+    let mut stack: Vec<f64> = Vec::new();
+    let pop = |stack: &mut Vec<f64>| stack.pop().expect("the program popped an empty stack");
+
+    for instr in program {
+        let result = match instr {
+            Instr::Push(value) => *value,
+            Instr::Add => pop(&mut stack) + pop(&mut stack),
+            Instr::Mul => pop(&mut stack) * pop(&mut stack),
+            Instr::Neg => -pop(&mut stack),
+        };
+        stack.push(result);
+    }
+
+    pop(&mut stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n(value: f64) -> Expr {
+        Expr::num(value)
+    }
+
+    #[test]
+    fn find_subexpr_returns_the_first_mul_in_pre_order() {
+        //(1 + (2 * 3)) * 4, where the whole expression is itself the first Mul.
+        let expr = (n(1.0) + n(2.0) * n(3.0)) * n(4.0);
+        let found = expr.find_subexpr(|e| matches!(e, Expr::Mul(..))).unwrap();
+        assert!(std::ptr::eq(found, &expr));
+
+        //Inside the left side the nested 2 * 3 is found before anything on the right.
+        let expr = (n(1.0) + n(2.0) * n(3.0)) + n(4.0) * n(5.0);
+        assert_eq!(expr.find_subexpr(|e| matches!(e, Expr::Mul(..))), Some(&(n(2.0) * n(3.0))));
+    }
+
+    #[test]
+    fn find_subexpr_finds_the_leftmost_leaf() {
+        let expr = (n(7.0) - n(8.0)) + n(9.0);
+        assert_eq!(expr.find_subexpr(|e| matches!(e, Expr::Num(_))), Some(&n(7.0)));
+        assert_eq!(expr.find_subexpr(|e| *e == n(9.0)), Some(&n(9.0)));
+    }
+
+    #[test]
+    fn find_subexpr_is_none_without_a_match() {
+        let expr = n(1.0) + n(2.0);
+        assert_eq!(expr.find_subexpr(|e| matches!(e, Expr::Sub(..))), None);
+        assert_eq!(n(1.0).find_subexpr(|e| *e == n(2.0)), None);
+    }
+
+    #[test]
+    fn add_and_mul_are_equivalent_in_any_order() {
+        assert!((n(2.0) + n(3.0)).equivalent(&(n(3.0) + n(2.0))));
+        assert!((n(2.0) * n(3.0) * n(4.0)).equivalent(&(n(4.0) * n(3.0) * n(2.0))));
+        assert!((n(2.0) * (n(3.0) * n(4.0))).equivalent(&(n(4.0) * n(2.0) * n(3.0))));
+    }
+
+    #[test]
+    fn sub_is_compared_in_order() {
+        assert!(!(n(2.0) - n(3.0)).equivalent(&(n(3.0) - n(2.0))));
+        assert!((n(2.0) - (n(1.0) + n(5.0))).equivalent(&(n(2.0) - (n(5.0) + n(1.0)))));
+    }
+
+    #[test]
+    fn different_operators_or_operands_are_not_equivalent() {
+        assert!(!(n(2.0) + n(3.0)).equivalent(&(n(2.0) * n(3.0))));
+        assert!(!(n(2.0) + n(2.0) + n(3.0)).equivalent(&(n(2.0) + n(3.0) + n(3.0))));
+        assert!(!(n(2.0) + n(3.0)).equivalent(&(n(2.0) + n(3.0) + n(0.0))));
+        //Mixing the operators does not let a Mul operand move into an Add.
+        assert!(!(n(2.0) * n(3.0) + n(4.0)).equivalent(&(n(2.0) * (n(3.0) + n(4.0)))));
+    }
+
+    #[test]
+    fn compiling_puts_operands_before_their_operator() {
+        let expr = (n(2.0) + n(3.0)) * n(4.0);
+        let program = expr.compile();
+        assert_eq!(
+            program,
+            [Instr::Push(2.0), Instr::Push(3.0), Instr::Add, Instr::Push(4.0), Instr::Mul]
+        );
+        assert_eq!(run(&program), 20.0);
+        assert_eq!(run(&program), expr.eval());
+    }
+
+    #[test]
+    fn sub_compiles_to_adding_the_negation() {
+        let expr = n(10.0) - n(4.0) - n(1.0);
+        let program = expr.compile();
+        assert_eq!(program.iter().filter(|&&instr| instr == Instr::Neg).count(), 2);
+        assert_eq!(run(&program), 5.0);
+        assert_eq!(run(&program), expr.eval());
+    }
+
+    #[test]
+    fn a_lone_number_is_a_single_push() {
+        assert_eq!(n(7.5).compile(), [Instr::Push(7.5)]);
+        assert_eq!(run(&n(7.5).compile()), 7.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "the program popped an empty stack")]
+    fn running_a_program_that_pops_too_much_panics() {
+        run(&[Instr::Push(1.0), Instr::Add]);
+    }
+}
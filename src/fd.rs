@@ -0,0 +1,101 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+//Counts how many descriptors have really been closed. Clones share the count, so the caller can
+// keep one while the descriptors themselves are dropped.
+#[derive(Clone, Default)]
+pub struct CloseCounter(Rc<Cell<usize>>);
+
+impl CloseCounter {
+    pub fn new() -> CloseCounter {
+        CloseCounter::default()
+    }
+
+    pub fn close_count(&self) -> usize {
+        self.0.get()
+    }
+}
+
+//Stands in for an OS file descriptor. Its Drop is the "close", and it only runs once, when the
+// last SharedFd pointing at it goes away.
+struct FdInner {
+    number: i32,
+    closes: CloseCounter,
+}
+
+impl Drop for FdInner {
+    fn drop(&mut self) {
+        self.closes.0.set(self.closes.0.get() + 1);
+    }
+}
+
+//Like dup() on a real descriptor, every SharedFd refers to the same open descriptor. Here that is
+// just an Rc, so dropping a duplicate only lowers the count.
+pub struct SharedFd {
+    inner: Rc<FdInner>,
+}
+
+impl SharedFd {
+    pub fn open(number: i32, closes: &CloseCounter) -> SharedFd {
+        SharedFd {
+            inner: Rc::new(FdInner {
+                number,
+                closes: closes.clone(),
+            }),
+        }
+    }
+
+    pub fn dup(&self) -> SharedFd {
+        SharedFd {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+
+    pub fn number(&self) -> i32 {
+        self.inner.number
+    }
+
+    pub fn open_handles(&self) -> usize {
+        Rc::strong_count(&self.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_descriptor_closes_once_after_every_duplicate_is_gone() {
+        let closes = CloseCounter::new();
+        let fd = SharedFd::open(3, &closes);
+        let duplicates: Vec<SharedFd> = (0..3).map(|_| fd.dup()).collect();
+        assert_eq!(fd.open_handles(), 4);
+        assert!(duplicates.iter().all(|dup| dup.number() == 3));
+
+        drop(fd);
+        assert_eq!(closes.close_count(), 0);
+        let mut duplicates = duplicates;
+        while duplicates.len() > 1 {
+            duplicates.pop();
+            assert_eq!(closes.close_count(), 0);
+        }
+        assert_eq!(duplicates[0].open_handles(), 1);
+        drop(duplicates);
+        assert_eq!(closes.close_count(), 1);
+    }
+
+    #[test]
+    fn separate_descriptors_close_separately() {
+        let closes = CloseCounter::new();
+        let first = SharedFd::open(3, &closes);
+        let second = SharedFd::open(4, &closes.clone());
+        let first_dup = first.dup();
+
+        drop(second);
+        assert_eq!(closes.close_count(), 1);
+        drop(first);
+        assert_eq!(closes.close_count(), 1);
+        drop(first_dup);
+        assert_eq!(closes.close_count(), 2);
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::rc_tree::Node;
+
+//The predicates a find-if command understands: `value`, a comparison and a whole number, split by
+// whitespace. For example `value > 10` or `value == 3`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Predicate {
+    pub comparison: Comparison,
+    pub operand: i32,
+}
+
+//Which token was wrong and what was expected in its place. A predicate that stops early has no bad
+// token to show, so that case only says what was missing.
+#[derive(Debug, PartialEq)]
+pub enum PredicateError {
+    BadToken { token: String, expected: &'static str },
+    Missing { expected: &'static str },
+}
+
+impl fmt::Display for PredicateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PredicateError::BadToken { token, expected } => {
+                write!(f, "unexpected '{}', expected {}", token, expected)
+            }
+            PredicateError::Missing { expected } => write!(f, "missing {}", expected),
+        }
+    }
+}
+
+const FIELD: &str = "'value'";
+const OPERATOR: &str = "one of == != < <= > >=";
+const NUMBER: &str = "a whole number";
+const END: &str = "the end of the predicate";
+
+impl Predicate {
+    pub fn parse(source: &str) -> Result<Predicate, PredicateError> {
+        let mut tokens = source.split_whitespace();
+        let mut next = |expected| tokens.next().ok_or(PredicateError::Missing { expected });
+        let bad = |token: &str, expected| PredicateError::BadToken {
+            token: token.to_string(),
+            expected,
+        };
+
+        let field = next(FIELD)?;
+        if field != "value" {
+            return Err(bad(field, FIELD));
+        }
+        let operator = next(OPERATOR)?;
+        let comparison = match operator {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => return Err(bad(operator, OPERATOR)),
+        };
+        let number = next(NUMBER)?;
+        let operand = number.parse().map_err(|_| bad(number, NUMBER))?;
+        if let Ok(extra) = next(END) {
+            return Err(bad(extra, END));
+        }
+        Ok(Predicate { comparison, operand })
+    }
+
+    pub fn matches(&self, value: i32) -> bool {
+        match self.comparison {
+            Comparison::Equal => value == self.operand,
+            Comparison::NotEqual => value != self.operand,
+            Comparison::Less => value < self.operand,
+            Comparison::LessOrEqual => value <= self.operand,
+            Comparison::Greater => value > self.operand,
+            Comparison::GreaterOrEqual => value >= self.operand,
+        }
+    }
+}
+
+//What a find-if command runs: parse the predicate, then hand it to Node::find_first(). Nothing is
+// searched if the predicate does not parse.
+pub fn find_if(root: &Rc<Node<i32>>, source: &str) -> Result<Option<Rc<Node<i32>>>, PredicateError> {
+    let predicate = Predicate::parse(source)?;
+    Ok(Node::find_first(root, |node| predicate.matches(node.value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree;
+
+    //        1
+    //      /   \
+    //     4     12
+    //    / \     \
+    //   7   20    8
+    fn sample() -> Rc<Node<i32>> {
+        tree!(1 => [4 => [7, 20], 12 => [8]])
+    }
+
+    fn found(root: &Rc<Node<i32>>, source: &str) -> Option<i32> {
+        find_if(root, source).unwrap().map(|node| node.value)
+    }
+
+    #[test]
+    fn find_if_returns_the_first_match_in_pre_order() {
+        let root = sample();
+        assert_eq!(found(&root, "value > 10"), Some(20));
+        assert_eq!(found(&root, "value == 8"), Some(8));
+        assert_eq!(found(&root, "value <= 1"), Some(1));
+        assert_eq!(found(&root, "value != 1"), Some(4));
+        assert_eq!(found(&root, "value >= 100"), None);
+        assert_eq!(found(&root, "  value   <   5 "), Some(1));
+        assert_eq!(found(&root, "value > -3"), Some(1));
+    }
+
+    #[test]
+    fn every_operator_parses() {
+        for (source, comparison) in [
+            ("value == 3", Comparison::Equal),
+            ("value != 3", Comparison::NotEqual),
+            ("value < 3", Comparison::Less),
+            ("value <= 3", Comparison::LessOrEqual),
+            ("value > 3", Comparison::Greater),
+            ("value >= 3", Comparison::GreaterOrEqual),
+        ] {
+            assert_eq!(Predicate::parse(source), Ok(Predicate { comparison, operand: 3 }));
+        }
+    }
+
+    #[test]
+    fn parse_errors_report_the_bad_token() {
+        let root = sample();
+        let error = |source| find_if(&root, source).err().unwrap();
+        assert_eq!(
+            error("size > 3"),
+            PredicateError::BadToken {
+                token: String::from("size"),
+                expected: FIELD
+            }
+        );
+        assert_eq!(
+            error("value => 3"),
+            PredicateError::BadToken {
+                token: String::from("=>"),
+                expected: OPERATOR
+            }
+        );
+        assert_eq!(
+            error("value > ten"),
+            PredicateError::BadToken {
+                token: String::from("ten"),
+                expected: NUMBER
+            }
+        );
+        assert_eq!(
+            error("value > 3 4"),
+            PredicateError::BadToken {
+                token: String::from("4"),
+                expected: END
+            }
+        );
+        assert_eq!(error("value >").to_string(), "missing a whole number");
+        assert_eq!(error("").to_string(), "missing 'value'");
+        assert_eq!(error("value > ten").to_string(), "unexpected 'ten', expected a whole number");
+    }
+}
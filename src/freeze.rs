@@ -0,0 +1,245 @@
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::rc_tree::Node;
+
+//A read only copy of an rc_tree::Node tree. There is no RefCell anywhere, so reading it never pays
+// for a borrow check and nothing can mutate it by accident. The parent links are left out, a
+// frozen tree is only ever walked from the root down.
+//The children are shared through Rc, so a frozen tree is never Send, even when T is. Only one
+// thread can ever read it. What it does promise is that there is no interior mutability left, which
+// the tests check by requiring RefUnwindSafe, something a RefCell anywhere inside would break.
+/// ```compile_fail
+/// use rust_book_chapter_15::freeze::FrozenNode;
+///
+/// fn requires_send<T: Send>(_: T) {}
+/// requires_send(FrozenNode { value: 1, children: Vec::new() });
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct FrozenNode<T> {
+    pub value: T,
+    pub children: Vec<Rc<FrozenNode<T>>>,
+}
+
+impl<T: Clone> Node<T> {
+    //Takes a deep snapshot of the tree. Later changes to the source tree do not show up in it.
+    //The tree is walked with an explicit stack so that a very deep tree cannot overflow the call
+    // stack. A node is frozen only after all of its children have been frozen.
+    pub fn freeze(root: &Rc<Node<T>>) -> Rc<FrozenNode<T>> {
+        let mut pending = vec![(Rc::clone(root), None)];
+        let mut frozen: Vec<Rc<FrozenNode<T>>> = Vec::new();
+
+        while let Some((node, child_count)) = pending.pop() {
+            match child_count {
+                Some(count) => {
+                    let children = frozen.split_off(frozen.len() - count);
+                    frozen.push(Rc::new(FrozenNode {
+                        value: node.value.clone(),
+                        children,
+                    }));
+                }
+                None => {
+                    let children = node.children();
+                    pending.push((node, Some(children.len())));
+                    pending.extend(children.into_iter().rev().map(|child| (child, None)));
+                }
+            }
+        }
+
+        frozen.pop().expect("the root is always frozen last")
+    }
+}
+
+impl<T: Clone> FrozenNode<T> {
+    //Builds a new mutable tree out of a frozen one, parent links included.
+    pub fn thaw(root: &Rc<FrozenNode<T>>) -> Rc<Node<T>> {
+        let thawed_root = Node::new(root.value.clone());
+        let mut pending: Vec<_> = root
+            .children
+            .iter()
+            .rev()
+            .map(|child| (Rc::clone(child), Rc::clone(&thawed_root)))
+            .collect();
+
+        while let Some((frozen, parent)) = pending.pop() {
+            let node = Node::new(frozen.value.clone());
+            Node::add_child(&parent, &node);
+            pending.extend(
+                frozen
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|child| (Rc::clone(child), Rc::clone(&node))),
+            );
+        }
+
+        thawed_root
+    }
+}
+
+//A value that can be changed through a shared reference, except while it is frozen. freeze()
+// hands out a guard and the value stays read only for as long as any guard is alive. Unlike a
+// FrozenNode nothing is copied. Each guard holds a Ref to the value, so it is the RefCell itself
+// that refuses to hand out a RefMut while the value is frozen.
+pub struct Freezable<T> {
+    value: RefCell<T>,
+    freezes: Cell<usize>,
+}
+
+impl<T> Freezable<T> {
+    pub fn new(value: T) -> Freezable<T> {
+        Freezable {
+            value: RefCell::new(value),
+            freezes: Cell::new(0),
+        }
+    }
+
+    pub fn get(&self) -> Ref<'_, T> {
+        self.value.borrow()
+    }
+
+    //None while frozen. It is also None instead of a panic if the value is currently borrowed
+    // through get() or another get_mut().
+    pub fn get_mut(&self) -> Option<RefMut<'_, T>> {
+        if self.is_frozen() {
+            return None;
+        }
+        self.value.try_borrow_mut().ok()
+    }
+
+    //Several guards can be alive at once, the value only thaws when the last one is dropped. A
+    // RefMut from get_mut() that is still alive could keep changing the value after the freeze, so
+    // the freeze fails with None until that RefMut is dropped.
+    pub fn freeze(&self) -> Option<FreezeGuard<'_, T>> {
+        let value = self.value.try_borrow().ok()?;
+        self.freezes.set(self.freezes.get() + 1);
+        Some(FreezeGuard {
+            value,
+            freezes: &self.freezes,
+        })
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.freezes.get() > 0
+    }
+}
+
+//Reading through the guard is always allowed, since it already holds a Ref.
+pub struct FreezeGuard<'a, T> {
+    value: Ref<'a, T>,
+    freezes: &'a Cell<usize>,
+}
+
+impl<T> Deref for FreezeGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Drop for FreezeGuard<'_, T> {
+    fn drop(&mut self) {
+        self.freezes.set(self.freezes.get() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::RefUnwindSafe;
+
+    use super::*;
+    use crate::tree;
+
+    fn assert_no_interior_mutability<T: RefUnwindSafe>() {}
+
+    #[test]
+    fn frozen_nodes_have_no_interior_mutability() {
+        assert_no_interior_mutability::<FrozenNode<i32>>();
+        assert_no_interior_mutability::<Rc<FrozenNode<String>>>();
+    }
+
+    #[test]
+    fn freeze_is_a_deep_snapshot() {
+        let root = tree!(1 => [2 => [3]]);
+        let frozen = Node::freeze(&root);
+        let two = Rc::clone(&root.children()[0]);
+        Node::add_child(&two, &Node::new(4));
+        Node::add_child(&root, &Node::new(5));
+
+        assert_eq!(frozen.children.len(), 1);
+        assert_eq!(frozen.children[0].children.len(), 1);
+        assert_eq!(frozen.children[0].children[0].value, 3);
+    }
+
+    #[test]
+    fn thaw_round_trips_the_structure() {
+        let root = tree!(1 => [2 => [4, 5], 3]);
+        let thawed = FrozenNode::thaw(&Node::freeze(&root));
+        assert!(Node::structural_eq(&root, &thawed));
+
+        let four = Rc::clone(&thawed.children()[0].children()[0]);
+        assert_eq!(four.parent().map(|parent| parent.value), Some(2));
+        assert!(thawed.parent().is_none());
+    }
+
+    #[test]
+    fn freezing_a_ten_thousand_level_chain_does_not_overflow() {
+        //Dropping either chain from the top would recurse once per level. Keeping every node in a
+        // Vec as well means each one is freed on its own when the Vec drops them in order.
+        let mut live = vec![Node::new(0)];
+        for value in 1..10_000 {
+            let next = Node::new(value);
+            Node::add_child(&live[live.len() - 1], &next);
+            live.push(next);
+        }
+
+        let mut frozen = vec![Node::freeze(&live[0])];
+        while let Some(child) = frozen[frozen.len() - 1].children.first() {
+            frozen.push(Rc::clone(child));
+        }
+        assert_eq!(frozen.len(), 10_000);
+        assert_eq!(frozen[frozen.len() - 1].value, 9_999);
+    }
+
+    #[test]
+    fn get_mut_is_blocked_while_frozen_and_allowed_after() {
+        let settings = Freezable::new(vec![1]);
+        {
+            let guard = settings.freeze().expect("nothing is borrowed yet");
+            assert!(settings.is_frozen());
+            assert!(settings.get_mut().is_none());
+            assert_eq!(*guard, [1]);
+            assert_eq!(*settings.get(), [1]);
+        }
+        assert!(!settings.is_frozen());
+        settings.get_mut().expect("the guard was dropped").push(2);
+        assert_eq!(*settings.get(), [1, 2]);
+    }
+
+    #[test]
+    fn the_value_thaws_when_the_last_guard_drops() {
+        let settings = Freezable::new(0);
+        let first = settings.freeze();
+        let second = settings.freeze();
+        drop(first);
+        assert!(settings.get_mut().is_none());
+        drop(second);
+        assert!(settings.get_mut().is_some());
+    }
+
+    #[test]
+    fn freezing_fails_while_a_get_mut_is_still_alive() {
+        let settings = Freezable::new(0);
+        let mut writer = settings.get_mut().expect("not frozen");
+        assert!(settings.freeze().is_none());
+        assert!(!settings.is_frozen());
+        *writer = 5;
+        drop(writer);
+
+        let _guard = settings.freeze().expect("the writer was dropped");
+        assert!(settings.get_mut().is_none());
+        assert_eq!(*settings.get(), 5);
+    }
+}
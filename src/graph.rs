@@ -0,0 +1,722 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use crate::heap_size::HeapSize;
+
+//A node in a directed graph where every edge is a strong Rc pointer. This makes it easy to share
+// a node between several parents, but as shown in reference_cycles_can_leak_memory(), an edge
+// that closes a cycle means none of the nodes on the cycle are ever cleaned up.
+pub struct GraphNode {
+    //The value is in a Cell so it can be changed through a shared Rc<GraphNode>.
+    value: Cell<i32>,
+    edges: RefCell<Vec<Rc<GraphNode>>>,
+    //weights[i] is the weight of edges[i]. Keeping them in their own Vec means everything that only
+    // follows the edges does not have to know about weights at all.
+    weights: RefCell<Vec<u32>>,
+}
+
+impl GraphNode {
+    pub fn new(value: i32) -> Rc<GraphNode> {
+        Rc::new(GraphNode {
+            value: Cell::new(value),
+            edges: RefCell::new(Vec::new()),
+            weights: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value.get()
+    }
+
+    pub fn set_value(&self, value: i32) {
+        self.value.set(value);
+    }
+
+    //Returns clones of the outgoing edges. Handing out clones instead of a Ref means the caller
+    // can walk the graph without holding a borrow of this node.
+    pub fn edges(&self) -> Vec<Rc<GraphNode>> {
+        self.edges.borrow().clone()
+    }
+
+    //With the debug_cycle_checks feature enabled (and debug assertions on), adding an edge that
+    // would make `to` able to reach back to this node panics instead of silently leaking. In a
+    // release build the check compiles away to nothing.
+    //A plain edge has a weight of 1.
+    pub fn add_edge(&self, to: &Rc<GraphNode>) {
+        self.add_weighted_edge(to, 1);
+    }
+
+    pub fn add_weighted_edge(&self, to: &Rc<GraphNode>, weight: u32) {
+        #[cfg(all(feature = "debug_cycle_checks", debug_assertions))]
+        if to.reaches(self) {
+            panic!(
+                "adding the edge {} -> {} would create a reference cycle",
+                self.value(), to.value()
+            );
+        }
+
+        self.edges.borrow_mut().push(Rc::clone(to));
+        self.weights.borrow_mut().push(weight);
+    }
+
+    //The outgoing edges together with their weights, cloned out for the same reason as edges().
+    pub fn neighbors(&self) -> Vec<(Rc<GraphNode>, u32)> {
+        let edges = self.edges.borrow();
+        let weights = self.weights.borrow();
+        edges.iter().cloned().zip(weights.iter().copied()).collect()
+    }
+
+    //Removing the outgoing edges is how a cycle is broken by hand, so that the nodes on it can be
+    // cleaned up again.
+    pub fn clear_edges(&self) {
+        self.edges.borrow_mut().clear();
+        self.weights.borrow_mut().clear();
+    }
+
+    fn edge_list_bytes(&self) -> usize {
+        self.edges.borrow().capacity() * size_of::<Rc<GraphNode>>()
+            + self.weights.borrow().capacity() * size_of::<u32>()
+    }
+
+    #[cfg(all(feature = "debug_cycle_checks", debug_assertions))]
+    fn reaches(&self, target: &GraphNode) -> bool {
+        if std::ptr::eq(self, target) {
+            return true;
+        }
+
+        let mut visited: HashSet<*const GraphNode> = HashSet::new();
+        let mut pending = self.edges();
+        while let Some(node) = pending.pop() {
+            if std::ptr::eq(Rc::as_ptr(&node), target) {
+                return true;
+            }
+            if visited.insert(Rc::as_ptr(&node)) {
+                pending.extend(node.edges());
+            }
+        }
+
+        false
+    }
+}
+
+//owned_bytes() has no `seen` set, so following the edges could go around a cycle forever. A node
+// only claims its own edge list and its share of each node it points at directly, without going
+// any further. reachable_bytes() does follow every edge, and the `seen` set stops it at the first
+// node it has already counted, so it also finishes on a graph with cycles.
+impl HeapSize for GraphNode {
+    fn owned_bytes(&self) -> usize {
+        let edges = self.edges.borrow();
+        let shares: usize = edges
+            .iter()
+            .map(|edge| size_of::<(usize, usize, GraphNode)>() / Rc::strong_count(edge))
+            .sum();
+        self.edge_list_bytes() + shares
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        let edges = self.edges.borrow();
+        let targets: usize = edges.iter().map(|edge| edge.reachable_bytes(seen)).sum();
+        self.edge_list_bytes() + targets
+    }
+}
+
+//A snapshot of who owns what in a graph. Each reachable node appears exactly once, nested under the
+// first node it was reached from, together with its strong and weak counts at the time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnershipReport {
+    pub value: i32,
+    pub strong_count: usize,
+    pub weak_count: usize,
+    pub children: Vec<OwnershipReport>,
+}
+
+pub fn ownership_report(root: &Rc<GraphNode>) -> OwnershipReport {
+    let mut visited = HashSet::new();
+    visited.insert(Rc::as_ptr(root));
+    report_node(root, &mut visited)
+}
+
+//The edges are read through the borrow instead of through edges(). Cloning them would add one to
+// every strong count that ends up in the report.
+fn report_node(node: &Rc<GraphNode>, visited: &mut HashSet<*const GraphNode>) -> OwnershipReport {
+    let mut children = Vec::new();
+    for edge in node.edges.borrow().iter() {
+        if visited.insert(Rc::as_ptr(edge)) {
+            children.push(report_node(edge, visited));
+        }
+    }
+
+    OwnershipReport {
+        value: node.value(),
+        strong_count: Rc::strong_count(node),
+        weak_count: Rc::weak_count(node),
+        children,
+    }
+}
+
+impl OwnershipReport {
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{}{} (strong: {}, weak: {})",
+            "  ".repeat(depth),
+            self.value,
+            self.strong_count,
+            self.weak_count
+        )?;
+        for child in &self.children {
+            child.write_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for OwnershipReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+//Replaces `old` with `new` in every node reachable from `root` and returns how many nodes were
+// changed. The visited set makes sure every node is only looked at once, which is also what stops
+// the search from going around a cycle forever.
+pub fn replace_value(root: &Rc<GraphNode>, old: i32, new: i32) -> usize {
+    let mut visited = HashSet::new();
+    let mut pending = vec![Rc::clone(root)];
+    let mut replaced = 0;
+
+    while let Some(node) = pending.pop() {
+        if !visited.insert(Rc::as_ptr(&node)) {
+            continue;
+        }
+        if node.value() == old {
+            node.set_value(new);
+            replaced += 1;
+        }
+        pending.extend(node.edges());
+    }
+
+    replaced
+}
+
+//Flattens the graph into one entry per reachable node, each with the values of the nodes its
+// edges point at. Nodes are listed in the order a depth-first walk from `root` first reaches them,
+// following edges in the order they were added. Because nodes are tracked by pointer in the
+// visited map, a cycle is written out as an ordinary edge instead of being followed forever.
+pub fn to_adjacency_list(root: &Rc<GraphNode>) -> Vec<(i32, Vec<i32>)> {
+    let mut order: HashMap<*const GraphNode, usize> = HashMap::new();
+    let mut adjacency = Vec::new();
+    let mut pending = vec![Rc::clone(root)];
+
+    while let Some(node) = pending.pop() {
+        if order.contains_key(&Rc::as_ptr(&node)) {
+            continue;
+        }
+        order.insert(Rc::as_ptr(&node), adjacency.len());
+
+        let edges = node.edges();
+        adjacency.push((node.value(), edges.iter().map(|edge| edge.value()).collect()));
+        //Pushed in reverse so the first edge is the next one popped.
+        pending.extend(edges.into_iter().rev());
+    }
+
+    adjacency
+}
+
+//Adds up the strong count of every node reachable from `root`, counting each node once. Compared
+// with the plain number of reachable nodes, the extra is how many more owners the nodes have
+// than a tree would give them. Like ownership_report(), the edges are read through the borrow so
+// the walk itself does not add to any count.
+pub fn weighted_reach(root: &Rc<GraphNode>) -> usize {
+    let mut visited = HashSet::new();
+    visited.insert(Rc::as_ptr(root));
+    weigh_node(root, &mut visited)
+}
+
+fn weigh_node(node: &Rc<GraphNode>, visited: &mut HashSet<*const GraphNode>) -> usize {
+    let mut total = Rc::strong_count(node);
+    for edge in node.edges.borrow().iter() {
+        if visited.insert(Rc::as_ptr(edge)) {
+            total += weigh_node(edge, visited);
+        }
+    }
+    total
+}
+
+//One step of the depth-first search in nodes_in_cycles(): a node, its outgoing edges and how many
+// of those edges have been followed so far.
+type Frame = (Rc<GraphNode>, Vec<Rc<GraphNode>>, usize);
+
+//The bookkeeping for Tarjan's algorithm, keyed by node pointer.
+#[derive(Default)]
+struct Tarjan {
+    index: HashMap<*const GraphNode, usize>,
+    low_link: HashMap<*const GraphNode, usize>,
+    on_stack: HashSet<*const GraphNode>,
+    stack: Vec<Rc<GraphNode>>,
+    frames: Vec<Frame>,
+}
+
+impl Tarjan {
+    fn visit(&mut self, node: Rc<GraphNode>) {
+        let ptr = Rc::as_ptr(&node);
+        let next_index = self.index.len();
+        self.index.insert(ptr, next_index);
+        self.low_link.insert(ptr, next_index);
+        self.on_stack.insert(ptr);
+        self.stack.push(Rc::clone(&node));
+        let edges = node.edges();
+        self.frames.push((node, edges, 0));
+    }
+
+    fn lower(&mut self, node: *const GraphNode, candidate: usize) {
+        if let Some(low) = self.low_link.get_mut(&node) {
+            *low = (*low).min(candidate);
+        }
+    }
+}
+
+//Every node reachable from `root` that sits on at least one cycle, in the order they were first
+// reached. This is Tarjan's strongly connected components algorithm. A component with more than
+// one node is a group of nodes that can all reach each other, so each of them is on a cycle. A
+// component with a single node only counts if that node has an edge to itself.
+//The usual version is recursive. Here the call stack is replaced by a Vec of frames, so a long
+// chain of nodes cannot overflow the real stack.
+pub fn nodes_in_cycles(root: &Rc<GraphNode>) -> Vec<Rc<GraphNode>> {
+    let mut tarjan = Tarjan::default();
+    let mut in_cycles = Vec::new();
+    tarjan.visit(Rc::clone(root));
+
+    while let Some((node, edges, next_edge)) = tarjan.frames.last_mut() {
+        let node_ptr = Rc::as_ptr(node);
+
+        if let Some(edge) = edges.get(*next_edge) {
+            let edge = Rc::clone(edge);
+            *next_edge += 1;
+            let edge_ptr = Rc::as_ptr(&edge);
+            match tarjan.index.get(&edge_ptr) {
+                None => tarjan.visit(edge),
+                Some(&edge_index) if tarjan.on_stack.contains(&edge_ptr) => {
+                    tarjan.lower(node_ptr, edge_index)
+                }
+                Some(_) => {}
+            }
+            continue;
+        }
+
+        //Every edge has been followed. If nothing below this node reached further back than the
+        // node itself, it is the first node of a component and the component is on the stack.
+        let (node, edges, _) = tarjan
+            .frames
+            .pop()
+            .expect("the loop only runs while there is a frame");
+        let low = tarjan.low_link[&node_ptr];
+        if low == tarjan.index[&node_ptr] {
+            let mut component = Vec::new();
+            while let Some(member) = tarjan.stack.pop() {
+                tarjan.on_stack.remove(&Rc::as_ptr(&member));
+                let is_node = Rc::ptr_eq(&member, &node);
+                component.push(member);
+                if is_node {
+                    break;
+                }
+            }
+            let self_loop = edges.iter().any(|edge| Rc::ptr_eq(edge, &node));
+            if component.len() > 1 || self_loop {
+                in_cycles.extend(component);
+            }
+        }
+        if let Some((parent, _, _)) = tarjan.frames.last() {
+            let parent_ptr = Rc::as_ptr(parent);
+            tarjan.lower(parent_ptr, low);
+        }
+    }
+
+    in_cycles.sort_by_key(|node| tarjan.index[&Rc::as_ptr(node)]);
+    in_cycles
+}
+
+//Dijkstra's algorithm over the weighted edges. Returns the total weight of the lightest path from
+// `from` to `to` together with the nodes along it, or None if `to` cannot be reached.
+//Each node gets an index the first time it is seen, and the heap only holds (distance, index)
+// pairs. Neighbors are cloned out with neighbors(), so no borrow of any node is alive while the
+// heap is being pushed to or popped from. When two paths weigh the same the one found first is
+// kept, and the heap breaks ties between equal distances by the lower index, so the answer does
+// not change from run to run. The total is a u64 so a long path of heavy edges cannot overflow.
+pub fn shortest_path(from: &Rc<GraphNode>, to: &Rc<GraphNode>) -> Option<(u64, Vec<Rc<GraphNode>>)> {
+    let mut nodes = vec![Rc::clone(from)];
+    let mut index: HashMap<*const GraphNode, usize> = HashMap::from([(Rc::as_ptr(from), 0)]);
+    let mut distance: Vec<u64> = vec![0];
+    let mut previous: Vec<Option<usize>> = vec![None];
+    let mut done: Vec<bool> = vec![false];
+    let mut frontier = BinaryHeap::from([Reverse((0, 0))]);
+
+    while let Some(Reverse((current_distance, current))) = frontier.pop() {
+        if done[current] {
+            continue;
+        }
+        done[current] = true;
+        if Rc::ptr_eq(&nodes[current], to) {
+            let mut path = vec![Rc::clone(&nodes[current])];
+            let mut step = current;
+            while let Some(before) = previous[step] {
+                path.push(Rc::clone(&nodes[before]));
+                step = before;
+            }
+            path.reverse();
+            return Some((current_distance, path));
+        }
+
+        for (neighbor, weight) in nodes[current].neighbors() {
+            let next_distance = current_distance + u64::from(weight);
+            let next = *index.entry(Rc::as_ptr(&neighbor)).or_insert_with(|| {
+                nodes.push(neighbor);
+                distance.push(u64::MAX);
+                previous.push(None);
+                done.push(false);
+                nodes.len() - 1
+            });
+            if next_distance < distance[next] {
+                distance[next] = next_distance;
+                previous[next] = Some(current);
+                frontier.push(Reverse((next_distance, next)));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap_size::reachable_bytes;
+
+    //One Rc allocation plus the buffers of its edge list.
+    fn node_bytes(node: &GraphNode) -> usize {
+        size_of::<(usize, usize, GraphNode)>() + node.edge_list_bytes()
+    }
+
+    #[test]
+    fn reachable_bytes_counts_a_shared_node_once() {
+        let nodes: Vec<Rc<GraphNode>> = (0..4).map(GraphNode::new).collect();
+        for (from, to) in [(0, 1), (0, 2), (1, 3), (2, 3)] {
+            nodes[from].add_edge(&nodes[to]);
+        }
+        let expected: usize = nodes.iter().map(|node| node_bytes(node)).sum();
+        assert_eq!(reachable_bytes(&nodes[0]), expected);
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn reachable_bytes_terminates_on_a_two_node_cycle() {
+        let a = GraphNode::new(1);
+        let b = GraphNode::new(2);
+        a.add_edge(&b);
+        b.add_edge(&a);
+
+        assert_eq!(reachable_bytes(&a), node_bytes(&a) + node_bytes(&b));
+        assert!(a.owned_bytes() > 0);
+        a.clear_edges();
+    }
+
+    #[test]
+    fn a_lone_node_is_just_its_allocation() {
+        let node = GraphNode::new(1);
+        let allocation = size_of::<(usize, usize, GraphNode)>();
+        assert_eq!(GraphNode::owned_bytes(&node), 0);
+        assert_eq!(node.owned_bytes(), allocation);
+        assert_eq!(reachable_bytes(&node), allocation);
+    }
+
+    fn values(path: &[Rc<GraphNode>]) -> Vec<i32> {
+        path.iter().map(|node| node.value()).collect()
+    }
+
+    fn weighted(edges: &[(usize, usize, u32)], count: i32) -> Vec<Rc<GraphNode>> {
+        let nodes: Vec<Rc<GraphNode>> = (0..count).map(GraphNode::new).collect();
+        for &(from, to, weight) in edges {
+            nodes[from].add_weighted_edge(&nodes[to], weight);
+        }
+        nodes
+    }
+
+    #[test]
+    fn shortest_path_takes_the_lighter_route() {
+        let edges = [(0, 1, 7), (0, 2, 9), (0, 5, 14), (1, 2, 10), (1, 3, 15), (2, 3, 11), (2, 5, 2), (3, 4, 6), (5, 4, 9)];
+        let nodes = weighted(&edges, 6);
+        let (total, path) = shortest_path(&nodes[0], &nodes[4]).unwrap();
+        assert_eq!(total, 20);
+        assert_eq!(values(&path), [0, 2, 5, 4]);
+
+        let (total, path) = shortest_path(&nodes[0], &nodes[0]).unwrap();
+        assert_eq!((total, values(&path)), (0, vec![0]));
+    }
+
+    #[test]
+    fn neighbors_pairs_each_edge_with_its_weight() {
+        let nodes = weighted(&[(0, 1, 5), (0, 2, 0)], 3);
+        nodes[0].add_edge(&nodes[1]);
+        let pairs: Vec<(i32, u32)> = nodes[0]
+            .neighbors()
+            .iter()
+            .map(|(node, weight)| (node.value(), *weight))
+            .collect();
+        assert_eq!(pairs, [(1, 5), (2, 0), (1, 1)]);
+        nodes[0].clear_edges();
+        assert!(nodes[0].neighbors().is_empty());
+    }
+
+    #[test]
+    fn an_unreachable_target_has_no_path() {
+        let nodes = weighted(&[(0, 1, 1), (2, 1, 1)], 3);
+        assert!(shortest_path(&nodes[0], &nodes[2]).is_none());
+    }
+
+    #[test]
+    fn zero_weight_edges_are_free() {
+        let nodes = weighted(&[(0, 1, 0), (1, 2, 0), (0, 2, 1)], 3);
+        let (total, path) = shortest_path(&nodes[0], &nodes[2]).unwrap();
+        assert_eq!((total, values(&path)), (0, vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn equal_paths_always_give_the_first_one_found() {
+        let nodes = weighted(&[(0, 1, 1), (0, 2, 1), (1, 3, 1), (2, 3, 1)], 4);
+        for _ in 0..10 {
+            let (total, path) = shortest_path(&nodes[0], &nodes[3]).unwrap();
+            assert_eq!((total, values(&path)), (2, vec![0, 1, 3]));
+        }
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn a_cycle_does_not_loop_forever() {
+        let nodes = weighted(&[(0, 1, 1), (1, 2, 1), (2, 0, 1), (2, 3, 5)], 5);
+        let (total, path) = shortest_path(&nodes[0], &nodes[3]).unwrap();
+        assert_eq!((total, values(&path)), (7, vec![0, 1, 2, 3]));
+        assert!(shortest_path(&nodes[0], &nodes[4]).is_none());
+        for node in &nodes {
+            node.clear_edges();
+        }
+    }
+
+    //With debug_cycle_checks every add_edge() walks everything the target can reach, which makes
+    // building the grid itself quadratic, so this only runs without it.
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn a_ten_thousand_node_grid_finishes_quickly() {
+        const SIDE: usize = 100;
+        let nodes: Vec<Rc<GraphNode>> = (0..(SIDE * SIDE) as i32).map(GraphNode::new).collect();
+        for row in 0..SIDE {
+            for column in 0..SIDE {
+                let here = &nodes[row * SIDE + column];
+                if column + 1 < SIDE {
+                    here.add_weighted_edge(&nodes[row * SIDE + column + 1], 1 + (row % 3) as u32);
+                }
+                if row + 1 < SIDE {
+                    here.add_weighted_edge(&nodes[(row + 1) * SIDE + column], 1 + (column % 3) as u32);
+                }
+            }
+        }
+
+        let start = std::time::Instant::now();
+        let (total, path) = shortest_path(&nodes[0], &nodes[SIDE * SIDE - 1]).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(path.len(), 2 * SIDE - 1);
+        assert!(total >= (2 * SIDE - 2) as u64);
+    }
+
+    #[test]
+    fn edges_that_do_not_close_a_cycle_are_always_accepted() {
+        let nodes = weighted(&[(0, 1, 1), (0, 2, 1), (1, 3, 1), (2, 3, 1)], 4);
+        nodes[0].add_edge(&nodes[3]);
+        assert_eq!(nodes[0].edges().len(), 3);
+    }
+
+    #[cfg(all(feature = "debug_cycle_checks", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "adding the edge 3 -> 1 would create a reference cycle")]
+    fn an_edge_closing_a_cycle_panics_with_the_checks_on() {
+        let nodes = weighted(&[(1, 2, 1), (2, 3, 1)], 4);
+        nodes[3].add_edge(&nodes[1]);
+    }
+
+    #[cfg(all(feature = "debug_cycle_checks", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "adding the edge 5 -> 5 would create a reference cycle")]
+    fn a_self_loop_panics_with_the_checks_on() {
+        let node = GraphNode::new(5);
+        node.add_edge(&node);
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn without_the_checks_a_cycle_is_allowed() {
+        let nodes = weighted(&[(1, 2, 1), (2, 3, 1)], 4);
+        nodes[3].add_edge(&nodes[1]);
+        assert_eq!(Rc::strong_count(&nodes[1]), 2);
+        nodes[3].clear_edges();
+    }
+
+    fn diamond() -> Vec<Rc<GraphNode>> {
+        weighted(&[(0, 1, 1), (0, 2, 1), (1, 3, 1), (2, 3, 1)], 4)
+    }
+
+    fn flatten(report: &OwnershipReport, out: &mut Vec<(i32, usize, usize)>) {
+        out.push((report.value, report.strong_count, report.weak_count));
+        for child in &report.children {
+            flatten(child, out);
+        }
+    }
+
+    #[test]
+    fn ownership_report_lists_a_shared_node_once_with_its_counts() {
+        let nodes = diamond();
+        let _weak = Rc::downgrade(&nodes[3]);
+        let report = ownership_report(&nodes[0]);
+
+        let mut rows = Vec::new();
+        flatten(&report, &mut rows);
+        //Every node is also held by the Vec, so the shared bottom node has three owners.
+        assert_eq!(rows, [(0, 1, 0), (1, 2, 0), (3, 3, 1), (2, 2, 0)]);
+        assert!(report.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn ownership_report_display_indents_children() {
+        let nodes = diamond();
+        let expected = concat!(
+            "0 (strong: 1, weak: 0)\n",
+            "  1 (strong: 2, weak: 0)\n",
+            "    3 (strong: 3, weak: 0)\n",
+            "  2 (strong: 2, weak: 0)\n",
+        );
+        assert_eq!(ownership_report(&nodes[0]).to_string(), expected);
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn ownership_report_stops_at_a_cycle() {
+        let nodes = weighted(&[(0, 1, 1), (1, 0, 1)], 2);
+        let report = ownership_report(&nodes[0]);
+        assert_eq!(report.children.len(), 1);
+        assert!(report.children[0].children.is_empty());
+        nodes[1].clear_edges();
+    }
+
+    #[test]
+    fn replace_value_changes_every_match_once() {
+        //1 is reachable through both 0 and 2, but it is only counted once.
+        let nodes = weighted(&[(0, 1, 1), (0, 2, 1), (2, 1, 1), (2, 3, 1)], 4);
+        nodes[3].set_value(1);
+        assert_eq!(replace_value(&nodes[0], 1, 9), 2);
+        assert_eq!(values(&nodes), [0, 9, 2, 9]);
+        assert_eq!(replace_value(&nodes[0], 1, 9), 0);
+    }
+
+    #[test]
+    fn replace_value_leaves_unreachable_nodes_alone() {
+        let nodes = weighted(&[(0, 1, 1)], 3);
+        nodes[2].set_value(1);
+        assert_eq!(replace_value(&nodes[0], 1, 5), 1);
+        assert_eq!(values(&nodes), [0, 5, 1]);
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn replace_value_terminates_on_a_cycle() {
+        let nodes = weighted(&[(0, 1, 1), (1, 2, 1), (2, 0, 1)], 3);
+        for node in &nodes {
+            node.set_value(4);
+        }
+        assert_eq!(replace_value(&nodes[1], 4, 0), 3);
+        for node in &nodes {
+            node.clear_edges();
+        }
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn to_adjacency_list_writes_a_cycle_as_an_edge() {
+        let nodes = weighted(&[(0, 1, 1), (0, 2, 1), (1, 2, 1), (2, 0, 1)], 3);
+        let expected = vec![(0, vec![1, 2]), (1, vec![2]), (2, vec![0])];
+        assert_eq!(to_adjacency_list(&nodes[0]), expected);
+        for node in &nodes {
+            node.clear_edges();
+        }
+    }
+
+    #[test]
+    fn to_adjacency_list_lists_nodes_in_first_visit_order() {
+        //3 is reached through 1 before 2 gets its turn, so it comes before 2.
+        let nodes = weighted(&[(0, 1, 1), (0, 2, 1), (1, 3, 1), (2, 3, 1)], 5);
+        let expected = vec![(0, vec![1, 2]), (1, vec![3]), (3, vec![]), (2, vec![3])];
+        assert_eq!(to_adjacency_list(&nodes[0]), expected);
+        assert_eq!(to_adjacency_list(&nodes[4]), [(4, vec![])]);
+    }
+
+    #[test]
+    fn weighted_reach_counts_the_shared_node_more() {
+        //Every node is also held by the Vec, so 0 has one owner, 1 and 2 have two and 3 has three.
+        let nodes = diamond();
+        let plain = to_adjacency_list(&nodes[0]).len();
+        assert_eq!(plain, 4);
+        assert_eq!(weighted_reach(&nodes[0]), 1 + 2 + 2 + 3);
+
+        let extra = Rc::clone(&nodes[3]);
+        assert_eq!(weighted_reach(&nodes[0]), 1 + 2 + 2 + 4);
+        drop(extra);
+        assert_eq!(weighted_reach(&nodes[3]), 3);
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn weighted_reach_counts_each_node_of_a_cycle_once() {
+        let nodes = weighted(&[(0, 1, 1), (1, 0, 1)], 2);
+        assert_eq!(weighted_reach(&nodes[0]), 2 + 2);
+        for node in &nodes {
+            node.clear_edges();
+        }
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn nodes_in_cycles_leaves_out_the_appendages() {
+        //0 -> 1 -> 2 -> 3 -> 1 is the cycle, 0 leads into it and 4 and 5 hang off it.
+        let nodes = weighted(&[(0, 1, 1), (1, 2, 1), (2, 3, 1), (3, 1, 1), (2, 4, 1), (4, 5, 1)], 6);
+        assert_eq!(values(&nodes_in_cycles(&nodes[0])), [1, 2, 3]);
+        for node in &nodes {
+            node.clear_edges();
+        }
+    }
+
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    #[test]
+    fn nodes_in_cycles_counts_a_self_loop_and_separate_cycles() {
+        let nodes = weighted(&[(0, 1, 1), (1, 1, 1), (0, 2, 1), (2, 3, 1), (3, 2, 1), (3, 4, 1)], 5);
+        assert_eq!(values(&nodes_in_cycles(&nodes[0])), [1, 2, 3]);
+        for node in &nodes {
+            node.clear_edges();
+        }
+    }
+
+    #[test]
+    fn an_acyclic_graph_has_no_nodes_in_cycles() {
+        assert!(nodes_in_cycles(&diamond()[0]).is_empty());
+        assert!(nodes_in_cycles(&GraphNode::new(1)).is_empty());
+    }
+
+    #[test]
+    fn a_long_chain_does_not_overflow_the_stack() {
+        let nodes: Vec<Rc<GraphNode>> = (0..10_000).map(GraphNode::new).collect();
+        for pair in nodes.windows(2) {
+            pair[0].add_edge(&pair[1]);
+        }
+        assert!(nodes_in_cycles(&nodes[0]).is_empty());
+    }
+}
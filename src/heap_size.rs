@@ -0,0 +1,255 @@
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use crate::hello::HelloEnum;
+use crate::list::List;
+use crate::tree::Node;
+
+//Rough numbers for how much heap memory a value holds on to. Memory behind an Rc is where it gets
+// interesting, since it does not belong to any single owner. There are two ways to count it:
+// - owned_bytes() gives every Rc an equal share of what it points at, so the shares of all the
+//   owners add back up to the whole allocation. The division rounds down, so this is approximate.
+// - reachable_bytes() counts every allocation once, however many Rcs point at it. The `seen` set
+//   remembers allocations by address.
+//Only the memory the values asked for is counted. Allocator overhead and padding inside the
+// allocator are left out.
+pub trait HeapSize {
+    fn owned_bytes(&self) -> usize;
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize;
+}
+
+//Counts every allocation reachable from `value` exactly once.
+pub fn reachable_bytes<T: HeapSize>(value: &T) -> usize {
+    value.reachable_bytes(&mut HashSet::new())
+}
+
+//Just the Boxes that hold a cons list together, leaving out anything the elements own themselves.
+// Every Cons owns one Box with the rest of the list inside, including the Box that ends up holding
+// the Nil, so a list of n elements makes n allocations the size of a List<T>.
+pub fn estimated_heap_bytes<T>(list: &List<T>) -> usize {
+    list.iter().count() * size_of::<List<T>>()
+}
+
+//A String or Vec owns its whole buffer, so the capacity is counted rather than the length.
+impl HeapSize for String {
+    fn owned_bytes(&self) -> usize {
+        self.capacity()
+    }
+
+    fn reachable_bytes(&self, _seen: &mut HashSet<usize>) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for i32 {
+    fn owned_bytes(&self) -> usize {
+        0
+    }
+
+    fn reachable_bytes(&self, _seen: &mut HashSet<usize>) -> usize {
+        0
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn owned_bytes(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(HeapSize::owned_bytes).sum::<usize>()
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        let items: usize = self.iter().map(|item| item.reachable_bytes(seen)).sum();
+        self.capacity() * size_of::<T>() + items
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn owned_bytes(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::owned_bytes)
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        self.as_ref().map_or(0, |value| value.reachable_bytes(seen))
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn owned_bytes(&self) -> usize {
+        size_of::<T>() + (**self).owned_bytes()
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        size_of::<T>() + (**self).reachable_bytes(seen)
+    }
+}
+
+//The allocation behind an Rc holds the strong and weak counts next to the value.
+impl<T: HeapSize> HeapSize for Rc<T> {
+    fn owned_bytes(&self) -> usize {
+        let allocation = size_of::<(usize, usize, T)>() + (**self).owned_bytes();
+        allocation / Rc::strong_count(self)
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        if !seen.insert(Rc::as_ptr(self) as usize) {
+            return 0;
+        }
+        size_of::<(usize, usize, T)>() + (**self).reachable_bytes(seen)
+    }
+}
+
+//Walked in a loop instead of through the Box impl, so a long list does not recurse once per
+// element. Every Cons owns one Box holding the rest of the list.
+impl<T: HeapSize> HeapSize for List<T> {
+    fn owned_bytes(&self) -> usize {
+        let mut total = 0;
+        let mut current = self;
+        while let List::Cons(value, next) = current {
+            total += size_of::<List<T>>() + value.owned_bytes();
+            current = next;
+        }
+        total
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        let mut total = 0;
+        let mut current = self;
+        while let List::Cons(value, next) = current {
+            total += size_of::<List<T>>() + value.reachable_bytes(seen);
+            current = next;
+        }
+        total
+    }
+}
+
+//The same loop for the enum from the start of the chapter. Every Hello level is one Box holding the
+// next HelloEnum, and there is nothing else to count.
+impl HeapSize for HelloEnum {
+    fn owned_bytes(&self) -> usize {
+        let mut total = 0;
+        let mut current = self;
+        while let HelloEnum::Hello(next) = current {
+            total += size_of::<HelloEnum>();
+            current = next;
+        }
+        total
+    }
+
+    fn reachable_bytes(&self, _seen: &mut HashSet<usize>) -> usize {
+        self.owned_bytes()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn owned_bytes(&self) -> usize {
+        self.value.owned_bytes() + self.left.owned_bytes() + self.right.owned_bytes()
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        self.value.reachable_bytes(seen)
+            + self.left.reachable_bytes(seen)
+            + self.right.reachable_bytes(seen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hello::MemoryBudget;
+    use crate::rc_tree;
+
+    #[test]
+    fn string_and_vec_count_capacity_not_length() {
+        let mut text = String::with_capacity(32);
+        text.push_str("hi");
+        assert_eq!(text.owned_bytes(), 32);
+
+        let mut numbers: Vec<i32> = Vec::with_capacity(10);
+        numbers.push(1);
+        assert_eq!(numbers.owned_bytes(), 10 * size_of::<i32>());
+        assert_eq!(reachable_bytes(&numbers), 10 * size_of::<i32>());
+    }
+
+    #[test]
+    fn empty_structures_report_zero() {
+        assert_eq!(String::new().owned_bytes(), 0);
+        assert_eq!(Vec::<i32>::new().owned_bytes(), 0);
+        assert_eq!(None::<Box<i32>>.owned_bytes(), 0);
+        assert_eq!(List::<i32>::Nil.owned_bytes(), 0);
+        assert_eq!(reachable_bytes(&List::<i32>::Nil), 0);
+        assert_eq!(estimated_heap_bytes(&List::<i32>::Nil), 0);
+    }
+
+    #[test]
+    fn exact_sizes_of_small_structures() {
+        let list: List<i32> = (1..=3).collect();
+        assert_eq!(list.owned_bytes(), 3 * size_of::<List<i32>>());
+
+        //A root with two leaf children is two boxed nodes, the root itself is not on the heap.
+        let mut tree = Node::new(2);
+        tree.insert(1);
+        tree.insert(3);
+        assert_eq!(tree.owned_bytes(), 2 * size_of::<Node<i32>>());
+
+        let boxed = Box::new(String::with_capacity(8));
+        assert_eq!(boxed.owned_bytes(), size_of::<String>() + 8);
+    }
+
+    #[test]
+    fn every_hello_level_is_one_box() {
+        assert_eq!(HelloEnum::Null.owned_bytes(), 0);
+        let hello = HelloEnum::Hello(Box::new(HelloEnum::Hello(Box::new(HelloEnum::Null))));
+        assert_eq!(hello.owned_bytes(), 2 * size_of::<HelloEnum>());
+        assert_eq!(reachable_bytes(&hello), hello.owned_bytes());
+
+        //A chain this long would overflow the stack if it were walked recursively.
+        let budget = MemoryBudget::new(1_000_000 * HelloEnum::LEVEL_BYTES);
+        let deep = HelloEnum::try_from_depth(1_000_000, &budget).unwrap();
+        assert_eq!(deep.owned_bytes(), 1_000_000 * size_of::<HelloEnum>());
+    }
+
+    #[test]
+    fn owned_shares_of_an_rc_add_up_to_the_whole() {
+        let text = Rc::new(String::with_capacity(64));
+        let handles = vec![Rc::clone(&text), text];
+        let allocation = size_of::<(usize, usize, String)>() + 64;
+        let buffer = handles.capacity() * size_of::<Rc<String>>();
+        assert_eq!(handles.owned_bytes(), buffer + allocation);
+        assert_eq!(reachable_bytes(&handles), buffer + allocation);
+    }
+
+    #[test]
+    fn two_handles_to_one_tree_are_counted_as_one_tree() {
+        let root = rc_tree::Node::new(1);
+        rc_tree::Node::add_child(&root, &rc_tree::Node::new(2));
+        rc_tree::Node::add_child(&root, &rc_tree::Node::new(3));
+
+        let one = reachable_bytes(&root);
+        let mut seen = HashSet::new();
+        let both = root.reachable_bytes(&mut seen) + Rc::clone(&root).reachable_bytes(&mut seen);
+        assert_eq!(both, one);
+    }
+
+    #[test]
+    fn estimated_heap_bytes_grows_linearly() {
+        let per_node = size_of::<List<u64>>();
+        for len in [0, 1, 10, 1000] {
+            let list: List<u64> = (0..len).collect();
+            assert_eq!(estimated_heap_bytes(&list), len as usize * per_node);
+        }
+        //Doubling the length doubles the estimate.
+        let short: List<u64> = (0..50).collect();
+        let long: List<u64> = (0..100).collect();
+        assert_eq!(estimated_heap_bytes(&long), 2 * estimated_heap_bytes(&short));
+    }
+
+    #[test]
+    fn estimated_heap_bytes_leaves_out_what_the_elements_own() {
+        let numbers: List<i32> = (0..7).collect();
+        assert_eq!(estimated_heap_bytes(&numbers), numbers.owned_bytes());
+
+        let words: List<String> = ["a", "b", "c"].into_iter().map(String::from).collect();
+        assert_eq!(estimated_heap_bytes(&words), 3 * size_of::<List<String>>());
+        assert_eq!(words.owned_bytes(), estimated_heap_bytes(&words) + 3);
+    }
+}
@@ -0,0 +1,227 @@
+use std::cell::Cell;
+use std::mem::size_of;
+use std::ops::Deref;
+use std::rc::Rc;
+
+//The recursive enum from the start of the chapter. The Box is what gives it a known size, every
+// level only holds a pointer to the next one.
+#[derive(Debug)]
+pub enum HelloEnum {
+    Hello(Box<HelloEnum>),
+    Null,
+}
+
+//A cap on how many bytes may be handed out. Clones share the same remaining amount, so several
+// builders drawing from one budget cannot go over it together.
+#[derive(Clone)]
+pub struct MemoryBudget(Rc<Cell<usize>>);
+
+//Counted in levels rather than bytes. A depth from untrusted input can be so large that its size in
+// bytes does not fit in a usize, but the number of levels always does.
+#[derive(Debug, PartialEq)]
+pub struct BudgetExceeded {
+    pub requested_levels: usize,
+    pub affordable_levels: usize,
+}
+
+//Bytes taken out of a MemoryBudget. They go back into it when this is dropped.
+struct Charge {
+    budget: MemoryBudget,
+    bytes: usize,
+}
+
+//A value together with the bytes it was charged for. It derefs to the value, and dropping it drops
+// the value and refunds the budget at the same time.
+pub struct Budgeted<T> {
+    value: T,
+    _charge: Charge,
+}
+
+impl MemoryBudget {
+    pub fn new(bytes: usize) -> MemoryBudget {
+        MemoryBudget(Rc::new(Cell::new(bytes)))
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.0.get()
+    }
+
+    //The caller has already checked that the bytes fit.
+    fn charge(&self, bytes: usize) -> Charge {
+        self.0.set(self.remaining() - bytes);
+        Charge {
+            budget: self.clone(),
+            bytes,
+        }
+    }
+}
+
+impl BudgetExceeded {
+    //How many more levels the budget would have needed.
+    pub fn shortfall_levels(&self) -> usize {
+        self.requested_levels - self.affordable_levels
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.budget.0.set(self.budget.remaining() + self.bytes);
+    }
+}
+
+impl<T> Deref for Budgeted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl HelloEnum {
+    //A rough guess at what the allocator keeps next to every Box it hands out. The exact amount
+    // depends on the allocator, one word is a common size for its header.
+    pub const BOX_OVERHEAD: usize = size_of::<usize>();
+
+    //What one Hello level costs. Each level is one Box holding one HelloEnum, and the Box pointer
+    // itself is already part of the enum it sits in.
+    pub const LEVEL_BYTES: usize = size_of::<HelloEnum>() + HelloEnum::BOX_OVERHEAD;
+
+    //Builds `depth` Hello levels ending in Null. The depth might come from untrusted input, so the
+    // whole build is charged up front. A build that does not fit fails before a single Box is
+    // allocated, which means there is never a half built value to clean up.
+    //This returns a Budgeted<HelloEnum> instead of a plain HelloEnum. The bytes have to go back to
+    // the budget when the value is dropped, and the enum from the chapter has nowhere to keep the
+    // charge that does that.
+    pub fn try_from_depth(depth: usize, budget: &MemoryBudget) -> Result<Budgeted<HelloEnum>, BudgetExceeded> {
+        let affordable_levels = budget.remaining() / HelloEnum::LEVEL_BYTES;
+        if depth > affordable_levels {
+            return Err(BudgetExceeded {
+                requested_levels: depth,
+                affordable_levels,
+            });
+        }
+        let charge = budget.charge(depth * HelloEnum::LEVEL_BYTES);
+
+        let mut value = HelloEnum::Null;
+        for _ in 0..depth {
+            value = HelloEnum::Hello(Box::new(value));
+        }
+        Ok(Budgeted { value, _charge: charge })
+    }
+
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut current = self;
+        while let HelloEnum::Hello(next) = current {
+            depth += 1;
+            current = next;
+        }
+        depth
+    }
+}
+
+//A deep HelloEnum would otherwise be dropped one recursive call per level, the same problem List
+// has in list.rs. The levels are unlinked one at a time instead.
+impl Drop for HelloEnum {
+    fn drop(&mut self) {
+        let mut rest = match self {
+            HelloEnum::Hello(next) => std::mem::replace(&mut **next, HelloEnum::Null),
+            HelloEnum::Null => return,
+        };
+        while let HelloEnum::Hello(next) = &mut rest {
+            rest = std::mem::replace(&mut **next, HelloEnum::Null);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_budget_for_exactly_n_levels_fits_n_but_not_n_plus_one() {
+        let budget = MemoryBudget::new(5 * HelloEnum::LEVEL_BYTES);
+        let hello = HelloEnum::try_from_depth(5, &budget).unwrap();
+        assert_eq!(hello.depth(), 5);
+        assert_eq!(budget.remaining(), 0);
+        drop(hello);
+
+        let error = HelloEnum::try_from_depth(6, &budget).err().unwrap();
+        assert_eq!(
+            error,
+            BudgetExceeded {
+                requested_levels: 6,
+                affordable_levels: 5,
+            }
+        );
+        assert_eq!(error.shortfall_levels(), 1);
+    }
+
+    #[test]
+    fn a_depth_too_big_to_count_in_bytes_reports_the_real_shortfall() {
+        let budget = MemoryBudget::new(3 * HelloEnum::LEVEL_BYTES);
+        let error = HelloEnum::try_from_depth(usize::MAX, &budget).err().unwrap();
+        assert_eq!(
+            error,
+            BudgetExceeded {
+                requested_levels: usize::MAX,
+                affordable_levels: 3,
+            }
+        );
+        assert_eq!(error.shortfall_levels(), usize::MAX - 3);
+    }
+
+    #[test]
+    fn every_level_is_charged_for_its_box_as_well() {
+        assert!(HelloEnum::LEVEL_BYTES > size_of::<HelloEnum>());
+        let budget = MemoryBudget::new(2 * size_of::<HelloEnum>());
+        assert!(HelloEnum::try_from_depth(2, &budget).is_err());
+    }
+
+    #[test]
+    fn a_failed_build_leaves_the_budget_untouched() {
+        let budget = MemoryBudget::new(3 * HelloEnum::LEVEL_BYTES);
+        assert!(HelloEnum::try_from_depth(4, &budget).is_err());
+        assert_eq!(budget.remaining(), 3 * HelloEnum::LEVEL_BYTES);
+        assert!(HelloEnum::try_from_depth(usize::MAX, &budget).is_err());
+        assert_eq!(budget.remaining(), 3 * HelloEnum::LEVEL_BYTES);
+    }
+
+    #[test]
+    fn builders_sharing_a_budget_cannot_go_over_it_together() {
+        let budget = MemoryBudget::new(10 * HelloEnum::LEVEL_BYTES);
+        let first = HelloEnum::try_from_depth(6, &budget).unwrap();
+        let second = HelloEnum::try_from_depth(4, &budget.clone()).unwrap();
+        assert!(HelloEnum::try_from_depth(1, &budget).is_err());
+
+        drop(first);
+        assert_eq!(budget.remaining(), 6 * HelloEnum::LEVEL_BYTES);
+        assert_eq!(second.depth(), 4);
+    }
+
+    #[test]
+    fn a_zero_budget_only_fits_depth_zero() {
+        let budget = MemoryBudget::new(0);
+        assert!(HelloEnum::try_from_depth(1, &budget).is_err());
+        let null = HelloEnum::try_from_depth(0, &budget).unwrap();
+        assert!(matches!(*null, HelloEnum::Null));
+    }
+
+    #[test]
+    fn dropping_refunds_so_sequential_builds_reuse_the_budget() {
+        let budget = MemoryBudget::new(100 * HelloEnum::LEVEL_BYTES);
+        for _ in 0..10 {
+            let hello = HelloEnum::try_from_depth(100, &budget).unwrap();
+            assert_eq!(budget.remaining(), 0);
+            drop(hello);
+        }
+        assert_eq!(budget.remaining(), 100 * HelloEnum::LEVEL_BYTES);
+    }
+
+    #[test]
+    fn a_very_deep_hello_drops_without_overflowing() {
+        let budget = MemoryBudget::new(1_000_000 * HelloEnum::LEVEL_BYTES);
+        let hello = HelloEnum::try_from_depth(1_000_000, &budget).unwrap();
+        assert_eq!(hello.depth(), 1_000_000);
+    }
+}
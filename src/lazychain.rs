@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+//A computation that has not run yet. It is nothing more than a Box<dyn FnOnce() -> T>, and each
+// combinator wraps the previous box in a new one. Nothing runs until force() is called, and a Lazy
+// that is dropped without being forced never runs at all.
+pub struct Lazy<T> {
+    compute: Box<dyn FnOnce() -> T>,
+}
+
+impl<T: 'static> Lazy<T> {
+    pub fn new(compute: impl FnOnce() -> T + 'static) -> Lazy<T> {
+        Lazy {
+            compute: Box::new(compute),
+        }
+    }
+
+    pub fn force(self) -> T {
+        (self.compute)()
+    }
+
+    pub fn map<U: 'static>(self, f: impl FnOnce(T) -> U + 'static) -> Lazy<U> {
+        Lazy::new(move || f(self.force()))
+    }
+
+    pub fn and_then<U: 'static>(self, f: impl FnOnce(T) -> Lazy<U> + 'static) -> Lazy<U> {
+        Lazy::new(move || f(self.force()).force())
+    }
+
+    //Forces this computation and then the other one.
+    pub fn zip<U: 'static>(self, other: Lazy<U>) -> Lazy<(T, U)> {
+        Lazy::new(move || (self.force(), other.force()))
+    }
+
+    //Turns the computation into one that can be shared by cloning, and that only ever runs once no
+    // matter how many of the clones force it.
+    pub fn shared(self) -> SharedLazy<T> {
+        SharedLazy {
+            state: Rc::new(RefCell::new(LazyState::Pending(self))),
+        }
+    }
+}
+
+enum LazyState<T> {
+    Pending(Lazy<T>),
+    Done(T),
+    //The computation is running right now, or it panicked and never finished. Either way there is
+    // no value to hand out and the closure is gone. A computation that forces its own SharedLazy
+    // gets Err(Poisoned) back from that inner call, but this is not permanent. The outer force
+    // still stores whatever the computation returns, so only a panic leaves the state poisoned.
+    Poisoned,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Poisoned;
+
+#[derive(Clone)]
+pub struct SharedLazy<T> {
+    state: Rc<RefCell<LazyState<T>>>,
+}
+
+impl<T: Clone + 'static> SharedLazy<T> {
+    //The first call runs the computation and stores the result, every later call clones the
+    // stored result. The state is marked as poisoned while the computation runs, and the RefCell
+    // is not borrowed at that point, so a panic leaves the state poisoned rather than pending.
+    pub fn force(&self) -> Result<T, Poisoned> {
+        //The state is taken out in its own statement. Matching on the borrow_mut() directly would
+        // keep the RefMut alive for the whole match (see temporaries.rs) and the arms below would
+        // hit an already borrowed RefCell.
+        let state = std::mem::replace(&mut *self.state.borrow_mut(), LazyState::Poisoned);
+        let pending = match state {
+            LazyState::Pending(lazy) => lazy,
+            LazyState::Done(value) => {
+                *self.state.borrow_mut() = LazyState::Done(value.clone());
+                return Ok(value);
+            }
+            LazyState::Poisoned => return Err(Poisoned),
+        };
+
+        let value = pending.force();
+        *self.state.borrow_mut() = LazyState::Done(value.clone());
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn counted(runs: &Rc<Cell<usize>>, value: i32) -> Lazy<i32> {
+        let runs = Rc::clone(runs);
+        Lazy::new(move || {
+            runs.set(runs.get() + 1);
+            value
+        })
+    }
+
+    #[test]
+    fn shared_handles_run_the_computation_once() {
+        let runs = Rc::new(Cell::new(0));
+        let first = counted(&runs, 5).shared();
+        let second = first.clone();
+        assert_eq!(first.force(), Ok(5));
+        assert_eq!(second.force(), Ok(5));
+        assert_eq!(first.force(), Ok(5));
+        assert_eq!(runs.get(), 1);
+    }
+
+    #[test]
+    fn map_and_and_then_run_in_chain_order() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let (a, b, c) = (Rc::clone(&order), Rc::clone(&order), Rc::clone(&order));
+        let chain = Lazy::new(move || {
+            a.borrow_mut().push("new");
+            1
+        })
+        .map(move |value| {
+            b.borrow_mut().push("map");
+            value + 1
+        })
+        .and_then(move |value| {
+            c.borrow_mut().push("and_then");
+            Lazy::new(move || value * 10)
+        });
+
+        assert!(order.borrow().is_empty());
+        assert_eq!(chain.force(), 20);
+        assert_eq!(*order.borrow(), ["new", "map", "and_then"]);
+    }
+
+    #[test]
+    fn zip_forces_both_sides() {
+        let runs = Rc::new(Cell::new(0));
+        assert_eq!(counted(&runs, 1).zip(counted(&runs, 2)).force(), (1, 2));
+        assert_eq!(runs.get(), 2);
+    }
+
+    #[test]
+    fn a_panicking_computation_stays_poisoned() {
+        let failing = Lazy::new(|| -> i32 { panic!("stage failed") }).shared();
+        let first = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| failing.force()));
+        assert!(first.is_err());
+        assert_eq!(failing.force(), Err(Poisoned));
+        assert_eq!(failing.clone().force(), Err(Poisoned));
+    }
+
+    #[test]
+    fn a_reentrant_force_sees_poisoned_but_the_outer_force_still_finishes() {
+        let inner_result = Rc::new(RefCell::new(None));
+        let slot: Rc<RefCell<Option<SharedLazy<i32>>>> = Rc::new(RefCell::new(None));
+        let (inner, handle) = (Rc::clone(&inner_result), Rc::clone(&slot));
+        let lazy = Lazy::new(move || {
+            let this = handle.borrow().clone().expect("set before forcing");
+            *inner.borrow_mut() = Some(this.force());
+            7
+        })
+        .shared();
+        *slot.borrow_mut() = Some(lazy.clone());
+
+        assert_eq!(lazy.force(), Ok(7));
+        assert_eq!(*inner_result.borrow(), Some(Err(Poisoned)));
+        assert_eq!(lazy.force(), Ok(7));
+    }
+
+    #[test]
+    fn an_unforced_chain_never_runs() {
+        let runs = Rc::new(Cell::new(0));
+        drop(counted(&runs, 1).map(|value| value + 1));
+        drop(counted(&runs, 1).shared());
+        assert_eq!(runs.get(), 0);
+    }
+}
@@ -0,0 +1,52 @@
+//Everything the chapter walkthrough in main.rs builds on. Keeping it in a library means the
+// modules can be unit tested and doc tested, including the examples that must not compile.
+
+pub mod automaton;
+pub mod bitset;
+pub mod channel;
+pub mod clipboard;
+pub mod compare;
+pub mod counter;
+pub mod cow_vec;
+pub mod dedup;
+pub mod defer_queue;
+pub mod deque;
+pub mod dll;
+pub mod drop_edge_cases;
+pub mod drop_log;
+pub mod experiments;
+pub mod expr;
+pub mod fd;
+pub mod find_if;
+pub mod freeze;
+pub mod graph;
+pub mod heap_size;
+pub mod hello;
+pub mod lazychain;
+pub mod list;
+pub mod logger;
+#[macro_use]
+mod macros;
+pub mod memo;
+pub mod mistakes;
+pub mod observable;
+pub mod ordered_drop;
+pub mod pmap;
+pub mod pqueue;
+pub mod rc_tree;
+pub mod rc_utils;
+pub mod refcell_utils;
+pub mod rope;
+pub mod shared_iter;
+pub mod shared_vec;
+pub mod slab;
+pub mod sparse;
+pub mod stack;
+pub mod timed_drop;
+pub mod timer;
+pub mod temporaries;
+pub mod trace;
+pub mod tree;
+pub mod versioned_tree;
+pub mod weak_handle;
+pub mod zip_shared;
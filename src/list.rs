@@ -0,0 +1,251 @@
+use std::mem::ManuallyDrop;
+use std::ptr;
+
+//The cons list from the book, made generic over the element type. Each element lives on the heap
+// inside of a Box so that the compiler can calculate the size of List<T> at compile time.
+#[derive(Debug, PartialEq)]
+pub enum List<T> {
+    Cons(T, Box<List<T>>),
+    Nil,
+}
+
+impl<T> List<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { current: self }
+    }
+
+    //Inserts every element of `other` so that the first one ends up at index `at`. If `at` is past
+    // the end of the list, `other` is simply appended. No nodes are re-allocated, the boxes are
+    // only re-linked.
+    pub fn splice(mut self, at: usize, other: List<T>) -> List<T> {
+        let mut cursor = &mut self;
+        for _ in 0..at {
+            match cursor {
+                List::Cons(_, next) => cursor = next,
+                List::Nil => break,
+            }
+        }
+
+        //The tail that used to follow the cursor is swapped out for `other`. Then the end of
+        // `other` is found and the tail is re-attached there.
+        let tail = std::mem::replace(cursor, other);
+        loop {
+            match cursor {
+                List::Cons(_, next) => cursor = next,
+                end => {
+                    *end = tail;
+                    break;
+                }
+            }
+        }
+
+        self
+    }
+
+    //Splits off the first element and the Box holding the rest of the list. List has its own Drop,
+    // so a pattern is not allowed to move the fields out. The list is wrapped in ManuallyDrop
+    // instead, which means its Drop never runs, and each field is read out of it exactly once.
+    fn into_parts(self) -> Option<(T, Box<List<T>>)> {
+        let list = ManuallyDrop::new(self);
+        match &*list {
+            //Safe because `list` is never used or dropped again, so both fields have exactly one
+            // owner after this.
+            List::Cons(value, next) => unsafe { Some((ptr::read(value), ptr::read(next))) },
+            List::Nil => None,
+        }
+    }
+}
+
+//The derived drop would drop the first Box, which drops the next Box and so on, one stack frame per
+// element, so a long enough list overflows the stack. Just like the Stack in stack.rs, the nodes
+// are unlinked one at a time instead. Each node is dropped with a Nil behind it, so its own drop
+// has nothing left to walk.
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        let mut rest = match self {
+            List::Cons(_, next) => std::mem::replace(&mut **next, List::Nil),
+            List::Nil => return,
+        };
+        while let List::Cons(_, next) = &mut rest {
+            rest = std::mem::replace(&mut **next, List::Nil);
+        }
+    }
+}
+
+//Merges two lists that are already sorted into one sorted list. The heads are compared in a loop
+// instead of recursively, and each node's Box is moved over to the merged list rather than being
+// re-allocated. When the heads are equal the element from `a` goes first.
+pub fn merge_sorted<T: Ord>(mut a: List<T>, mut b: List<T>) -> List<T> {
+    let mut merged = List::Nil;
+    let mut tail = &mut merged;
+
+    loop {
+        let source = match (&a, &b) {
+            (List::Cons(x, _), List::Cons(y, _)) => {
+                if x <= y {
+                    &mut a
+                } else {
+                    &mut b
+                }
+            }
+            (List::Cons(..), List::Nil) => {
+                *tail = a;
+                break;
+            }
+            (List::Nil, _) => {
+                *tail = b;
+                break;
+            }
+        };
+
+        if let Some((value, mut rest)) = std::mem::replace(source, List::Nil).into_parts() {
+            *source = std::mem::replace(&mut *rest, List::Nil);
+            *tail = List::Cons(value, rest);
+            if let List::Cons(_, next) = tail {
+                tail = next;
+            }
+        }
+    }
+
+    merged
+}
+
+//Pairs up the elements of both lists, stopping at the end of the shorter one. Any extra elements
+// in the longer list are dropped, through the iterative Drop above. Like merge_sorted(), this works
+// in a loop instead of recursing.
+pub fn zip<A, B>(mut a: List<A>, mut b: List<B>) -> List<(A, B)> {
+    let mut zipped = List::Nil;
+    let mut tail = &mut zipped;
+
+    while let (Some((x, next_a)), Some((y, next_b))) = (a.into_parts(), b.into_parts()) {
+        a = *next_a;
+        b = *next_b;
+        *tail = List::Cons((x, y), Box::new(List::Nil));
+        if let List::Cons(_, next) = tail {
+            tail = next;
+        }
+    }
+
+    zipped
+}
+
+//The list is built from the back so that each new element can be placed in front of the already
+// constructed part. Building it this way needs no recursion, and together with the iterative Drop
+// that means even a very long list can be built and dropped without overflowing the stack.
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        let mut list = List::Nil;
+        for item in items.into_iter().rev() {
+            list = List::Cons(item, Box::new(list));
+        }
+        list
+    }
+}
+
+pub struct Iter<'a, T> {
+    current: &'a List<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.current {
+            List::Cons(value, next) => {
+                self.current = next;
+                Some(value)
+            }
+            List::Nil => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list;
+    use std::rc::Rc;
+
+    fn values(list: &List<i32>) -> Vec<i32> {
+        list.iter().copied().collect()
+    }
+
+    #[test]
+    fn splice_in_the_middle() {
+        assert_eq!(values(&list![1, 2, 3].splice(1, list![10, 11])), [1, 10, 11, 2, 3]);
+    }
+
+    #[test]
+    fn splice_at_the_front_and_the_end() {
+        assert_eq!(values(&list![1, 2, 3].splice(0, list![10, 11])), [10, 11, 1, 2, 3]);
+        assert_eq!(values(&list![1, 2, 3].splice(3, list![10, 11])), [1, 2, 3, 10, 11]);
+        assert_eq!(values(&list![1, 2, 3].splice(99, list![10, 11])), [1, 2, 3, 10, 11]);
+    }
+
+    #[test]
+    fn splice_with_empty_lists() {
+        assert_eq!(values(&list![].splice(0, list![10, 11])), [10, 11]);
+        assert_eq!(values(&list![1, 2].splice(1, list![])), [1, 2]);
+    }
+
+    #[test]
+    fn collecting_and_dropping_a_long_list() {
+        let list: List<u32> = (0..1_000_000).collect();
+        assert_eq!(list.iter().count(), 1_000_000);
+        drop(list);
+    }
+
+    #[test]
+    fn merge_sorted_interleaves_two_sorted_lists() {
+        let merged = merge_sorted(list![1, 3, 5], list![2, 4, 6]);
+        assert_eq!(values(&merged), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn merge_sorted_with_an_empty_list() {
+        assert_eq!(values(&merge_sorted(list![], list![7, 8])), [7, 8]);
+        assert_eq!(values(&merge_sorted(list![7, 8], list![])), [7, 8]);
+        assert_eq!(merge_sorted(list![], list![]), List::<i32>::Nil);
+    }
+
+    #[test]
+    fn merge_sorted_puts_equal_values_from_the_first_list_first() {
+        let a = list![(1, 'a'), (2, 'a')];
+        let b = list![(1, 'b'), (2, 'b')];
+        let merged: Vec<_> = merge_sorted(a, b).iter().map(|&(_, from)| from).collect();
+        assert_eq!(merged, ['a', 'b', 'a', 'b']);
+    }
+
+    #[test]
+    fn merge_sorted_builds_and_drops_a_long_list() {
+        let evens: List<u32> = (0..500_000).map(|n| n * 2).collect();
+        let odds: List<u32> = (0..500_000).map(|n| n * 2 + 1).collect();
+        let merged = merge_sorted(evens, odds);
+        assert!(merged.iter().copied().eq(0..1_000_000));
+        drop(merged);
+    }
+
+    #[test]
+    fn zip_drops_the_rest_of_a_long_list_without_recursing() {
+        let long: List<u32> = (0..1_000_000).collect();
+        let short: List<u32> = (0..10).collect();
+        let zipped = zip(long, short);
+        assert_eq!(zipped.iter().count(), 10);
+        assert_eq!(zipped.iter().last(), Some(&(9, 9)));
+    }
+
+    #[test]
+    fn zip_stops_at_the_shorter_list() {
+        assert_eq!(zip(list![1, 2, 3], list!['a', 'b']), list![(1, 'a'), (2, 'b')]);
+        assert_eq!(zip(List::<i32>::Nil, list![1]), List::Nil);
+    }
+
+    #[test]
+    fn zip_drops_the_extra_element_of_the_longer_list() {
+        let extra = Rc::new(3);
+        let zipped = zip(list![Rc::new(1), Rc::new(2), Rc::clone(&extra)], list!['a', 'b']);
+        assert_eq!(zipped.iter().count(), 2);
+        assert_eq!(Rc::strong_count(&extra), 1);
+    }
+}
@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+struct LoggerState {
+    min_level: Level,
+    buffer: Vec<String>,
+}
+
+//A logger that can be handed to several parts of a program by cloning it. All of the clones share
+// one LoggerState, so changing the level through one handle changes it for every handle.
+#[derive(Clone)]
+pub struct Logger {
+    state: Rc<RefCell<LoggerState>>,
+}
+
+impl Logger {
+    pub fn new(min_level: Level) -> Logger {
+        Logger {
+            state: Rc::new(RefCell::new(LoggerState {
+                min_level,
+                buffer: Vec::new(),
+            })),
+        }
+    }
+
+    //Messages below the current minimum level are ignored.
+    pub fn log(&self, level: Level, message: &str) {
+        let mut state = self.state.borrow_mut();
+        if level >= state.min_level {
+            state.buffer.push(format!("[{:?}] {}", level, message));
+        }
+    }
+
+    pub fn set_level(&self, level: Level) {
+        self.state.borrow_mut().min_level = level;
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.state.borrow().buffer.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_messages_at_or_above_the_level_are_kept() {
+        let logger = Logger::new(Level::Info);
+        logger.log(Level::Debug, "hidden");
+        logger.log(Level::Info, "shown");
+        logger.log(Level::Error, "also shown");
+        assert_eq!(logger.messages(), ["[Info] shown", "[Error] also shown"]);
+    }
+
+    #[test]
+    fn changing_the_level_through_one_handle_affects_every_handle() {
+        let logger = Logger::new(Level::Debug);
+        let other = logger.clone();
+        other.log(Level::Debug, "first");
+
+        logger.set_level(Level::Warn);
+        other.log(Level::Info, "dropped");
+        other.log(Level::Warn, "second");
+
+        logger.set_level(Level::Debug);
+        logger.log(Level::Debug, "third");
+        assert_eq!(other.messages(), ["[Debug] first", "[Warn] second", "[Debug] third"]);
+    }
+
+    #[test]
+    fn nothing_below_error_gets_through_an_error_logger() {
+        let logger = Logger::new(Level::Error);
+        for level in [Level::Debug, Level::Info, Level::Warn] {
+            logger.log(level, "ignored");
+        }
+        assert!(logger.messages().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//Shorthands for building the shared structures in this crate. Spelling out every
+// Rc::new(RefCell::new(...)) or add_child() call in a demo hides what the demo is actually about.
+// Each macro only expands into the same calls that would otherwise be written by hand.
+
+//rc_refcell!(value) is Rc::new(RefCell::new(value)).
+#[macro_export]
+macro_rules! rc_refcell {
+    ($value:expr $(,)?) => {
+        ::std::rc::Rc::new(::std::cell::RefCell::new($value))
+    };
+}
+
+//list![1, 2, 3] builds the cons list Cons(1, Box::new(Cons(2, Box::new(Cons(3, Box::new(Nil)))))),
+// and list![] is Nil. A trailing comma is allowed.
+#[macro_export]
+macro_rules! list {
+    () => {
+        $crate::list::List::Nil
+    };
+    ($first:expr $(, $rest:expr)* $(,)?) => {
+        $crate::list::List::Cons($first, Box::new($crate::list![$($rest),*]))
+    };
+}
+
+//tree!(1 => [2 => [4, 5], 3]) builds an rc_tree::Node tree. A value followed by `=> [...]` gets
+// the nodes inside the brackets as its children, in order, and a plain value is a leaf. Every child
+// is attached with add_child(), so the parent links are set up the same way as usual.
+//Anything that is not a value, or a value followed by a bracketed list, is rejected when compiling.
+/// ```compile_fail
+/// let root = rust_book_chapter_15::tree!(1 => [2 =>, 3]);
+/// ```
+/// ```compile_fail
+/// let root = rust_book_chapter_15::tree!(1 => 2);
+/// ```
+#[macro_export]
+macro_rules! tree {
+    (@children $parent:ident;) => {};
+    (@children $parent:ident; $value:expr => [$($children:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::rc_tree::Node::add_child(&$parent, &$crate::tree!($value => [$($children)*]));
+        $crate::tree!(@children $parent; $($($rest)*)?);
+    };
+    (@children $parent:ident; $value:expr $(, $($rest:tt)*)?) => {
+        $crate::rc_tree::Node::add_child(&$parent, &$crate::rc_tree::Node::new($value));
+        $crate::tree!(@children $parent; $($($rest)*)?);
+    };
+    ($value:expr => [$($children:tt)*]) => {{
+        let node = $crate::rc_tree::Node::new($value);
+        $crate::tree!(@children node; $($children)*);
+        node
+    }};
+    ($value:expr) => {
+        $crate::rc_tree::Node::new($value)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::list::List;
+    use crate::rc_tree::Node;
+
+    #[test]
+    fn tree_matches_the_same_tree_built_with_add_child() {
+        let by_hand = Node::new(1);
+        let two = Node::new(2);
+        Node::add_child(&by_hand, &two);
+        Node::add_child(&two, &Node::new(4));
+        Node::add_child(&two, &Node::new(5));
+        Node::add_child(&by_hand, &Node::new(3));
+
+        let by_macro = tree!(1 => [2 => [4, 5], 3]);
+        assert!(Node::structural_eq(&by_hand, &by_macro));
+    }
+
+    #[test]
+    fn tree_wires_up_the_parent_links() {
+        let root = tree!(1 => [2 => [4]]);
+        let two = Rc::clone(&root.children()[0]);
+        let four = Rc::clone(&two.children()[0]);
+        assert!(Rc::ptr_eq(&four.parent().unwrap(), &two));
+        assert!(Rc::ptr_eq(&two.parent().unwrap(), &root));
+        assert!(root.parent().is_none());
+    }
+
+    #[test]
+    fn tree_nests_five_levels_deep() {
+        let mut current = tree!(1 => [2 => [3 => [4 => [5]]]]);
+        let mut values = vec![current.value];
+        while let Some(child) = current.children().first().cloned() {
+            values.push(child.value);
+            current = child;
+        }
+        assert_eq!(values, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn tree_single_node_and_empty_children() {
+        let single = tree!("alone");
+        assert!(single.children().is_empty());
+        let empty_children = tree!("alone" => []);
+        assert!(Node::structural_eq(&single, &empty_children));
+    }
+
+    #[test]
+    fn trailing_commas_are_accepted() {
+        assert!(Node::structural_eq(&tree!(1 => [2 => [4, 5,], 3,]), &tree!(1 => [2 => [4, 5], 3])));
+        assert_eq!(list![1, 2, 3,], list![1, 2, 3]);
+        assert_eq!(*rc_refcell!(5,).borrow(), 5);
+    }
+
+    #[test]
+    fn list_matches_the_cons_list_written_out() {
+        let by_hand = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Cons(3, Box::new(List::Nil))))));
+        assert_eq!(list![1, 2, 3], by_hand);
+        assert_eq!(list![], List::<i32>::Nil);
+        assert_eq!(list![list![1], list![]], List::Cons(list![1], Box::new(list![List::Nil])));
+    }
+
+    #[test]
+    fn rc_refcell_shares_one_value() {
+        let shared: Rc<RefCell<Vec<i32>>> = rc_refcell!(Vec::new());
+        let other = Rc::clone(&shared);
+        other.borrow_mut().push(1);
+        assert_eq!(*shared.borrow(), [1]);
+        assert_eq!(Rc::strong_count(&shared), 2);
+    }
+}
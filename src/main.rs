@@ -1,18 +1,150 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
 use std::ops::Deref;
 use std::rc::{Rc, Weak};
 
+use rust_book_chapter_15::{
+    automaton, drop_edge_cases, list, mistakes, rc_refcell, rc_tree, temporaries, trace, tree,
+};
+
+use rust_book_chapter_15::bitset::SharedBitSet;
+use rust_book_chapter_15::channel::LocalChannel;
+use rust_book_chapter_15::clipboard::Clipboard;
+use rust_book_chapter_15::compare::compile_vs_runtime;
+use rust_book_chapter_15::compare::ownership_styles::{self, AddressBook, BorrowedBook, Contact, RcBook};
+use rust_book_chapter_15::counter::{bump_all, Counter};
+use rust_book_chapter_15::cow_vec::CowVec;
+use rust_book_chapter_15::dedup::{content_hash, deduplicate};
+use rust_book_chapter_15::defer_queue::TaskQueue;
+use rust_book_chapter_15::deque::SharedDeque;
+use rust_book_chapter_15::dll::DoublyLinkedList;
+use rust_book_chapter_15::drop_log::{DropLog, Logged, ScopeGuard};
+use rust_book_chapter_15::experiments::{churn, ChurnReport, DesignFlavor};
+use rust_book_chapter_15::expr::{run, Expr};
+use rust_book_chapter_15::fd::{CloseCounter, SharedFd};
+use rust_book_chapter_15::find_if::find_if;
+use rust_book_chapter_15::freeze::{Freezable, FrozenNode};
+use rust_book_chapter_15::graph::{
+    nodes_in_cycles, ownership_report, replace_value, shortest_path, to_adjacency_list, weighted_reach,
+    GraphNode,
+};
+use rust_book_chapter_15::heap_size::{estimated_heap_bytes, reachable_bytes, HeapSize};
+use rust_book_chapter_15::hello::{HelloEnum, MemoryBudget};
+use rust_book_chapter_15::lazychain::Lazy;
+use rust_book_chapter_15::list::{merge_sorted, zip, List};
+use rust_book_chapter_15::logger::{Level, Logger};
+use rust_book_chapter_15::memo::WeakMemo;
+use rust_book_chapter_15::observable::Observable;
+use rust_book_chapter_15::ordered_drop::{Part, Service};
+use rust_book_chapter_15::pmap::PMap;
+use rust_book_chapter_15::pqueue::SharedPQueue;
+use rust_book_chapter_15::rc_utils::{distinct_allocations, downgrade_limited, drop_and_count, require_alive};
+use rust_book_chapter_15::shared_iter::SharedIter;
+use rust_book_chapter_15::shared_vec::{IndexOrBorrowError, SharedVec};
+use rust_book_chapter_15::slab::HandleSlab;
+use rust_book_chapter_15::rope::Rope;
+use rust_book_chapter_15::refcell_utils::{take_if, with_retry, with_retry_yielding};
+use rust_book_chapter_15::sparse::SparseMatrix;
+use rust_book_chapter_15::stack::Stack;
+use rust_book_chapter_15::timed_drop::{clear_drop_log, drop_log, TimedDrop};
+use rust_book_chapter_15::timer::TimerWheel;
+use rust_book_chapter_15::tree::{box_tree_to_rc, list_to_balanced_tree, BoundedTree, DepthLimitReached, Node};
+use rust_book_chapter_15::versioned_tree::VersionedTree;
+use rust_book_chapter_15::weak_handle::WeakHandle;
+use rust_book_chapter_15::zip_shared::{structural_zip, zip_trees};
+
 fn main() {
+    //Hidden flag used by drop_edge_cases::run_crash_demo_in_subprocess(). It runs the drop that
+    // aborts the process, so it has to happen in a separate process from the rest of the demo.
+    if std::env::args().any(|arg| arg == "--crash-demo") {
+        drop_edge_cases::crash_demo();
+        return;
+    }
+
+    //`--trace rc` or `--trace refcell` re-runs just that demo, printing a numbered line for every
+    // operation on the tracked values instead of the normal output.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--trace") {
+        let demo = args.get(position + 1).map_or("", String::as_str);
+        match trace::run_traced(demo) {
+            Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
     //In Rust, they have smart pointers that offer additional functionality compared to the standard
     // references. The pointers often own the data they point to. String and Vec<T> are both
     // examples of smart pointers.
+
+    //Anything that should only happen after every section has finished is pushed onto this queue.
+    let deferred = TaskQueue::new();
+
     using_box_to_point_to_data_on_the_heap();
     treating_smart_pointers_like_regular_references_with_deref_trait();
     running_code_on_cleanup_with_the_drop_trait();
     rc_the_reference_counted_smart_pointer();
     refcell_and_the_interior_mutability_pattern();
-    reference_cycles_can_leak_memory();
+    reference_cycles_can_leak_memory(&deferred);
+    weak_count_lifecycle();
+    working_with_a_generic_cons_list();
+    scheduling_callbacks_on_a_timer_wheel();
+    draining_a_box_based_stack();
+    sharing_nodes_in_an_rc_graph();
+    memoizing_with_weak_references();
+    sharing_a_clipboard();
+    comparing_lifetimes_with_rc();
+    timing_when_values_are_dropped();
+    limiting_weak_references();
+    searching_a_boxed_expression_tree();
+    sharing_a_bit_set();
+    analysing_a_boxed_binary_tree();
+    resuming_shared_iterators_independently();
+    choosing_the_drop_order_of_fields();
+    caching_paths_in_an_rc_tree();
+    deferring_work_with_a_task_queue();
+    temporary_lifetimes();
+    counting_freed_allocations();
+    sharing_a_sparse_matrix();
+    filtering_a_shared_logger();
+    zipping_two_shared_trees();
+    freezing_a_shared_tree();
+    copying_a_vector_on_write();
+    nesting_drop_log_scopes();
+    chaining_lazy_computations();
+    taking_from_a_refcell_conditionally();
+    sharing_a_priority_queue();
+    comparing_compile_time_and_runtime_borrow_errors();
+    retrying_a_failed_borrow();
+    invalidating_weak_handles();
+    covering_drop_edge_cases();
+    bumping_shared_counters_once();
+    reversing_a_doubly_linked_list();
+    splitting_and_appending_doubly_linked_lists();
+    rearranging_children_in_an_rc_tree();
+    batching_observer_notifications();
+    common_mistakes();
+    building_strings_with_a_rope();
+    deduplicating_frozen_subtrees();
+    naming_nodes_with_stable_handles();
+    freezing_a_value_while_a_guard_lives();
+    indexing_a_shared_vec_through_guards();
+    sharing_a_deque_between_two_handles();
+    finding_the_nodes_on_cycles();
+    searching_an_rc_tree_with_predicates();
+    estimating_heap_usage(&deferred);
+    closing_a_shared_descriptor_once();
+    stepping_a_grid_of_shared_cells();
+    passing_values_through_a_bounded_channel();
+    building_structures_with_macros();
+    sharing_subtrees_between_map_versions();
+    undoing_edits_to_a_versioned_tree();
+    comparing_designs_under_churn(&deferred);
+
+    let summary = deferred.run_all();
+    println!("deferred tasks executed: {} cancelled: {}", summary.executed, summary.cancelled);
 }
 
 fn using_box_to_point_to_data_on_the_heap() {
@@ -51,13 +183,7 @@ fn using_box_to_point_to_data_on_the_heap() {
     println!("my_hello.hello: {:?}", my_hello.hello);
 
     //It should be noted that the same thing as above can be accomplished with an enum instead of
-    // nesting an Option<T> inside of the struct.
-    #[derive(Debug)]
-    enum HelloEnum {
-        Hello(Box<HelloEnum>),
-        Null,
-    }
-
+    // nesting an Option<T> inside of the struct. HelloEnum lives in hello.rs.
     let my_hello = HelloEnum::Hello(
         Box::new(
             HelloEnum::Null
@@ -65,6 +191,21 @@ fn using_box_to_point_to_data_on_the_heap() {
     );
 
     println!("my_hello: {:?}", my_hello);
+
+    //When the depth comes from somewhere untrusted, try_from_depth() charges every level to a
+    // MemoryBudget first. Clones of a budget share it, so two builds together cannot go over it.
+    let budget = MemoryBudget::new(10 * HelloEnum::LEVEL_BYTES);
+    let first = HelloEnum::try_from_depth(6, &budget);
+    let second = HelloEnum::try_from_depth(6, &budget.clone());
+    println!(
+        "first depth: {:?} second: {:?}",
+        first.as_ref().map(|hello| hello.depth()).ok(),
+        second.as_ref().err()
+    );
+
+    //Dropping a build gives its bytes back.
+    drop(first);
+    println!("remaining after drop: {} levels", budget.remaining() / HelloEnum::LEVEL_BYTES);
 }
 
 fn treating_smart_pointers_like_regular_references_with_deref_trait() {
@@ -123,45 +264,55 @@ fn running_code_on_cleanup_with_the_drop_trait() {
     //Drop lets me customize what happens when a value is about to go out of scope. This seems to be
     // similar to a deconstructor in c++. Just like in c++, this is often used to deallocate memory
     // for the object.
-
-    #[derive(Debug)]
+    //Instead of printing straight away, each Square records its drop in a DropLog (drop_log.rs).
+    // That way the drops can be shown inside the scope they happened in.
     struct Square {
         side: isize,
+        log: DropLog,
     }
 
     impl Drop for Square {
         fn drop(&mut self) {
-            println!("Square {:?} dropped", self);
+            self.log.record(format!("Square {} dropped", self.side));
         }
     }
 
-    println!("Before scope");
+    let log = DropLog::new();
+    log.record("before scope");
     {
+        //The block is wrapped in a named scope. The scope guard is declared first, so it is dropped
+        // last and both squares show up inside the scope.
+        let _scope = log.scope("inner block");
+
         //Note that the objects are deallocated in the reverse order they are allocated in. I
         // assume that this is because they are popped from the stack as they are removed.
         let square_one = Square {
             side: 5,
+            log: log.clone(),
         };
 
         let square_two = Square {
             side: 1,
+            log: log.clone(),
         };
 
-        println!("squares created {:?} {:?}", square_one, square_two);
+        log.record(format!("squares created {} {}", square_one.side, square_two.side));
     }
-    println!("After scope");
+    log.record("after scope");
 
     let square_three = Square {
         side: 2,
+        log: log.clone(),
     };
 
     //The drop function cannot be manually called here. Instead Rust provides a function called
     // std::mem::drop that can be used to drop.
     // square_three.drop() //Invalid, will cause a compiler error.
 
-    println!("Before square_three dropped");
+    log.record("before square_three dropped");
     drop(square_three);
-    println!("After square_three dropped");
+    log.record("after square_three dropped");
+    print!("{}", log.render_tree());
 }
 
 fn rc_the_reference_counted_smart_pointer() {
@@ -175,7 +326,7 @@ fn rc_the_reference_counted_smart_pointer() {
     // let b = a;
     // let c = a;
 
-    let mut a = Rc::new(String::from("String value"));
+    let a = Rc::new(String::from("String value"));
     //Note that Rc::clone is used instead of a.clone(). This is a convention in Rust to be explicit
     // that it is an Rc<T> object that is being cloned. This is important because clone on Rc<T>
     // does not work the same was it does for most objects. For most object it makes a deep copy of
@@ -186,7 +337,8 @@ fn rc_the_reference_counted_smart_pointer() {
     println!("a: {} b: {} c: {}", a, b, c);
 
     //The reference count can be checked with Rc::strong_count. Rc::weak_count has something to do
-    // with creating a weak ptr, but that isn't covered yet.
+    // with creating a weak ptr, but that isn't covered yet. See weak_count_lifecycle() for the
+    // full walk through once Weak<T> has been introduced.
     println!("ref count: {}", Rc::strong_count(&a));
 
     //A problem with Rc<T> is that all references are immutable. The next section will help when
@@ -254,7 +406,7 @@ fn refcell_and_the_interior_mutability_pattern() {
     // let crash = ref_three.borrow_mut();
 }
 
-fn reference_cycles_can_leak_memory() {
+fn reference_cycles_can_leak_memory(deferred: &TaskQueue) {
     //Rust does not actually guarantee no memory leaks. For example Rc<T> can have items that
     // reference each other and so are never cleaned up. This is actually interesting because from
     // a simplistic view I assumed the borrow checker would take care of memory leaks completely.
@@ -284,6 +436,16 @@ fn reference_cycles_can_leak_memory() {
     //First now stores second and second stores first. This is a memory leak.
     first.borrow_mut().hello.replace(Rc::clone(&second));
 
+    //The leak check waits until every other section has run. By then `first` and `second` have
+    // long gone out of scope, so if the Weak can still be upgraded the cycle really did leak.
+    let leak_check = Rc::downgrade(&first);
+    deferred.push(move || {
+        println!(
+            "leak check: MemLeak cycle still alive after every section: {}",
+            leak_check.upgrade().is_some()
+        )
+    });
+
     //This statement will cause a stack overflow and crash because it is an endless loop of printing
     // internals.
     // println!("first: {:?}", first);
@@ -295,7 +457,7 @@ fn reference_cycles_can_leak_memory() {
     // previously discussed. Also note that when upgrading a Weak<T> object, an Rc<T> object is
     // returned and a strong reference now exists.
 
-    let mut weak_fails: Weak<i32>;
+    let weak_fails: Weak<i32>;
     {
         let pointer = Rc::new(5);
         let weak_exists = Rc::downgrade(&pointer);
@@ -308,3 +470,1802 @@ fn reference_cycles_can_leak_memory() {
     println!("weak_fails: {:?}", weak_fails.upgrade());
 
 }
+
+fn working_with_a_generic_cons_list() {
+    //The book uses a cons list of i32 values to show off Box<T>. The List<T> in list.rs is the same
+    // idea except it is generic and has a few operations built on top of it.
+    let list: List<i32> = list![1, 2, 3];
+    println!("list: {:?}", list.iter().collect::<Vec<_>>());
+
+    //Splicing re-links the boxes instead of copying the values. This will print [1, 10, 11, 2, 3].
+    let spliced = list.splice(1, list![10, 11]);
+    println!("spliced at 1: {:?}", spliced.iter().collect::<Vec<_>>());
+
+    let front: List<i32> = list![1, 2, 3];
+    let front = front.splice(0, list![10, 11]);
+    println!("spliced at 0: {:?}", front.iter().collect::<Vec<_>>());
+
+    //An index past the end simply appends the other list.
+    let back: List<i32> = list![1, 2, 3];
+    let back = back.splice(3, list![10, 11]);
+    println!("spliced at end: {:?}", back.iter().collect::<Vec<_>>());
+
+    //Merging two sorted lists moves the boxes from both inputs into the result. This will print
+    // [1, 2, 3, 4, 5, 6].
+    let odds: List<i32> = list![1, 3, 5];
+    let evens: List<i32> = list![2, 4, 6];
+    println!("merged: {:?}", merge_sorted(odds, evens).iter().collect::<Vec<_>>());
+
+    let empty: List<i32> = list![];
+    let merged = merge_sorted(empty, list![7, 8]);
+    println!("merged with empty: {:?}", merged.iter().collect::<Vec<_>>());
+
+    //Zipping stops at the shorter list, so the 3 is dropped. This will print [(1, 'a'), (2, 'b')].
+    let numbers: List<i32> = list![1, 2, 3];
+    let letters: List<char> = list!['a', 'b'];
+    println!("zipped: {:?}", zip(numbers, letters).iter().collect::<Vec<_>>());
+}
+
+fn scheduling_callbacks_on_a_timer_wheel() {
+    //The timer wheel in timer.rs combines Rc, Weak and closures. The callbacks are shared as
+    // Rc<dyn Fn()> and the wheel only hands out a Weak pointer to itself.
+    let wheel = TimerWheel::new(8);
+
+    let fired_at = Rc::new(Cell::new(0));
+    let wheel_handle = wheel.handle();
+    let fired_at_handle = Rc::clone(&fired_at);
+    wheel.schedule(3, Rc::new(move || {
+        if let Some(wheel) = wheel_handle.upgrade() {
+            fired_at_handle.set(wheel.current_tick());
+        }
+    }));
+
+    //Nothing fires on ticks 1 and 2, the callback fires on tick 3.
+    for _ in 0..3 {
+        let fired = wheel.tick();
+        println!("tick {} fired {} callback(s)", wheel.current_tick(), fired);
+    }
+    println!("fired_at: {}", fired_at.get());
+
+    //A callback can reschedule itself through the Weak pointer to the wheel. Because the callback
+    // only holds a Weak pointer, the wheel and the callback do not keep each other alive.
+    fn every_two_ticks(wheel: Weak<TimerWheel>, count: Rc<Cell<usize>>) -> Rc<dyn Fn()> {
+        Rc::new(move || {
+            count.set(count.get() + 1);
+            if let Some(strong_wheel) = wheel.upgrade() {
+                strong_wheel.schedule(2, every_two_ticks(wheel.clone(), Rc::clone(&count)));
+            }
+        })
+    }
+
+    let repeat_count = Rc::new(Cell::new(0));
+    wheel.schedule(2, every_two_ticks(wheel.handle(), Rc::clone(&repeat_count)));
+    for _ in 0..10 {
+        wheel.tick();
+    }
+
+    //Ten ticks with the callback firing every second tick means it fired five times.
+    println!("repeat_count: {}", repeat_count.get());
+    println!("wheel strong_count: {} weak_count: {}", Rc::strong_count(&wheel), Rc::weak_count(&wheel));
+}
+
+//Checks both counts at once so each step of weak_count_lifecycle() is a single line.
+fn assert_counts<T>(rc: &Rc<T>, strong: usize, weak: usize) {
+    assert_eq!(Rc::strong_count(rc), strong, "unexpected strong_count");
+    assert_eq!(Rc::weak_count(rc), weak, "unexpected weak_count");
+}
+
+fn weak_count_lifecycle() {
+    //This walks through every transition of Rc::weak_count. Each step is asserted and then added to
+    // a table that is printed at the end.
+    let mut table: Vec<(&str, usize, usize)> = Vec::new();
+
+    let first = Rc::new(String::from("value"));
+    assert_counts(&first, 1, 0);
+    table.push(("fresh Rc", 1, 0));
+
+    let weak_one = Rc::downgrade(&first);
+    assert_counts(&first, 1, 1);
+    table.push(("after downgrade", 1, 1));
+
+    let weak_two = Weak::clone(&weak_one);
+    assert_counts(&first, 1, 2);
+    table.push(("after cloning the Weak", 1, 2));
+
+    //Cloning the Rc only changes the strong count.
+    let second = Rc::clone(&first);
+    assert_counts(&first, 2, 2);
+    table.push(("after cloning the Rc", 2, 2));
+
+    drop(weak_two);
+    assert_counts(&first, 2, 1);
+    table.push(("after dropping a Weak", 2, 1));
+
+    drop(second);
+    assert_counts(&first, 1, 1);
+    table.push(("after dropping an Rc clone", 1, 1));
+
+    //Internally all of the strong pointers together hold one extra weak reference so the
+    // allocation is not freed while the value is still alive. Rc::weak_count never includes it,
+    // which is why a fresh Rc reported 0 above.
+
+    //Once the last strong pointer is dropped the String is dropped, but the allocation is kept
+    // around for the remaining Weak. The counts can now only be read from the Weak side, and
+    // Weak::weak_count reports 0 once there are no strong pointers left.
+    drop(first);
+    assert!(weak_one.upgrade().is_none());
+    assert_eq!(weak_one.strong_count(), 0);
+    assert_eq!(weak_one.weak_count(), 0);
+    table.push(("after the last Rc dropped (read from Weak)", 0, 0));
+
+    //Dropping the last Weak frees the allocation itself. There is nothing left to read the counts
+    // from at this point.
+    drop(weak_one);
+    table.push(("after the last Weak dropped", 0, 0));
+
+    //Weak::new() does not allocate at all, so it reports 0 for both counts and can never upgrade.
+    let empty: Weak<String> = Weak::new();
+    assert_eq!(empty.strong_count(), 0);
+    assert_eq!(empty.weak_count(), 0);
+    assert!(empty.upgrade().is_none());
+    table.push(("Weak::new()", 0, 0));
+
+    println!("{:<45} {:>6} {:>6}", "transition", "strong", "weak");
+    for (transition, strong, weak) in table {
+        println!("{:<45} {:>6} {:>6}", transition, strong, weak);
+    }
+}
+
+fn draining_a_box_based_stack() {
+    //The Stack<T> in stack.rs stores each element in a Box. Implementing IntoIterator lets the
+    // stack be consumed by a for loop, which pops the elements so they come out top first.
+    let mut popped = Stack::new();
+    let mut drained = Stack::new();
+    for value in 1..=3 {
+        popped.push(value);
+        drained.push(value);
+    }
+
+    let mut pop_order = Vec::new();
+    while let Some(value) = popped.pop() {
+        pop_order.push(value);
+    }
+
+    //The stack is moved into the loop here, so `drained` cannot be used afterwards.
+    let mut drain_order = Vec::new();
+    for value in drained {
+        drain_order.push(value);
+    }
+    println!("pop_order: {:?} drain_order: {:?}", pop_order, drain_order);
+
+    //The iterator pops one node at a time instead of recursing, so even a large stack works fine
+    // with iterator adaptors.
+    let mut large = Stack::new();
+    for value in 0..1_000_000u64 {
+        large.push(value);
+    }
+    let even_sum: u64 = large.into_iter().filter(|value| value % 2 == 0).sum();
+    println!("even_sum: {}", even_sum);
+}
+
+fn sharing_nodes_in_an_rc_graph() {
+    //GraphNode in graph.rs stores its edges as Rc<GraphNode>, so a node can be reached from several
+    // other nodes. Here `bottom` is shared by both `left` and `right`.
+    let top = GraphNode::new(1);
+    let left = GraphNode::new(2);
+    let right = GraphNode::new(3);
+    let bottom = GraphNode::new(4);
+
+    top.add_edge(&left);
+    top.add_edge(&right);
+    left.add_edge(&bottom);
+    right.add_edge(&bottom);
+
+    let edge_values: Vec<i32> = top.edges().iter().map(|node| node.value()).collect();
+    println!("top edges: {:?} bottom strong_count: {}", edge_values, Rc::strong_count(&bottom));
+
+    //The ownership report lists every node once. `bottom` shows a strong count of 3 because the
+    // local variable, `left` and `right` all own it.
+    print!("{}", ownership_report(&top));
+
+    //Summing those counts gives 1 + 2 + 2 + 3. If every node had exactly one owner, four nodes
+    // would sum to 4. Here the extra local variables and the shared `bottom` push it higher.
+    println!(
+        "weighted reach: {} reachable nodes: {}",
+        weighted_reach(&top),
+        to_adjacency_list(&top).len()
+    );
+
+    //replace_value() changes every matching node it can reach. `bottom` is reachable through both
+    // `left` and `right` but is only counted once.
+    bottom.set_value(2);
+    println!("replaced 2 -> 7: {}", replace_value(&top, 2, 7));
+    println!("left: {} bottom: {}", left.value(), bottom.value());
+
+    //Adding the edge bottom -> top creates a cycle, and would leak all four nodes if it was left in
+    // place. When the crate is built with `--features debug_cycle_checks` this panics instead.
+    #[cfg(all(feature = "debug_cycle_checks", debug_assertions))]
+    {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            bottom.add_edge(&top);
+        }));
+        println!("cycle rejected: {}", result.is_err());
+    }
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    bottom.add_edge(&top);
+
+    //Even with the cycle in place the search stops, thanks to the visited set. Clearing the edge
+    // afterwards breaks the cycle again so nothing is leaked.
+    println!("replaced 7 -> 8 starting at bottom: {}", replace_value(&bottom, 7, 8));
+    //The same goes for flattening the graph into an adjacency list. The edge back to top is listed
+    // under bottom, but top itself only appears once.
+    println!("adjacency list: {:?}", to_adjacency_list(&top));
+    bottom.clear_edges();
+    println!("top strong_count after breaking the cycle: {}", Rc::strong_count(&top));
+
+    //Edges can also carry a weight, and shortest_path() finds the lightest way between two nodes.
+    // The direct edge from start to goal is heavier than going around through the other two.
+    let start = GraphNode::new(1);
+    let middle = GraphNode::new(2);
+    let detour = GraphNode::new(3);
+    let goal = GraphNode::new(4);
+    start.add_weighted_edge(&goal, 10);
+    start.add_weighted_edge(&middle, 2);
+    middle.add_weighted_edge(&detour, 3);
+    detour.add_weighted_edge(&goal, 1);
+    if let Some((total, path)) = shortest_path(&start, &goal) {
+        let values: Vec<i32> = path.iter().map(|node| node.value()).collect();
+        println!("shortest path: {:?} total weight: {}", values, total);
+    }
+    println!("goal back to start: {:?}", shortest_path(&goal, &start).map(|(total, _)| total));
+}
+
+fn memoizing_with_weak_references() {
+    //A normal memoization cache keeps every result alive forever. WeakMemo in memo.rs only stores
+    // Weak pointers, so a result is only cached while somebody still holds the Rc for it.
+    let memo: WeakMemo<usize, String> = WeakMemo::new(4);
+    let expensive = |n: usize| Rc::new(format!("{}!", "ab".repeat(n).to_uppercase()));
+
+    let two = memo.get_or_insert_with(2, || expensive(2));
+    let three = memo.get_or_insert_with(3, || expensive(3));
+    let four = memo.get_or_insert_with(4, || expensive(4));
+    println!("after first pass recomputations: {}", memo.recomputations());
+
+    //Asking again while the results are held returns the same allocation without recomputing.
+    let two_again = memo.get_or_insert_with(2, || expensive(2));
+    println!("two is cached: {} recomputations: {}", Rc::ptr_eq(&two, &two_again), memo.recomputations());
+
+    //Once every Rc for a key is dropped, only that key is recomputed.
+    drop(two);
+    drop(two_again);
+    drop(three);
+    let _two = memo.get_or_insert_with(2, || expensive(2));
+    let _four = memo.get_or_insert_with(4, || expensive(4));
+    println!("after dropping 2 and 3 recomputations: {} live: {}", memo.recomputations(), memo.live_entries());
+
+    //The map now holds a dead entry for 3. Inserting once the map has reached the threshold of 4
+    // prunes it, while the live entries for 2 and 4 are kept.
+    let _five = memo.get_or_insert_with(5, || expensive(5));
+    println!("tracked before prune threshold: {}", memo.tracked_entries());
+    let _six = memo.get_or_insert_with(6, || expensive(6));
+    println!("tracked after prune: {} live: {} four: {}", memo.tracked_entries(), memo.live_entries(), four);
+
+    //with_strong_lru() adds a tier that holds the most recently used values itself. Here a value
+    // outlives its only caller because that tier still has it, until two newer values push it out.
+    let hot: WeakMemo<&str, String> = WeakMemo::with_strong_lru(8, 2);
+    let greeting = hot.get_or_insert_with("greeting", || Rc::new("hello".to_string()));
+    drop(greeting);
+    println!("greeting after its caller dropped it: {:?}", hot.get(&"greeting"));
+    hot.insert_many([("a", Rc::new("a".to_string())), ("b", Rc::new("b".to_string()))]);
+    println!("after two newer values: {:?}", hot.get_many(&["greeting", "a", "b"]));
+}
+
+fn sharing_a_clipboard() {
+    //The Clipboard in clipboard.rs is an Rc<RefCell<...>> underneath, so an editor and a terminal
+    // holding clones of it see the same history.
+    let editor = Clipboard::new();
+    let terminal = editor.clone();
+
+    println!("empty paste: {:?}", terminal.paste());
+
+    editor.copy("first");
+    editor.copy("second");
+    terminal.copy("third");
+
+    //Both handles paste the latest entry. The pasted Rc<str> is the same allocation that is stored
+    // in the history, only the reference count went up.
+    match editor.paste() {
+        Some(pasted) => {
+            println!("editor paste: {} terminal paste: {:?}", pasted, terminal.paste());
+            println!("pasted shares the history entry: {}", Rc::ptr_eq(&pasted, &terminal.history()[2]));
+        }
+        None => println!("editor paste: nothing was copied"),
+    }
+
+    //Navigating through one handle moves the position for every handle.
+    println!("previous: {:?} previous: {:?} previous: {:?}", editor.previous(), editor.previous(), editor.previous());
+    println!("next: {:?} terminal paste: {:?}", editor.next(), terminal.paste());
+}
+
+fn comparing_lifetimes_with_rc() {
+    //compare/ownership_styles.rs has the same address book written once with lifetimes and once
+    // with Rc<T>. Running the same scenario on both gives the same groups.
+    let contacts = ownership_styles::sample_contacts();
+    let mut borrowed = BorrowedBook::new(&contacts);
+    let mut shared = RcBook::new(ownership_styles::sample_contacts());
+
+    let borrowed_groups = ownership_styles::run_scenario(&mut borrowed);
+    let shared_groups = ownership_styles::run_scenario(&mut shared);
+    println!("borrowed: {:?}", borrowed_groups);
+    println!("rc:       {:?} same: {}", shared_groups, borrowed_groups == shared_groups);
+
+    //The trade-offs that were actually measured. The borrowed version needed no allocations for
+    // sharing, while the Rc version allocated one Rc per contact.
+    println!(
+        "sharing allocations borrowed: {} rc: {}",
+        borrowed.sharing_allocations(),
+        shared.sharing_allocations()
+    );
+
+    //In exchange the Rc version can keep changing the central list. The borrowed version would
+    // not compile if `contacts` was pushed to here, see the compile_fail doc tests in
+    // ownership_styles.rs.
+    shared.add_contact(Contact::new("Dan", "dan@example.com"));
+    shared.add_to_group("work", "Dan");
+    println!("rc contacts: {} allocations: {}", shared.contact_count(), shared.sharing_allocations());
+
+    //And the groups survive the central list being removed entirely.
+    shared.remove_central_list();
+    println!("rc contacts: {} work still has: {:?}", shared.contact_count(), shared.members("work"));
+    println!("first contact email: {}", contacts[0].email);
+}
+
+fn timing_when_values_are_dropped() {
+    //TimedDrop in timed_drop.rs records an Instant whenever the wrapped value is dropped. This makes
+    // it possible to see both the order and the spacing of the drops.
+    clear_drop_log();
+    let start = std::time::Instant::now();
+
+    let first = TimedDrop::new(1, String::from("first"));
+    {
+        let _second = TimedDrop::new(2, String::from("second"));
+        let _third = TimedDrop::new(3, String::from("third"));
+        //Just like the squares earlier, these two are dropped in reverse order at the end of the
+        // scope.
+    }
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    println!("first is still alive: {}", *first);
+    drop(first);
+
+    for (id, at) in drop_log() {
+        println!("id {} dropped after {:?}", id, at.duration_since(start));
+    }
+}
+
+fn limiting_weak_references() {
+    //downgrade_limited() in rc_utils.rs is Rc::downgrade with a cap on Rc::weak_count. It is handy
+    // for catching an observer list that keeps piling up Weak pointers.
+    let subject = Rc::new(String::from("subject"));
+    let mut observers = Vec::new();
+
+    for attempt in 1..=4 {
+        match downgrade_limited(&subject, 3) {
+            Ok(weak) => observers.push(weak),
+            Err(error) => println!("attempt {} refused: {:?}", attempt, error),
+        }
+        println!("attempt {} weak_count: {}", attempt, Rc::weak_count(&subject));
+    }
+
+    //Dropping one of the Weak pointers makes room for a new one.
+    observers.pop();
+    println!("after dropping one: {:?}", downgrade_limited(&subject, 3).is_ok());
+
+    //require_alive() turns the None from a failed upgrade into an error that says what was missing.
+    let observer = &observers[0];
+    println!("while alive: {:?}", require_alive(observer, "subject for observer 0"));
+    drop(subject);
+    println!("after drop: {:?}", require_alive(observer, "subject for observer 0"));
+}
+
+fn searching_a_boxed_expression_tree() {
+    //Expr in expr.rs is another recursive type made possible by Box<T>. This is (2 + 3) * (4 * 5).
+    let expr = (Expr::num(2.0) + Expr::num(3.0)) * (Expr::num(4.0) * Expr::num(5.0));
+    println!("eval: {}", expr.eval());
+
+    //Compiling walks the tree children first and produces a flat program for a stack machine.
+    // Running it gives the same answer as eval().
+    let small = (Expr::num(2.0) + Expr::num(3.0)) * Expr::num(4.0);
+    let program = small.compile();
+    println!("(2 + 3) * 4 compiles to {:?}", program);
+    println!("runs to {} eval: {}", run(&program), small.eval());
+    let difference = Expr::num(2.0) - Expr::num(3.0);
+    println!("2 - 3 compiles to {:?} and runs to {}", difference.compile(), run(&difference.compile()));
+
+    //The search is pre-order, so the root Mul is found before the Mul on the right.
+    let first_mul = expr.find_subexpr(|e| matches!(e, Expr::Mul(..)));
+    println!("first Mul is the root: {}", first_mul.map(|e| std::ptr::eq(e, &expr)).unwrap_or(false));
+
+    //The first leaf is the 2 because the left side is searched before the right side.
+    println!("first Num: {:?}", expr.find_subexpr(|e| matches!(e, Expr::Num(_))));
+    println!("first Num > 10: {:?}", expr.find_subexpr(|e| matches!(e, Expr::Num(n) if *n > 10.0)));
+
+    //equivalent() treats Add and Mul as commutative, but Sub is not.
+    let two_plus_three = Expr::num(2.0) + Expr::num(3.0);
+    let three_plus_two = Expr::num(3.0) + Expr::num(2.0);
+    println!("2+3 equivalent to 3+2: {}", two_plus_three.equivalent(&three_plus_two));
+
+    let forwards = Expr::num(2.0) * Expr::num(3.0) * Expr::num(4.0);
+    let backwards = Expr::num(4.0) * Expr::num(3.0) * Expr::num(2.0);
+    println!("2*3*4 equivalent to 4*3*2: {}", forwards.equivalent(&backwards));
+
+    let two_minus_three = Expr::num(2.0) - Expr::num(3.0);
+    let three_minus_two = Expr::num(3.0) - Expr::num(2.0);
+    println!(
+        "2-3 equivalent to 3-2: {} ({} vs {})",
+        two_minus_three.equivalent(&three_minus_two),
+        two_minus_three.eval(),
+        three_minus_two.eval()
+    );
+}
+
+fn sharing_a_bit_set() {
+    //SharedBitSet in bitset.rs is an Rc<RefCell<Vec<u64>>>. Bits set through one handle can be read
+    // through any other handle.
+    let writer = SharedBitSet::new();
+    let reader = writer.clone();
+
+    writer.set(3);
+    writer.set(63);
+    println!("reader sees 3: {} 63: {} 4: {}", reader.test(3), reader.test(63), reader.test(4));
+
+    //Bit 200 is past the first word, so the backing vec grows to four words.
+    println!("words before: {}", reader.word_count());
+    writer.set(200);
+    println!("words after: {} reader sees 200: {}", reader.word_count(), reader.test(200));
+
+    reader.clear(3);
+    reader.clear(1000);
+    println!("writer sees 3 after clear: {} words: {}", writer.test(3), writer.word_count());
+}
+
+fn analysing_a_boxed_binary_tree() {
+    //Node<T> in tree.rs is a binary search tree built out of Option<Box<Node<T>>> children.
+    //        5
+    //      /   \
+    //     3     8
+    //    / \     \
+    //   1   4     9
+    let mut root = Node::new(5);
+    for value in [3, 8, 1, 4, 9] {
+        root.insert(value);
+    }
+    println!("leaves: {} internal: {}", root.leaf_count(), root.internal_count());
+    let eight = root.subtree_containing(&8).map(|subtree| subtree.in_order());
+    println!("subtree at 8: {:?} subtree at 6: {:?}", eight, root.subtree_containing(&6).map(|n| n.value));
+    println!(
+        "depth 0: {:?} depth 1: {:?} depth 2: {:?} depth 3: {:?}",
+        root.nodes_at_depth(0),
+        root.nodes_at_depth(1),
+        root.nodes_at_depth(2),
+        root.nodes_at_depth(3)
+    );
+
+    //A single node has no children, so it is one leaf and zero internal nodes.
+    let single = Node::new(42);
+    println!("single leaves: {} internal: {}", single.leaf_count(), single.internal_count());
+
+    //The tree built with insert() is a valid binary search tree. Placing a 6 under the 3 by hand
+    // breaks it, even though 6 is a perfectly fine right child for 3 on its own, because
+    // everything on the left of the 5 has to be smaller than 5.
+    println!("root is_bst: {}", root.is_bst());
+    if let Some(three) = root.left.as_mut() {
+        three.right = Some(Box::new(Node::new(6)));
+    }
+    println!("misplaced is_bst: {}", root.is_bst());
+
+    //Converting to an rc_tree gives every node a fresh Rc. Each one is owned once, by its parent
+    // (or by `shared` for the root), and has one Weak pointing at it from each of its children.
+    let shared = box_tree_to_rc(&root);
+    let mut pending = vec![Rc::clone(&shared)];
+    let mut visited = Vec::new();
+    while let Some(node) = pending.pop() {
+        let children = node.children();
+        visited.push((node.value, children.len()));
+        pending.extend(children.into_iter().rev());
+    }
+    let counts = (Rc::strong_count(&shared), Rc::weak_count(&shared));
+    println!("as an rc tree (value, children): {:?} root counts: {:?}", visited, counts);
+    //Holding on to the 8 adds one strong count on top of its parent, and the 9 below it holds
+    // the one Weak.
+    let eight = shared.children().swap_remove(1);
+    println!("8 strong: {} weak: {}", Rc::strong_count(&eight), Rc::weak_count(&eight));
+
+    //The diameter counts the edges on the longest path between two nodes. For the tree above that
+    // is 1 -> 3 -> 5 -> 8 -> 9, which is 4 edges. Inserting values in order makes every node a right
+    // child, so the tree is a single path and the diameter is one less than the node count.
+    println!("balanced diameter: {} single diameter: {}", root.diameter(), single.diameter());
+    let mut skewed = Node::new(1);
+    for value in 2..=6 {
+        skewed.insert(value);
+    }
+    println!("skewed diameter: {}", skewed.diameter());
+
+    //Turning a cons list into a tree sorts the values and builds the tree from the middle out, so
+    // the result is balanced. The skewed tree from above is not.
+    let list: List<i32> = vec![7, 3, 9, 1, 5, 8, 2].into_iter().collect();
+    if let Some(balanced) = list_to_balanced_tree(&list) {
+        println!(
+            "from list: {:?} height: {} balanced: {} is_bst: {}",
+            balanced.in_order(),
+            balanced.height(),
+            balanced.is_balanced(),
+            balanced.is_bst()
+        );
+    }
+    println!("skewed height: {} balanced: {}", skewed.height(), skewed.is_balanced());
+
+    //BoundedTree rejects any insert that would make the tree deeper than its limit. Shuffled
+    // values stay well within 4 levels, but sorted values make every node a new level.
+    let mut bounded = BoundedTree::new(4);
+    let shuffled: Vec<Result<(), DepthLimitReached>> =
+        [5, 3, 8, 1, 4, 9].into_iter().map(|value| bounded.insert(value)).collect();
+    println!("shuffled inserts: {:?} height: {}", shuffled, bounded.height());
+
+    let mut ascending = BoundedTree::new(4);
+    let sorted: Vec<Result<(), DepthLimitReached>> =
+        (1..=6).map(|value| ascending.insert(value)).collect();
+    println!("ascending inserts: {:?} height: {}", sorted, ascending.height());
+    if let Some(root) = ascending.root() {
+        println!("ascending values kept: {:?}", root.in_order());
+    }
+}
+
+fn resuming_shared_iterators_independently() {
+    //SharedIter in shared_iter.rs keeps the data in an Rc<Vec<T>> and its own position next to it.
+    // Cloning it gives a second cursor over the same data without copying the vec.
+    let mut first = SharedIter::new(vec!['a', 'b', 'c', 'd']);
+    first.next();
+
+    let mut second = first.clone();
+    println!("shares data: {} owners: {}", first.shares_data_with(&second), first.owners());
+
+    //Advancing the clone does not move the original.
+    second.next();
+    second.next();
+    println!("first position: {} second position: {}", first.position(), second.position());
+    println!("first rest: {:?} second rest: {:?}", first.collect::<String>(), second.collect::<String>());
+}
+
+fn choosing_the_drop_order_of_fields() {
+    //Fields are normally dropped in declaration order. The Service in ordered_drop.rs wraps its
+    // fields in ManuallyDrop<T> and drops them in whatever order it was given.
+    let recorder = rc_refcell!(Vec::new());
+
+    drop(Service::new(&recorder, vec![Part::Cache, Part::Logger, Part::Connection]));
+    println!("custom order: {:?}", recorder.borrow());
+
+    //Duplicates are only dropped once and anything left out is dropped afterwards, so every
+    // resource is still dropped exactly once.
+    recorder.borrow_mut().clear();
+    drop(Service::new(&recorder, vec![Part::Logger, Part::Logger]));
+    println!("partial order: {:?}", recorder.borrow());
+}
+
+fn caching_paths_in_an_rc_tree() {
+    //rc_tree.rs has the tree from the end of the chapter, children are Rc<Node<T>> and the parent
+    // is a Weak<Node<T>>. The path of a node is found by following the Weak parent links.
+    let root = rc_tree::Node::new("usr");
+    let local = rc_tree::Node::new("local");
+    let share = rc_tree::Node::new("share");
+    let bin = rc_tree::Node::new("bin");
+    rc_tree::Node::add_child(&root, &local);
+    rc_tree::Node::add_child(&root, &share);
+    rc_tree::Node::add_child(&local, &bin);
+
+    //The second call hands back the exact same Rc<str> instead of walking the parents again.
+    let first = bin.cached_path();
+    let second = bin.cached_path();
+    println!("path: {} cached: {}", first, Rc::ptr_eq(&first, &second));
+
+    //Moving `bin` under `share` invalidates its cached path, so the next call recomputes it.
+    rc_tree::Node::add_child(&share, &bin);
+    let moved = bin.cached_path();
+    println!("path after move: {} recomputed: {}", moved, !Rc::ptr_eq(&first, &moved));
+    println!(
+        "local children: {} bin parent: {:?}",
+        local.children().len(),
+        bin.parent().map(|parent| parent.value)
+    );
+}
+
+fn deferring_work_with_a_task_queue() {
+    //TaskQueue in defer_queue.rs stores Box<dyn FnOnce()> closures to run later. FnOnce means a
+    // closure can move a value out of what it captured, so each task runs at most once.
+    let queue = TaskQueue::new();
+    let order = rc_refcell!(Vec::new());
+
+    for name in ["first", "second", "third"] {
+        let order = Rc::clone(&order);
+        queue.push(move || order.borrow_mut().push(name));
+    }
+
+    //Cancelling skips exactly that task.
+    let cancelled = queue.push(|| println!("this never prints"));
+    println!("cancelled: {} cancelled again: {}", queue.cancel(cancelled), queue.cancel(cancelled));
+
+    //The String is moved into the closure and then moved out again when it runs.
+    let message = String::from("moved into the task");
+    let moved_order = Rc::clone(&order);
+    queue.push(move || {
+        moved_order.borrow_mut().push("moved");
+        drop(message);
+    });
+
+    println!("queued: {} summary: {:?}", queue.len(), queue.run_all());
+    println!("run order: {:?} empty: {}", order.borrow(), queue.is_empty());
+
+    //The queue can be reused after run_all(). Running it while empty does nothing.
+    println!("empty run: {:?}", queue.run_all());
+
+    //A task that pushes another task needs a pointer to the queue. It holds a Weak pointer so that a
+    // task which never runs does not keep the queue alive. The new task waits for the next run.
+    let queue = Rc::new(queue);
+    let queue_handle = Rc::downgrade(&queue);
+    queue.push(move || {
+        if let Some(queue) = queue_handle.upgrade() {
+            queue.push(|| println!("pushed by another task"));
+        }
+    });
+    println!("first run: {:?} waiting: {}", queue.run_all(), queue.len());
+    println!("second run: {:?}", queue.run_all());
+
+    //main() has a queue of its own. The leak check, heap stats and churn report sections push
+    // their final output onto it, and it is run once every section has finished.
+}
+
+fn temporary_lifetimes() {
+    //The drop order of named variables was covered earlier. Temporaries, values that are created
+    // in the middle of an expression and never given a name, follow their own rules. Every
+    // scenario in temporaries.rs returns a DropLog showing the order things happened in.
+    let scenarios = [
+        (
+            "chained method temporaries",
+            "both temporaries die at the end of the statement, newest first",
+            temporaries::chained_method_temporaries(),
+        ),
+        (
+            "let binding borrowing a temporary",
+            "a borrowed temporary lives to the end of the block, a copied one does not",
+            temporaries::let_binding_borrowing_a_temporary(),
+        ),
+        (
+            "if let scrutinee",
+            "the scrutinee is still alive inside the body",
+            temporaries::if_let_scrutinee(),
+        ),
+        (
+            "temporary in an argument list",
+            "arguments outlive the call and die at the end of the statement",
+            temporaries::temporary_in_argument_list(),
+        ),
+        (
+            "let else",
+            "the initializer's temporaries die before either branch continues",
+            temporaries::let_else(),
+        ),
+    ];
+
+    for (name, explanation, log) in scenarios {
+        println!("{}: {:?}", name, log.events());
+        println!("    {}", explanation);
+    }
+}
+
+fn counting_freed_allocations() {
+    //Dropping an Rc only frees the value when it was the last strong owner. drop_and_count() in
+    // rc_utils.rs drops a batch of handles and reports how many values were really freed.
+    let sole_owners: Vec<Rc<i32>> = (0..4).map(Rc::new).collect();
+    println!("sole owners freed: {}", drop_and_count(sole_owners));
+
+    //Two of these values are still owned by `kept`, so only two out of four are freed. The
+    // duplicated handle to `shared` counts once, when its last handle in the batch is dropped.
+    let kept: Vec<Rc<i32>> = (0..2).map(Rc::new).collect();
+    let shared = Rc::new(10);
+    let batch = vec![
+        Rc::clone(&kept[0]),
+        Rc::clone(&kept[1]),
+        Rc::new(20),
+        Rc::clone(&shared),
+        shared,
+    ];
+    //Five handles, but the last two are clones of each other, so they only hold four values.
+    println!(
+        "handles: {} distinct allocations: {}",
+        batch.len(),
+        distinct_allocations(&batch)
+    );
+    println!("partially shared freed: {} kept alive: {:?}", drop_and_count(batch), kept);
+}
+
+fn sharing_a_sparse_matrix() {
+    //SparseMatrix in sparse.rs is one more Rc<RefCell<T>>, this time around a HashMap of the
+    // non-zero entries.
+    let writer = SparseMatrix::new();
+    let reader = writer.clone();
+
+    writer.set(0, 0, 1.5);
+    writer.set(2, 7, -3.0);
+    println!("reader (2, 7): {} (1, 1): {} nnz: {}", reader.get(2, 7), reader.get(1, 1), reader.nnz());
+
+    //Setting an entry back to zero removes it, so nnz goes down.
+    reader.set(0, 0, 0.0);
+    println!("writer (0, 0): {} nnz: {}", writer.get(0, 0), writer.nnz());
+}
+
+fn filtering_a_shared_logger() {
+    //Logger in logger.rs keeps its level and buffer in an Rc<RefCell<LoggerState>>. Two parts of a
+    // program can log through their own clone and still end up in the same buffer.
+    let network = Logger::new(Level::Info);
+    let storage = network.clone();
+
+    network.log(Level::Debug, "connecting");
+    network.log(Level::Info, "connected");
+    storage.log(Level::Warn, "disk almost full");
+
+    //Raising the level through one handle filters the other handle's messages as well.
+    storage.set_level(Level::Error);
+    network.log(Level::Warn, "slow response");
+    network.log(Level::Error, "connection lost");
+
+    println!("messages: {:?}", storage.messages());
+}
+
+fn zipping_two_shared_trees() {
+    //zip_shared.rs walks two Rc trees side by side. Each step only borrows a node's children for a
+    // moment, so the trees can still be changed while they are being walked.
+    fn build(values: &[i32]) -> Rc<rc_tree::Node<i32>> {
+        let root = rc_tree::Node::new(values[0]);
+        for value in &values[1..] {
+            rc_tree::Node::add_child(&root, &rc_tree::Node::new(*value));
+        }
+        root
+    }
+
+    let a = build(&[1, 2, 3]);
+    let b = build(&[1, 2, 3]);
+    println!("zipped: {:?}", zip_trees(&a, &b).collect::<Vec<_>>());
+    println!("structural_eq: {}", rc_tree::Node::structural_eq(&a, &b));
+
+    //Adding a child to `b` in the middle of the walk is fine, and because the 3 has not been visited
+    // yet, the new child shows up as a position that only exists on the right.
+    let mut walk = structural_zip(&a, &b);
+    let mut entries: Vec<_> = walk.by_ref().take(2).collect();
+    rc_tree::Node::add_child(&b.children()[1], &rc_tree::Node::new(4));
+    entries.extend(walk);
+    println!("structural zip: {:?}", entries);
+    println!("structural_eq: {}", rc_tree::Node::structural_eq(&a, &b));
+}
+
+fn freezing_a_shared_tree() {
+    //freeze.rs turns an rc_tree::Node tree into FrozenNodes, which have no RefCell at all. The
+    // frozen copy is a deep snapshot, so it does not see later changes to the original.
+    let root = tree!(1 => [2 => [3]]);
+
+    let frozen = rc_tree::Node::freeze(&root);
+    rc_tree::Node::add_child(&root, &rc_tree::Node::new(4));
+    println!("frozen children: {} live children: {}", frozen.children.len(), root.children().len());
+
+    //Thawing builds a brand new mutable tree with the same shape as the snapshot.
+    let thawed = FrozenNode::thaw(&frozen);
+    println!("thawed matches frozen: {}", rc_tree::Node::freeze(&thawed) == frozen);
+    println!(
+        "thawed child parent: {:?} path: {}",
+        thawed.children()[0].parent().map(|parent| parent.value),
+        thawed.children()[0].children()[0].cached_path()
+    );
+
+    //Freezing walks the tree with its own stack, so even a 10,000 level deep chain is fine.
+    let deep_root = rc_tree::Node::new(0);
+    let mut deepest = Rc::clone(&deep_root);
+    for value in 1..10_000 {
+        let next = rc_tree::Node::new(value);
+        rc_tree::Node::add_child(&deepest, &next);
+        deepest = next;
+    }
+    let frozen_deep = rc_tree::Node::freeze(&deep_root);
+    println!("deep frozen root: {} children: {}", frozen_deep.value, frozen_deep.children.len());
+}
+
+fn copying_a_vector_on_write() {
+    //CowVec in cow_vec.rs is an Rc<Vec<T>> that only copies the Vec when a shared one is changed.
+    let mut original = CowVec::new();
+    println!("starts empty: {}", original.is_empty());
+    original.push(1);
+    original.push(2);
+
+    let mut copy = original.clone();
+    println!("clones share the Vec: {}", original.shares_backing_with(&copy));
+
+    //Pushing onto a shared CowVec copies it first, so the original is left alone.
+    copy.push(3);
+    println!(
+        "after push shared: {} original len: {} copy len: {} copy[2]: {:?}",
+        original.shares_backing_with(&copy),
+        original.len(),
+        copy.len(),
+        copy.get(2)
+    );
+
+    //`copy` is the only owner of its Vec now, so the next push changes it in place.
+    let before = copy.backing_ptr();
+    copy.push(4);
+    println!("unique push copied: {}", before != copy.backing_ptr());
+}
+
+fn nesting_drop_log_scopes() {
+    //Scopes nest, and values dropped inside a scope are rendered inside of it.
+    let log = DropLog::new();
+    {
+        let _outer = log.scope("outer");
+        let _a = Logged::new(&log, "a");
+        {
+            let _middle = log.scope("middle");
+            let _inner = log.scope("inner");
+            let _b = Logged::new(&log, "b");
+        }
+    }
+    print!("{}", log.render_tree());
+
+    //A guard is just a value, so it can be returned from a function. The scope then stays open
+    // until the caller drops the guard, not when the function that opened it returns.
+    fn open_scope(log: &DropLog) -> ScopeGuard {
+        let guard = log.scope("moved");
+        log.record("function returning");
+        guard
+    }
+
+    let log = DropLog::new();
+    let guard = open_scope(&log);
+    log.record("caller still inside");
+    drop(guard);
+    print!("{}", log.render_tree());
+
+    //If a guard is never dropped the scope never gets its exit event. Rendering does not panic, it
+    // marks the scope as unclosed instead.
+    let log = DropLog::new();
+    std::mem::forget(log.scope("forgotten"));
+    log.record("after forget");
+    print!("{}", log.render_tree());
+}
+
+fn chaining_lazy_computations() {
+    //Lazy<T> in lazychain.rs is a Box<dyn FnOnce() -> T> with combinators on top. Building the
+    // chain does no work at all, the Cell counts how often the expensive first stage really runs.
+    let runs = Rc::new(Cell::new(0));
+    let runs_handle = Rc::clone(&runs);
+    let chain = Lazy::new(move || {
+        runs_handle.set(runs_handle.get() + 1);
+        21
+    })
+    .map(|value| value * 2)
+    .and_then(|value| Lazy::new(move || format!("answer: {}", value)));
+    println!("runs before force: {}", runs.get());
+
+    //Sharing the chain means both consumers get the result, but the work only happens once.
+    let first_consumer = chain.shared();
+    let second_consumer = first_consumer.clone();
+    println!("{:?} {:?} runs: {}", first_consumer.force(), second_consumer.force(), runs.get());
+
+    //zip forces both sides.
+    println!("zipped: {:?}", Lazy::new(|| 1).zip(Lazy::new(|| "one")).force());
+
+    //A chain that is dropped without being forced never runs.
+    let never_runs = Rc::clone(&runs);
+    drop(Lazy::new(move || never_runs.set(never_runs.get() + 100)));
+    println!("runs after dropping an unforced chain: {}", runs.get());
+
+    //If forcing panics, the shared state is left poisoned and forcing again returns an error
+    // instead of running half finished work a second time. The expected panic is caught without
+    // printing a message.
+    let failing = Lazy::new(|| -> i32 { panic!("stage failed") }).shared();
+    let first = compile_vs_runtime::catch_unwind_quietly(|| failing.force());
+    println!("first force panicked: {} second force: {:?}", first.is_err(), failing.force());
+}
+
+fn taking_from_a_refcell_conditionally() {
+    //take_if() in refcell_utils.rs checks the contents of a RefCell and swaps them for the default
+    // value in a single borrow.
+    let batch = RefCell::new(vec![1, 2, 3]);
+
+    //The predicate fails, so nothing is taken and the cell is unchanged.
+    println!("take when over 5: {:?} cell: {:?}", take_if(&batch, |items| items.len() > 5), batch.borrow());
+
+    //The predicate holds, so the old Vec is handed back and the cell now holds an empty Vec.
+    println!("take when over 2: {:?} cell: {:?}", take_if(&batch, |items| items.len() > 2), batch.borrow());
+}
+
+fn sharing_a_priority_queue() {
+    //SharedPQueue in pqueue.rs shares a BinaryHeap through Rc<RefCell<T>>. Jobs pushed by the
+    // producer come out of the consumer's handle highest priority first.
+    let producer = SharedPQueue::new();
+    let consumer = producer.clone();
+
+    producer.push((2, "write report"));
+    producer.push((5, "fix outage"));
+    producer.push((1, "water plants"));
+
+    println!("peek: {:?}", consumer.peek());
+    while let Some((priority, job)) = consumer.pop() {
+        println!("priority {}: {}", priority, job);
+    }
+}
+
+fn comparing_compile_time_and_runtime_borrow_errors() {
+    //compare/compile_vs_runtime.rs has four aliasing mistakes. Written with plain references the
+    // compiler rejects every one of them. Written with RefCell<T> and Rc<T> they compile, and the
+    // same mistake shows up as a panic instead, which is caught here so the demo keeps going.
+    for pair in compile_vs_runtime::pairs() {
+        let message = compile_vs_runtime::panic_message(pair.runtime);
+        println!("{}:", pair.name);
+        println!("    compiler: {}", pair.compiler_error);
+        println!("    runtime:  {}", message.unwrap_or_else(|| String::from("no panic")));
+    }
+}
+
+fn retrying_a_failed_borrow() {
+    //with_retry() in refcell_utils.rs uses try_borrow_mut, which returns an Err instead of
+    // panicking when the cell is already borrowed, and tries again a few times.
+    let counter = RefCell::new(0);
+    println!("free cell: {:?}", with_retry(&counter, 3, |count| {
+        *count += 1;
+        *count
+    }));
+
+    //While a borrow is held, every attempt fails. Nothing runs between the attempts, so retrying
+    // on its own cannot help in single threaded code.
+    let held = counter.borrow();
+    println!("held cell: {:?} (held value {})", with_retry(&counter, 3, |count| *count), *held);
+
+    //The yielding version gives the holder a chance to finish. Here the held borrow is released
+    // after the second failed attempt, so the third attempt succeeds.
+    let holder = RefCell::new(Some(held));
+    let result = with_retry_yielding(
+        &counter,
+        5,
+        |attempt| {
+            println!("attempt {} failed", attempt);
+            if attempt == 2 {
+                holder.borrow_mut().take();
+            }
+        },
+        |count| {
+            *count += 1;
+            *count
+        },
+    );
+    println!("after yielding: {:?}", result);
+}
+
+fn invalidating_weak_handles() {
+    //WeakHandle in weak_handle.rs wraps the Rc::downgrade and upgrade() pattern from
+    // reference_cycles_can_leak_memory().
+    let source = Rc::new(String::from("config"));
+    let handle = WeakHandle::from_rc(&source);
+    let copy = handle.clone();
+
+    //is_valid() does not keep anything alive, the strong count stays at 1. The Rc returned by get()
+    // does count as an owner for as long as it is held.
+    println!("valid: {} strong_count: {}", handle.is_valid(), Rc::strong_count(&source));
+    let upgraded = handle.get();
+    println!("get: {:?} strong_count: {}", upgraded, Rc::strong_count(&source));
+    drop(upgraded);
+
+    //Once the source is dropped every handle becomes invalid at the same time.
+    drop(source);
+    println!("valid: {} get: {:?} copy valid: {}", handle.is_valid(), handle.get(), copy.is_valid());
+}
+
+fn covering_drop_edge_cases() {
+    //A few situations where drop() itself needs some care. The scenarios live in
+    // drop_edge_cases.rs and each one returns the DropLog it recorded into.
+    let scenarios = [
+        ("nested drop", drop_edge_cases::nested_drop()),
+        ("guarded panic while unwinding", drop_edge_cases::guarded_panic_while_unwinding()),
+        ("take a field in drop", drop_edge_cases::take_field_in_drop()),
+        ("re-entrant drop", drop_edge_cases::reentrant_drop()),
+    ];
+    for (name, log) in scenarios {
+        println!("{}: {:?}", name, log.events());
+    }
+
+    //Without the thread::panicking() check the second panic aborts the whole process. Starting this
+    // binary with --crash-demo shows that, and tests/crash_demo.rs checks how that process ends. It
+    // is not started from here so a normal run does not leave an aborted child behind.
+}
+
+fn bumping_shared_counters_once() {
+    //`views` and `clicks` are two handles to the same count, while `errors` is a separate one.
+    // bump_all() bumps each allocation once no matter how many of its handles are passed in.
+    let clicks = Counter::new();
+    let views = clicks.clone();
+    let errors = Counter::new();
+
+    bump_all(&[&clicks, &views, &errors, &clicks]);
+    println!("clicks: {} views: {} errors: {}", clicks.get(), views.get(), errors.get());
+
+    bump_all(&[&errors]);
+    println!("after bumping errors alone: clicks: {} errors: {}", clicks.get(), errors.get());
+
+    //Thresholds fire once each, at the bump that reaches them. Adding 4 at once still fires the
+    // threshold at 10, with the count it actually ended up at.
+    let fired = rc_refcell!(Vec::new());
+    let requests = Counter::new();
+    for (at, label) in [(5, "warning"), (10, "limit")] {
+        let fired = Rc::clone(&fired);
+        requests.on_threshold(at, Rc::new(move |count| fired.borrow_mut().push((label, count))));
+    }
+    for _ in 0..7 {
+        requests.bump();
+    }
+    println!("after 7 bumps: {:?}", fired.borrow());
+    requests.add(4);
+    requests.bump();
+    println!("after 12: {:?}", fired.borrow());
+}
+
+fn reversing_a_doubly_linked_list() {
+    //The nodes in dll.rs point forwards with Rc and backwards with Weak. Reversing the list swaps
+    // those roles around on every node without allocating any new ones.
+    let mut list = DoublyLinkedList::new();
+    for value in 2..=4 {
+        list.push_back(value);
+    }
+    list.push_front(1);
+
+    let mut forwards = Vec::new();
+    list.for_each(|value| forwards.push(*value));
+    list.reverse();
+    let mut reversed = Vec::new();
+    list.for_each(|value| reversed.push(*value));
+    let mut backwards = Vec::new();
+    list.for_each_backward(|value| backwards.push(*value));
+    println!("forwards: {:?} reversed: {:?} reversed backwards: {:?}", forwards, reversed, backwards);
+
+    //If reversing had left two nodes owning each other, some of these would never be dropped.
+    let log = DropLog::new();
+    let mut logged = DoublyLinkedList::new();
+    for name in ["a", "b", "c"] {
+        logged.push_back(Logged::new(&log, name));
+    }
+    logged.reverse();
+    drop(logged);
+    println!("dropped after reverse: {:?}", log.events());
+}
+
+fn splitting_and_appending_doubly_linked_lists() {
+    //A CursorMut can cut a list in two after the node it is on. append() joins two lists by
+    // relinking the nodes where they meet, so swapping the halves around moves no values at all.
+    let mut front = DoublyLinkedList::new();
+    for value in 1..=6 {
+        front.push_back(value);
+    }
+    let mut back = {
+        let mut cursor = front.cursor_mut();
+        cursor.move_next();
+        cursor.move_next();
+        cursor.split_after()
+    };
+    println!("split lengths: {} and {}", front.len(), back.len());
+
+    back.append(front);
+    let mut swapped = Vec::new();
+    back.for_each(|value| swapped.push(*value));
+    println!("halves swapped: {:?} consistent: {}", swapped, back.is_consistent());
+}
+
+fn rearranging_children_in_an_rc_tree() {
+    //The children of an rc_tree::Node live in a RefCell<Vec<_>>, so they can be rearranged through
+    // a shared Rc<Node<T>>. Nothing about the parent links changes, only the order.
+    fn values_of(node: &rc_tree::Node<&'static str>) -> Vec<&'static str> {
+        node.children().iter().map(|child| child.value).collect()
+    }
+
+    let root = rc_tree::Node::new("root");
+    let first_a = rc_tree::Node::new("a");
+    let second_a = rc_tree::Node::new("a");
+    let c = rc_tree::Node::new("c");
+    for child in [&c, &first_a, &rc_tree::Node::new("b"), &second_a] {
+        rc_tree::Node::add_child(&root, child);
+    }
+    for name in ["z", "x", "y"] {
+        rc_tree::Node::add_child(&c, &rc_tree::Node::new(name));
+    }
+
+    rc_tree::Node::move_child(&root, 0, 3);
+    println!("after moving c to the end: {:?}", values_of(&root));
+    rc_tree::Node::move_child(&root, 1, 1);
+    println!("moving a child to its own index: {:?}", values_of(&root));
+
+    println!("reorder [3, 0, 1]: {:?}", rc_tree::Node::reorder_children(&root, &[3, 0, 1]));
+    println!("reorder [0, 0, 1, 2]: {:?}", rc_tree::Node::reorder_children(&root, &[0, 0, 1, 2]));
+    println!("reorder [4, 0, 1, 2]: {:?}", rc_tree::Node::reorder_children(&root, &[4, 0, 1, 2]));
+    println!("reorder [3, 2, 1, 0]: {:?}", rc_tree::Node::reorder_children(&root, &[3, 2, 1, 0]));
+    println!("after reordering: {:?}", values_of(&root));
+
+    //Sorting every level of the tree. The two "a" children compare equal, so the stable sort keeps
+    // them in the order they were in before.
+    let first_a_leads = || {
+        rc_tree::Node::index_of_child(&root, &first_a) < rc_tree::Node::index_of_child(&root, &second_a)
+    };
+    let first_a_before = first_a_leads();
+    let mut pending = vec![Rc::clone(&root)];
+    while let Some(node) = pending.pop() {
+        rc_tree::Node::sort_children_by_value(&node);
+        pending.extend(node.children());
+    }
+    let first_a_after = first_a_leads();
+    println!("sorted root: {:?} sorted c: {:?}", values_of(&root), values_of(&c));
+    println!("equal values kept their order: {}", first_a_before == first_a_after);
+}
+
+fn batching_observer_notifications() {
+    //Every set on an Observable notifies its observers, unless it happens inside batch(). A batch
+    // sends one notification at the end, and none if the value is back where it started.
+    let temperature = Observable::new(20);
+    let seen = rc_refcell!(Vec::new());
+    let recorder = Rc::clone(&seen);
+    temperature.subscribe(move |value| recorder.borrow_mut().push(*value));
+
+    temperature.set(21);
+    temperature.batch(|| {
+        temperature.set(22);
+        temperature.set(23);
+        temperature.set(24);
+    });
+    println!("after one set and a batch of three: {:?}", seen.borrow());
+
+    temperature.batch(|| {
+        temperature.set(30);
+        temperature.set(24);
+    });
+    println!("after a batch that changes nothing: {:?}", seen.borrow());
+}
+
+fn common_mistakes() {
+    //Each mistake in mistakes.rs compiles without complaint. Running it next to its fixed version
+    // shows what actually goes wrong.
+    let held = mistakes::ref_across_borrow_mut();
+    let released = mistakes::ref_across_borrow_mut_fixed();
+    println!("ref held across borrow_mut panicked with: {:?}", held.panic_message);
+    println!("    fixed: {:?}", released.panic_message);
+
+    let cycle = mistakes::callback_cycle();
+    let weak = mistakes::callback_cycle_fixed();
+    println!(
+        "callback capturing its owner: strong_count {} leaked {}",
+        cycle.strong_count_with_callback, cycle.leaked
+    );
+    println!("    fixed: strong_count {} leaked {}", weak.strong_count_with_callback, weak.leaked);
+
+    let cloned = mistakes::clone_instead_of_borrow();
+    let borrowed = mistakes::clone_instead_of_borrow_fixed();
+    println!(
+        "cloning instead of borrowing: counts {:?} totals {:?}",
+        cloned.strong_counts, cloned.totals
+    );
+    println!("    fixed: counts {:?} totals {:?}", borrowed.strong_counts, borrowed.totals);
+
+    //The elapsed times depend on the machine, so only the number of upgrades is exact.
+    let slow = mistakes::upgrade_in_hot_loop();
+    let fast = mistakes::upgrade_in_hot_loop_fixed();
+    println!(
+        "upgrading in a hot loop: {} upgrades, total {}, {:?}",
+        slow.upgrades, slow.total, slow.elapsed
+    );
+    println!("    fixed: {} upgrade, total {}, {:?}", fast.upgrades, fast.total, fast.elapsed);
+
+    let clone_only = mistakes::dropping_a_clone();
+    let every_handle = mistakes::dropping_a_clone_fixed();
+    println!(
+        "dropping a clone: strong_count {} events {:?}",
+        clone_only.strong_count_after, clone_only.events
+    );
+    println!(
+        "    fixed: strong_count {} events {:?}",
+        every_handle.strong_count_after, every_handle.events
+    );
+}
+
+fn building_strings_with_a_rope() {
+    //Rope in rope.rs is a Box based tree with Rc<str> leaves. Joining ropes never copies the text,
+    // and the same leaf can end up in more than one rope.
+    let shared_tail = Rope::leaf("-ed");
+    let walked = Rope::leaf("walk").concat(shared_tail.clone());
+    let talked = Rope::leaf("talk").concat(shared_tail.clone());
+    let sentence = walked.concat(Rope::leaf(" and ")).concat(talked);
+
+    println!("rope length: {} empty: {}", sentence.len(), sentence.is_empty());
+    let across_boundaries: String = [0, 3, 4, 6, 7, 11, 16]
+        .iter()
+        .filter_map(|&index| sentence.char_at(index))
+        .collect();
+    println!("chars around the boundaries: {:?}", across_boundaries);
+    println!("past the end: {:?}", sentence.char_at(sentence.len()));
+
+    //substring() collects a range of chars, crossing from one leaf into the next when it has to.
+    println!("within one leaf: {:?}", sentence.substring(1, 3));
+    println!("across leaves: {:?}", sentence.substring(2, 14));
+    println!("empty range: {:?}", sentence.substring(5, 5));
+    println!("running past the end: {:?}", sentence.substring(16, 100));
+    if let Rope::Leaf(text) = &shared_tail {
+        println!("\"-ed\" leaf strong_count: {}", Rc::strong_count(text));
+    }
+}
+
+fn deduplicating_frozen_subtrees() {
+    //A frozen tree never changes, so identical subtrees might as well be one shared allocation.
+    // Here both projects contain the same src directory, which freezes into two separate copies.
+    let root = tree!("projects" => [
+        "app" => ["src" => ["main.rs", "lib.rs"]],
+        "tool" => ["src" => ["main.rs", "lib.rs"]],
+    ]);
+    let frozen = rc_tree::Node::freeze(&root);
+
+    let app_src = &frozen.children[0].children[0];
+    let tool_src = &frozen.children[1].children[0];
+    println!(
+        "src hashes equal: {} same allocation: {}",
+        content_hash(app_src) == content_hash(tool_src),
+        Rc::ptr_eq(app_src, tool_src)
+    );
+
+    let (shared, stats) = deduplicate(frozen);
+    println!("nodes before: {} after: {}", stats.nodes_before, stats.nodes_after);
+    println!(
+        "src shared after dedup: {}",
+        Rc::ptr_eq(&shared.children[0].children[0], &shared.children[1].children[0])
+    );
+
+    //Running it again finds nothing more to share.
+    let (_, again) = deduplicate(shared);
+    println!("second pass before: {} after: {}", again.nodes_before, again.nodes_after);
+}
+
+fn naming_nodes_with_stable_handles() {
+    //Two nodes with the value 2 cannot be told apart by value. HandleSlab in slab.rs gives each
+    // one its own id instead, and only keeps a Weak to it.
+    let mut slab = HandleSlab::new();
+    let root = rc_tree::Node::new(1);
+    let first_two = rc_tree::Node::new(2);
+    let second_two = rc_tree::Node::new(2);
+    rc_tree::Node::add_child(&root, &first_two);
+    rc_tree::Node::add_child(&root, &second_two);
+
+    let root_id = slab.insert(&root);
+    let first_id = slab.insert(&first_two);
+    let second_id = slab.insert(&second_two);
+    let twos = slab.find_by(|node| node.value == 2);
+    let points_at_second = slab.get(second_id).is_some_and(|node| Rc::ptr_eq(&node, &second_two));
+    println!("ids of the twos: {:?} second id points at second_two: {}", twos, points_at_second);
+
+    //Removing one id leaves the others alone, and a dropped value simply stops being found.
+    println!(
+        "removed first: {} second still found: {}",
+        slab.remove(first_id),
+        slab.get(second_id).is_some()
+    );
+    drop(root);
+    drop(second_two);
+    println!("root after drop: {:?}", slab.get(root_id).map(|node| node.value));
+    println!("compacted: {:?}", slab.compact());
+}
+
+fn freezing_a_value_while_a_guard_lives() {
+    //Freezable in freeze.rs can be changed through &self like any RefCell, but only while nobody
+    // holds a FreezeGuard for it. The guard's Drop is what makes it writable again.
+    let settings = Freezable::new(vec!["dark mode"]);
+    if let Some(mut list) = settings.get_mut() {
+        list.push("large text");
+    }
+
+    {
+        let _guard = settings.freeze();
+        let _second_guard = settings.freeze();
+        println!("frozen: {} get_mut is none: {}", settings.is_frozen(), settings.get_mut().is_none());
+    }
+
+    //A RefMut taken before the freeze would let the value keep changing, so freeze() refuses and
+    // returns None while one is alive.
+    if let Some(mut list) = settings.get_mut() {
+        println!("freeze while writing is none: {}", settings.freeze().is_none());
+        list.push("high contrast");
+    }
+
+    println!("after the guards drop, get_mut is some: {}", settings.get_mut().is_some());
+    if let Some(mut list) = settings.get_mut() {
+        list.push("no animations");
+    }
+    println!("settings: {:?}", *settings.get());
+}
+
+fn indexing_a_shared_vec_through_guards() {
+    //at() and at_mut() in shared_vec.rs hand out guards for a single element of a shared Vec. Both
+    // return an error instead of panicking, for a bad index and for a borrow conflict alike.
+    let numbers = SharedVec::new(vec![4, 2, 7, 1]);
+
+    //One pass of bubble sort. The two shared guards are compared, and dropped at the end of the
+    // block so the swap can borrow the Vec mutably.
+    let pass = |numbers: &SharedVec<i32>| -> Result<(), IndexOrBorrowError> {
+        for index in 0..numbers.len()? - 1 {
+            let out_of_order = {
+                let left = numbers.at(index)?;
+                let right = numbers.at(index + 1)?;
+                *left > *right
+            };
+            if out_of_order {
+                numbers.swap(index, index + 1)?;
+            }
+        }
+        Ok(())
+    };
+    println!("bubble pass: {:?}", pass(&numbers));
+    println!(
+        "first: {} last: {}",
+        *numbers.first().expect("not empty"),
+        *numbers.last().expect("not empty")
+    );
+
+    //While a shared guard is alive at_mut() reports the conflict, and once it is dropped the same
+    // call succeeds.
+    let held = numbers.at(0);
+    println!("holding a guard, at_mut gives: {:?}", numbers.at_mut(1).err());
+    drop(held);
+    if let Ok(mut second) = numbers.at_mut(1) {
+        *second *= 10;
+    }
+    let values: Vec<_> = (0..4).map(|index| numbers.at(index).map(|value| *value)).collect();
+    println!("after at_mut: {:?}", values);
+    println!("swap with itself: {:?}", numbers.swap(2, 2));
+
+    //The Display strings of both kinds of error.
+    if let Err(error) = numbers.at(9) {
+        println!("error: {}", error);
+    }
+    let guard = numbers.at(0);
+    if let Err(error) = numbers.at_mut(0) {
+        println!("error: {}", error);
+    }
+    drop(guard);
+}
+
+fn sharing_a_deque_between_two_handles() {
+    //SharedDeque in deque.rs is the same idea as SharedPQueue, but items can go in and come out at
+    // either end. Whatever one handle does, the other one sees.
+    let front_desk = SharedDeque::new();
+    let back_office = front_desk.clone();
+
+    front_desk.push_back("regular");
+    front_desk.push_front("urgent");
+    back_office.push_back("late");
+    println!("front_desk len: {} back_office len: {}", front_desk.len(), back_office.len());
+
+    println!("back_office pops front: {:?}", back_office.pop_front());
+    println!("front_desk pops back: {:?}", front_desk.pop_back());
+    println!("left for either: {:?}", back_office.pop_front());
+    println!("both empty: {} {}", front_desk.is_empty(), back_office.is_empty());
+}
+
+fn finding_the_nodes_on_cycles() {
+    //nodes_in_cycles() picks out the nodes that sit on a cycle, here 1 -> 2 -> 3 -> 1 and the
+    // self loop on 6. The entry node 0 and the tail 4 -> 5 only hang off the cycle.
+    let nodes: Vec<Rc<GraphNode>> = (0..=6).map(GraphNode::new).collect();
+    for (from, to) in [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 6)] {
+        nodes[from].add_edge(&nodes[to]);
+    }
+
+    //With debug_cycle_checks on, add_edge refuses to close a cycle, so there is nothing to find.
+    #[cfg(not(all(feature = "debug_cycle_checks", debug_assertions)))]
+    {
+        nodes[3].add_edge(&nodes[1]);
+        nodes[6].add_edge(&nodes[6]);
+    }
+
+    let on_cycles: Vec<i32> = nodes_in_cycles(&nodes[0])
+        .iter()
+        .map(|node| node.value())
+        .collect();
+    println!("nodes on cycles: {:?}", on_cycles);
+
+    //Clearing every node's edges breaks the cycles so all seven nodes can be freed.
+    for node in &nodes {
+        node.clear_edges();
+    }
+}
+
+fn searching_an_rc_tree_with_predicates() {
+    //        1
+    //      /   \
+    //     4     12
+    //    / \     \
+    //   7   20    8
+    let root = tree!(1 => [4 => [7, 20], 12 => [8]]);
+    let four = Rc::clone(&root.children()[0]);
+
+    //Pre-order means 20 is found before 12, even though 12 is closer to the root.
+    let first_big = rc_tree::Node::find_first(&root, |node| node.value > 10);
+    println!("first value > 10: {:?}", first_big.map(|node| node.value));
+    let evens: Vec<i32> = rc_tree::Node::find_all(&root, |node| node.value % 2 == 0)
+        .iter()
+        .map(|node| node.value)
+        .collect();
+    println!("even values: {:?}", evens);
+    println!("any 3 under 4: {}", rc_tree::Node::any_in_subtree(&four, |node| node.value == 3));
+
+    //The predicate borrows other nodes' children while the search is going on, which is fine since
+    // the search itself holds no borrow while the predicate runs.
+    let has_sibling = rc_tree::Node::find_all(&root, |node| {
+        node.parent().is_some_and(|parent| parent.children().len() > 1)
+    });
+    println!("nodes with a sibling: {}", has_sibling.len());
+
+    let path: Vec<i32> = rc_tree::Node::find_path(&root, |node| node.value == 8)
+        .unwrap_or_default()
+        .iter()
+        .map(|node| node.value)
+        .collect();
+    println!("path to 8: {:?}", path);
+
+    //There is no REPL in this crate to type a find-if command into, so find_if() is called with the
+    // text such a command would have been given.
+    for source in ["value > 10", "value => 10"] {
+        match find_if(&root, source) {
+            Ok(found) => println!("find-if {}: {:?}", source, found.map(|node| node.value)),
+            Err(error) => println!("find-if {}: {}", source, error),
+        }
+    }
+}
+
+fn estimating_heap_usage(deferred: &TaskQueue) {
+    //HeapSize in heap_size.rs estimates the heap memory behind a value. Each Cons of a list is one
+    // Box, so three elements are three allocations the size of a List<i32>, and Nil owns nothing.
+    let list: List<i32> = list![1, 2, 3];
+    println!(
+        "list of 3: {} bytes ({} per Cons) empty list: {}",
+        list.owned_bytes(),
+        std::mem::size_of::<List<i32>>(),
+        List::<i32>::Nil.owned_bytes()
+    );
+
+    //estimated_heap_bytes() only counts the Boxes, so it grows by exactly one List<T> per element.
+    // A bigger element type makes every Box bigger too.
+    for len in [0, 1, 10, 100] {
+        let numbers: List<i32> = (0..len).collect();
+        let words: List<String> = (0..len).map(|n| n.to_string()).collect();
+        println!(
+            "{} elements: {} bytes of i32 boxes, {} bytes of String boxes",
+            len,
+            estimated_heap_bytes(&numbers),
+            estimated_heap_bytes(&words)
+        );
+    }
+
+    let mut tree = Node::new(2);
+    tree.insert(1);
+    tree.insert(3);
+    println!("tree of 3: {} bytes", tree.owned_bytes());
+
+    //A diamond, 0 -> 1 -> 3 and 0 -> 2 -> 3. Node 3 is owned by nodes 1 and 2 and by the Vec, so
+    // node 1 only claims a third of it, while reachable_bytes() counts it once.
+    let diamond: Vec<Rc<GraphNode>> = (0..4).map(GraphNode::new).collect();
+    for (from, to) in [(0, 1), (0, 2), (1, 3), (2, 3)] {
+        diamond[from].add_edge(&diamond[to]);
+    }
+    println!(
+        "diamond graph: node 1 owns {} reachable from 0: {}",
+        GraphNode::owned_bytes(&diamond[1]),
+        reachable_bytes(&diamond[0])
+    );
+
+    //Two handles to one String. Split between the two owners, the shares add back up to the whole
+    // allocation, and counted by address the string is only counted once, so both agree.
+    let text = Rc::new(String::with_capacity(64));
+    let handles = vec![Rc::clone(&text), text];
+    println!(
+        "two handles: owned {} reachable {}",
+        handles.owned_bytes(),
+        reachable_bytes(&handles)
+    );
+
+    //A third owner outside the Vec takes its share of the string, but the string is still
+    // reachable from the Vec in full.
+    let outside = Rc::clone(&handles[0]);
+    println!(
+        "with a third owner: owned {} reachable {} outside share {}",
+        handles.owned_bytes(),
+        reachable_bytes(&handles),
+        outside.owned_bytes()
+    );
+
+    //The totals are printed at the very end. The task owns the diamond and the handles, so they
+    // stay alive until then.
+    deferred.push(move || {
+        println!(
+            "heap stats: diamond reachable {} bytes, shared string reachable {} bytes",
+            reachable_bytes(&diamond[0]),
+            reachable_bytes(&handles)
+        )
+    });
+}
+
+fn closing_a_shared_descriptor_once() {
+    //SharedFd in fd.rs closes its descriptor in Drop, but the Drop belongs to the value inside the
+    // Rc, so it only runs after the last duplicate is gone.
+    let closes = CloseCounter::new();
+    let fd = SharedFd::open(3, &closes);
+    let duplicates: Vec<SharedFd> = (0..3).map(|_| fd.dup()).collect();
+    println!("fd {} open handles: {}", fd.number(), fd.open_handles());
+
+    drop(duplicates);
+    println!(
+        "after dropping the duplicates: handles {} closes {}",
+        fd.open_handles(),
+        closes.close_count()
+    );
+    drop(fd);
+    println!("after dropping the original: closes {}", closes.close_count());
+}
+
+fn stepping_a_grid_of_shared_cells() {
+    //automaton.rs keeps a Game of Life board as Rc<RefCell<Cell>> values that know their
+    // neighbours through Weak pointers. A blinker flips between a row and a column of three.
+    let width = 5;
+    let cells = automaton::grid(width, 5, &[(2, 1), (2, 2), (2, 3)]);
+    println!("generation 0:\n{}", automaton::render(&cells, width));
+    automaton::step(&cells);
+    println!("generation 1:\n{}", automaton::render(&cells, width));
+    automaton::step(&cells);
+    println!("generation 2:\n{}", automaton::render(&cells, width));
+
+    //Only the grid holds strong references, the neighbour links are all Weak.
+    let centre = &cells[12];
+    println!("centre cell strong: {} weak: {}", Rc::strong_count(centre), Rc::weak_count(centre));
+}
+
+fn passing_values_through_a_bounded_channel() {
+    //LocalChannel in channel.rs holds at most `capacity` values. Two senders share the same queue
+    // and the values come out in the order they were sent, whichever sender sent them.
+    let channel = LocalChannel::new(3);
+    let producer_a = channel.sender();
+    let producer_b = producer_a.clone();
+    let consumer = channel.receiver();
+
+    println!("a sends 1: {:?}", producer_a.try_send(1));
+    println!("b sends 2: {:?}", producer_b.try_send(2));
+    println!("a sends 3: {:?}", producer_a.try_send(3));
+    //The queue is full, so the value comes straight back instead of being dropped.
+    println!("b sends 4: {:?}", producer_b.try_send(4));
+    println!("queued: {}", channel.len());
+
+    println!("received: {:?}", consumer.try_recv());
+    channel.close();
+    println!("a sends 5 after close: {:?}", producer_a.try_send(5));
+
+    //The two values sent before close() are still delivered, and only then is it Disconnected.
+    while let Ok(value) = consumer.try_recv() {
+        println!("drained after close: {}", value);
+    }
+    println!("after draining: {:?} empty: {}", consumer.try_recv(), channel.is_empty());
+
+    //The handles only hold Weak pointers, so dropping the channel is noticed from both sides.
+    let channel = LocalChannel::new(1);
+    let sender = channel.sender();
+    let receiver = channel.receiver();
+    println!("empty but open: {:?}", receiver.try_recv());
+    drop(channel);
+    println!(
+        "channel dropped, send: {:?} recv: {:?}",
+        sender.try_send("hello"),
+        receiver.try_recv()
+    );
+
+    //A lot of traffic through a small channel still frees every payload. Each queued value is a
+    // clone of `payload`, so its strong count shows how many are still alive somewhere.
+    let payload = Rc::new("payload");
+    let channel = LocalChannel::new(8);
+    let sender = channel.sender();
+    let receiver = channel.receiver();
+    let (mut rejected, mut received) = (0, 0);
+    for round in 0..1000 {
+        //A rejected value comes back inside the error and is dropped right here.
+        if sender.try_send(Rc::clone(&payload)).is_err() {
+            rejected += 1;
+        }
+        if round % 3 == 0 && receiver.try_recv().is_ok() {
+            received += 1;
+        }
+    }
+    println!(
+        "rejected: {} received: {} still queued: {} payload count: {}",
+        rejected,
+        received,
+        channel.len(),
+        Rc::strong_count(&payload)
+    );
+    drop(channel);
+    println!("payload count after dropping the channel: {}", Rc::strong_count(&payload));
+}
+
+fn building_structures_with_macros() {
+    //The macros in macros.rs expand into the same calls that would be written out by hand, so a
+    // tree built with tree! is structurally equal to one built with add_child().
+    let by_hand = rc_tree::Node::new(1);
+    let two = rc_tree::Node::new(2);
+    rc_tree::Node::add_child(&by_hand, &two);
+    rc_tree::Node::add_child(&two, &rc_tree::Node::new(4));
+    rc_tree::Node::add_child(&two, &rc_tree::Node::new(5));
+    rc_tree::Node::add_child(&by_hand, &rc_tree::Node::new(3));
+
+    let by_macro = tree!(1 => [2 => [4, 5], 3]);
+    println!("same tree: {}", rc_tree::Node::structural_eq(&by_hand, &by_macro));
+    println!(
+        "parent of 4: {:?}",
+        by_macro.children()[0].children()[0].parent().map(|parent| parent.value)
+    );
+
+    //Trailing commas are fine, a value on its own is a single node, and nesting works at any depth.
+    let trailing = tree!(1 => [2 => [4, 5,], 3,]);
+    println!("with trailing commas: {}", rc_tree::Node::structural_eq(&by_macro, &trailing));
+    let single = tree!("alone");
+    println!("single node children: {}", single.children().len());
+    let deep = tree!(1 => [2 => [3 => [4 => [5]]]]);
+    let mut depth = 1;
+    let mut current = deep;
+    while let Some(child) = current.children().first().cloned() {
+        depth += 1;
+        current = child;
+    }
+    println!("deepest value: {} depth: {}", current.value, depth);
+
+    //list! builds the cons list from the book without writing out every Box::new.
+    let by_hand = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Cons(3, Box::new(List::Nil))))));
+    println!("same list: {}", list![1, 2, 3,] == by_hand);
+    println!("empty list: {}", list![] == List::<i32>::Nil);
+
+    let shared = rc_refcell!(String::from("shared"));
+    let other = Rc::clone(&shared);
+    other.borrow_mut().push_str(" and changed");
+    println!("rc_refcell: {}", shared.borrow());
+}
+
+fn sharing_subtrees_between_map_versions() {
+    //PMap in pmap.rs never changes in place. Every insert returns a new version of the map, and the
+    // old version is still there. Inserting the keys in sorted order would turn a plain search tree
+    // into a list, but PMap rebalances as it goes.
+    let mut map = PMap::new();
+    println!("new map is empty: {}", map.is_empty());
+    for key in 1..=15 {
+        map = map.insert(key, key * 10);
+    }
+    println!("len: {} height: {}", map.len(), map.height());
+
+    let before = map.clone();
+    let after = map.insert(16, 160).insert(3, 333);
+    println!("before: len {} get(16) {:?} get(3) {:?}", before.len(), before.get(&16), before.get(&3));
+    println!("after: len {} get(16) {:?} get(3) {:?}", after.len(), after.get(&16), after.get(&3));
+    println!("after keys: {:?}", after.keys());
+
+    //Only the nodes on the paths down to 16 and 3 were copied. Every other node of `after` is the
+    // same allocation as in `before`.
+    println!(
+        "nodes in after: {} shared with before: {}",
+        after.len(),
+        after.shared_nodes(&before)
+    );
+    println!("map.clone() shares everything: {}", before.shared_nodes(&map) == map.len());
+}
+
+fn undoing_edits_to_a_versioned_tree() {
+    //VersionedTree in versioned_tree.rs keeps frozen snapshots of an rc_tree::Node tree. A commit
+    // only allocates the nodes on the path to whatever was edited, the rest is shared with the
+    // snapshot before it.
+    let mut versions = VersionedTree::new(tree!("docs" => ["guide" => ["intro"], "api"]));
+    let first = versions.commit();
+
+    let guide = Rc::clone(&versions.current().children()[0]);
+    rc_tree::Node::add_child(&guide, &rc_tree::Node::new("setup"));
+    let second = versions.commit();
+
+    let (before, after) = (versions.checkout(first).unwrap(), versions.checkout(second).unwrap());
+    println!(
+        "guide children before: {} after: {}",
+        before.children[0].children.len(),
+        after.children[0].children.len()
+    );
+    println!(
+        "api shared: {} guide shared: {}",
+        Rc::ptr_eq(&before.children[1], &after.children[1]),
+        Rc::ptr_eq(&before.children[0], &after.children[0])
+    );
+
+    //restore() thaws an old snapshot back into the live tree, which is how an undo works.
+    versions.restore(first).unwrap();
+    println!("guide children after undo: {}", versions.current().children()[0].children().len());
+    let third = versions.commit();
+    println!("undo shares the first root: {}", Rc::ptr_eq(&before, &versions.checkout(third).unwrap()));
+}
+
+fn comparing_designs_under_churn(deferred: &TaskQueue) {
+    //experiments.rs runs one workload against three designs. Each round makes a parent with a few
+    // children that point back at it and then drops the parent. Only the strong back pointers leave
+    // anything behind, and they leave a whole round behind every time.
+    //The churn runs now, and the table is printed with the other reports once every section is done.
+    let reports: Vec<_> = [DesignFlavor::StrongCycle, DesignFlavor::WeakBackRef, DesignFlavor::ArenaIndices]
+        .into_iter()
+        .map(|flavor| churn(8, flavor))
+        .collect();
+    deferred.push(move || print_churn_reports(&reports));
+}
+
+fn print_churn_reports(reports: &[ChurnReport]) {
+    println!("{:<14} {:>6} {:>6} {:>9}  live per round", "design", "live", "bytes", "children");
+    for report in reports {
+        println!(
+            "{:<14} {:>6} {:>6} {:>9}  {}",
+            format!("{:?}", report.flavor),
+            report.live_objects.last().unwrap_or(&0),
+            report.live_bytes.last().unwrap_or(&0),
+            report.children_processed,
+            report.sparkline()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Every transition in weak_count_lifecycle() is asserted as it happens, so running the demo
+    // checks all of them.
+    #[test]
+    fn weak_count_lifecycle_transitions_hold() {
+        weak_count_lifecycle();
+    }
+
+    #[test]
+    fn weak_count_ignores_the_implicit_weak_held_by_the_strong_pointers() {
+        let value = Rc::new(1);
+        assert_counts(&value, 1, 0);
+        let weak = Rc::downgrade(&value);
+        assert_counts(&value, 1, 1);
+        assert_eq!(weak.weak_count(), 1);
+    }
+
+    #[test]
+    fn cloning_an_rc_leaves_weak_count_alone() {
+        let value = Rc::new(1);
+        let _weak = Rc::downgrade(&value);
+        let clones: Vec<Rc<i32>> = (0..5).map(|_| Rc::clone(&value)).collect();
+        assert_counts(&value, 6, 1);
+        drop(clones);
+        assert_counts(&value, 1, 1);
+    }
+
+    #[test]
+    fn a_weak_outlives_the_value_and_reports_zero() {
+        let value = Rc::new(String::from("gone"));
+        let weak = Rc::downgrade(&value);
+        let other = Weak::clone(&weak);
+        drop(value);
+        assert!(weak.upgrade().is_none());
+        assert_eq!((weak.strong_count(), weak.weak_count()), (0, 0));
+        assert_eq!((other.strong_count(), other.weak_count()), (0, 0));
+    }
+
+    #[test]
+    fn weak_new_has_no_allocation() {
+        let empty: Weak<String> = Weak::new();
+        assert_eq!((empty.strong_count(), empty.weak_count()), (0, 0));
+        assert!(empty.upgrade().is_none());
+        assert!(Weak::clone(&empty).upgrade().is_none());
+    }
+}
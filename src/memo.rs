@@ -0,0 +1,309 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::rc::{Rc, Weak};
+
+//A memoization cache that only stores Weak pointers to its results. A result stays cached for as
+// long as some caller is still holding the Rc<V> it was given. Once every caller drops it, the
+// value is cleaned up and the next request for that key computes it again.
+//Optionally there is a second tier on top. It holds strong pointers to the `lru_capacity` most
+// recently used values, so a hot value stays cached even after every caller has dropped it. Once it
+// falls out of that tier it is back to being held only by whoever else still has it.
+pub struct WeakMemo<K, V> {
+    entries: RefCell<HashMap<K, Weak<V>>>,
+    recomputations: Cell<usize>,
+    min_prune_threshold: usize,
+    prune_threshold: Cell<usize>,
+    recent: RefCell<VecDeque<Rc<V>>>,
+    lru_capacity: usize,
+}
+
+impl<K: Hash + Eq, V> WeakMemo<K, V> {
+    //Dead entries are removed on insert whenever the map has grown to `prune_threshold` entries.
+    // After each prune the threshold is moved to twice the number of live entries, but never below
+    // `prune_threshold`. Without that, a map full of live entries would run a whole prune on every
+    // insert once it reached the threshold.
+    pub fn new(prune_threshold: usize) -> WeakMemo<K, V> {
+        WeakMemo::with_strong_lru(prune_threshold, 0)
+    }
+
+    //A capacity of 0 turns the strong tier off, which is the same as new().
+    pub fn with_strong_lru(prune_threshold: usize, lru_capacity: usize) -> WeakMemo<K, V> {
+        WeakMemo {
+            entries: RefCell::new(HashMap::new()),
+            recomputations: Cell::new(0),
+            min_prune_threshold: prune_threshold,
+            prune_threshold: Cell::new(prune_threshold),
+            recent: RefCell::new(VecDeque::with_capacity(lru_capacity)),
+            lru_capacity,
+        }
+    }
+
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> Rc<V>) -> Rc<V> {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+
+        //The borrow is released before `f` runs, so `f` is allowed to use the memo as well.
+        let value = f();
+        self.recomputations.set(self.recomputations.get() + 1);
+        self.insert(key, Rc::clone(&value));
+        value
+    }
+
+    //Counts as a use for the strong tier, so a value that keeps being asked for stays in it.
+    pub fn get(&self, key: &K) -> Option<Rc<V>> {
+        let value = self.entries.borrow().get(key).and_then(Weak::upgrade)?;
+        self.touch(&value);
+        Some(value)
+    }
+
+    //The results come back in the same order as the keys.
+    pub fn get_many<'a>(&self, keys: impl IntoIterator<Item = &'a K>) -> Vec<Option<Rc<V>>>
+    where
+        K: 'a,
+    {
+        keys.into_iter().map(|key| self.get(key)).collect()
+    }
+
+    //Stores a value that was computed somewhere else. It replaces whatever the key had before.
+    pub fn insert(&self, key: K, value: Rc<V>) {
+        {
+            let mut entries = self.entries.borrow_mut();
+            if entries.len() >= self.prune_threshold.get() {
+                self.remove_dead(&mut entries);
+            }
+            entries.insert(key, Rc::downgrade(&value));
+        }
+        self.touch(&value);
+    }
+
+    pub fn insert_many(&self, items: impl IntoIterator<Item = (K, Rc<V>)>) {
+        for (key, value) in items {
+            self.insert(key, value);
+        }
+    }
+
+    //Removes every dead entry right away instead of waiting for the threshold, and returns how many
+    // were removed.
+    pub fn prune(&self) -> usize {
+        self.remove_dead(&mut self.entries.borrow_mut())
+    }
+
+    fn remove_dead(&self, entries: &mut HashMap<K, Weak<V>>) -> usize {
+        let before = entries.len();
+        entries.retain(|_, weak| weak.strong_count() > 0);
+        let live = entries.len();
+        self.prune_threshold.set(self.min_prune_threshold.max(2 * live));
+        before - live
+    }
+
+    //How many entries the map can grow to before the next insert prunes it.
+    pub fn prune_threshold(&self) -> usize {
+        self.prune_threshold.get()
+    }
+
+    //Moves `value` to the most recent end of the strong tier. The value that falls off the other
+    // end is only dropped after the borrow is released, in case its drop uses the memo.
+    fn touch(&self, value: &Rc<V>) {
+        if self.lru_capacity == 0 {
+            return;
+        }
+        let evicted = {
+            let mut recent = self.recent.borrow_mut();
+            recent.retain(|held| !Rc::ptr_eq(held, value));
+            recent.push_back(Rc::clone(value));
+            if recent.len() > self.lru_capacity {
+                recent.pop_front()
+            } else {
+                None
+            }
+        };
+        drop(evicted);
+    }
+
+    //Number of entries whose value is still alive somewhere.
+    pub fn live_entries(&self) -> usize {
+        self.entries
+            .borrow()
+            .values()
+            .filter(|weak| weak.strong_count() > 0)
+            .count()
+    }
+
+    //Number of entries in the map, including dead ones that have not been pruned yet.
+    pub fn tracked_entries(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn recomputations(&self) -> usize {
+        self.recomputations.get()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> WeakMemo<K, V> {
+    //Every entry whose value is still alive, in no particular order. The pairs are collected first,
+    // so the memo can be used freely while iterating.
+    pub fn iter_live(&self) -> impl Iterator<Item = (K, Rc<V>)> {
+        let live: Vec<(K, Rc<V>)> = self
+            .entries
+            .borrow()
+            .iter()
+            .filter_map(|(key, weak)| Some((key.clone(), weak.upgrade()?)))
+            .collect();
+        live.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live_keys(memo: &WeakMemo<i32, String>) -> Vec<i32> {
+        let mut keys: Vec<i32> = memo.iter_live().map(|(key, _)| key).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn without_the_strong_tier_values_die_with_their_callers() {
+        let memo: WeakMemo<i32, String> = WeakMemo::new(8);
+        let one = memo.get_or_insert_with(1, || Rc::new("one".to_string()));
+        assert!(Rc::ptr_eq(&one, &memo.get(&1).unwrap()));
+        drop(one);
+        assert!(memo.get(&1).is_none());
+        assert_eq!(memo.tracked_entries(), 1);
+        assert_eq!(memo.prune(), 1);
+        assert_eq!(memo.tracked_entries(), 0);
+    }
+
+    #[test]
+    fn the_strong_tier_keeps_exactly_capacity_values_alive() {
+        let memo: WeakMemo<i32, String> = WeakMemo::with_strong_lru(16, 2);
+        memo.insert_many((1..=4).map(|key| (key, Rc::new(key.to_string()))));
+        assert_eq!(live_keys(&memo), [3, 4]);
+        assert_eq!(memo.live_entries(), 2);
+    }
+
+    #[test]
+    fn getting_a_value_makes_it_the_most_recent() {
+        let memo: WeakMemo<i32, String> = WeakMemo::with_strong_lru(16, 2);
+        memo.insert(1, Rc::new("one".to_string()));
+        memo.insert(2, Rc::new("two".to_string()));
+        memo.get(&1);
+        memo.insert(3, Rc::new("three".to_string()));
+        assert_eq!(live_keys(&memo), [1, 3]);
+    }
+
+    #[test]
+    fn an_aged_out_value_dies_once_its_callers_are_gone() {
+        let memo: WeakMemo<i32, String> = WeakMemo::with_strong_lru(16, 1);
+        let one = memo.get_or_insert_with(1, || Rc::new("one".to_string()));
+        memo.insert(2, Rc::new("two".to_string()));
+        assert!(memo.get(&1).is_some());
+
+        //Two was pushed out by the get above, and nobody else holds it.
+        assert!(memo.get(&2).is_none());
+        memo.insert(3, Rc::new("three".to_string()));
+        drop(one);
+        assert!(memo.get(&1).is_none());
+        assert_eq!(live_keys(&memo), [3]);
+    }
+
+    #[test]
+    fn get_many_keeps_the_key_order() {
+        let memo: WeakMemo<i32, String> = WeakMemo::with_strong_lru(16, 8);
+        memo.insert_many([(1, Rc::new("one".to_string())), (2, Rc::new("two".to_string()))]);
+        let found: Vec<Option<String>> = memo
+            .get_many(&[2, 3, 1])
+            .into_iter()
+            .map(|value| value.map(|value| value.to_string()))
+            .collect();
+        assert_eq!(found, [Some("two".to_string()), None, Some("one".to_string())]);
+    }
+
+    #[test]
+    fn a_value_is_only_computed_again_after_it_dies() {
+        let memo: WeakMemo<i32, String> = WeakMemo::new(8);
+        let first = memo.get_or_insert_with(1, || Rc::new("one".to_string()));
+        memo.get_or_insert_with(1, || unreachable!("the value is still alive"));
+        assert_eq!(memo.recomputations(), 1);
+        drop(first);
+        memo.get_or_insert_with(1, || Rc::new("one".to_string()));
+        assert_eq!(memo.recomputations(), 2);
+    }
+
+    #[test]
+    fn a_held_result_comes_back_as_the_same_allocation() {
+        let memo: WeakMemo<i32, String> = WeakMemo::new(8);
+        let first = memo.get_or_insert_with(1, || Rc::new("one".to_string()));
+        let second = memo.get_or_insert_with(1, || Rc::new("other".to_string()));
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(memo.recomputations(), 1);
+    }
+
+    #[test]
+    fn interleaved_keys_do_not_interfere() {
+        let memo: WeakMemo<i32, String> = WeakMemo::new(8);
+        let one = memo.get_or_insert_with(1, || Rc::new("one".to_string()));
+        let two = memo.get_or_insert_with(2, || Rc::new("two".to_string()));
+        drop(one);
+        assert_eq!(*memo.get_or_insert_with(2, || Rc::new("again".to_string())), "two");
+        assert_eq!(*memo.get_or_insert_with(1, || Rc::new("one again".to_string())), "one again");
+        assert_eq!(memo.recomputations(), 3);
+        assert_eq!(*two, "two");
+    }
+
+    #[test]
+    fn pruning_starts_at_the_threshold_and_keeps_live_entries() {
+        let memo: WeakMemo<i32, String> = WeakMemo::new(3);
+        let kept = memo.get_or_insert_with(1, || Rc::new("kept".to_string()));
+        drop(memo.get_or_insert_with(2, || Rc::new("dead".to_string())));
+        drop(memo.get_or_insert_with(3, || Rc::new("dead".to_string())));
+        assert_eq!(memo.tracked_entries(), 3);
+
+        //The map has reached 3 entries, so this insert prunes the two dead ones first.
+        let _four = memo.get_or_insert_with(4, || Rc::new("four".to_string()));
+        assert_eq!(memo.tracked_entries(), 2);
+        assert_eq!(memo.live_entries(), 2);
+        assert!(Rc::ptr_eq(&kept, &memo.get(&1).unwrap()));
+    }
+
+    #[test]
+    fn the_threshold_doubles_while_every_entry_stays_alive() {
+        let memo: WeakMemo<i32, i32> = WeakMemo::new(2);
+        let mut held = Vec::new();
+        let mut thresholds = Vec::new();
+        for key in 0..100 {
+            held.push(memo.get_or_insert_with(key, || Rc::new(key)));
+            if thresholds.last() != Some(&memo.prune_threshold()) {
+                thresholds.push(memo.prune_threshold());
+            }
+        }
+        //Only one prune per doubling, instead of one on every insert from the third one on.
+        assert_eq!(thresholds, [2, 4, 8, 16, 32, 64, 128]);
+        assert_eq!(memo.live_entries(), 100);
+
+        //Once the values are gone, the next prune brings the threshold back down.
+        held.clear();
+        assert_eq!(memo.prune(), 100);
+        assert_eq!(memo.prune_threshold(), 2);
+    }
+
+    #[test]
+    fn the_threshold_never_drops_below_the_one_given() {
+        let memo: WeakMemo<i32, i32> = WeakMemo::new(10);
+        let _one = memo.get_or_insert_with(1, || Rc::new(1));
+        memo.prune();
+        assert_eq!(memo.prune_threshold(), 10);
+    }
+
+    #[test]
+    fn below_the_threshold_dead_entries_are_kept() {
+        let memo: WeakMemo<i32, String> = WeakMemo::new(4);
+        drop(memo.get_or_insert_with(1, || Rc::new("dead".to_string())));
+        let _two = memo.get_or_insert_with(2, || Rc::new("two".to_string()));
+        assert_eq!(memo.tracked_entries(), 2);
+        assert_eq!(memo.live_entries(), 1);
+    }
+}
@@ -0,0 +1,284 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+
+use crate::compare::compile_vs_runtime::panic_message;
+use crate::drop_log::{DropLog, Logged};
+
+//Five mistakes that compile fine and only show up while the program runs. Each one comes with a
+// fixed version, and both return the same evidence struct so the two can be compared.
+
+//1. Holding a Ref while calling something that needs a RefMut of the same RefCell.
+#[derive(Debug)]
+pub struct BorrowEvidence {
+    pub panic_message: Option<String>,
+}
+
+fn append_len(list: &RefCell<Vec<usize>>, len: usize) {
+    list.borrow_mut().push(len);
+}
+
+pub fn ref_across_borrow_mut() -> BorrowEvidence {
+    BorrowEvidence {
+        panic_message: panic_message(|| {
+            let list = RefCell::new(vec![1, 2]);
+            let items = list.borrow();
+            append_len(&list, items.len());
+        }),
+    }
+}
+
+//Copying the length out ends the Ref before append_len() needs the RefMut.
+pub fn ref_across_borrow_mut_fixed() -> BorrowEvidence {
+    BorrowEvidence {
+        panic_message: panic_message(|| {
+            let list = RefCell::new(vec![1, 2]);
+            let len = list.borrow().len();
+            append_len(&list, len);
+        }),
+    }
+}
+
+//2. A callback that captures an Rc to the struct that owns the callback. The struct and the
+// closure keep each other alive, so dropping the last outside handle frees neither of them.
+#[derive(Debug)]
+pub struct LeakEvidence {
+    pub strong_count_with_callback: usize,
+    pub leaked: bool,
+}
+
+#[derive(Default)]
+struct Button {
+    clicks: Cell<usize>,
+    on_click: RefCell<Option<Box<dyn Fn()>>>,
+}
+
+impl Button {
+    fn click(&self) {
+        if let Some(callback) = self.on_click.borrow().as_ref() {
+            callback();
+        }
+    }
+}
+
+//A Weak that can still be upgraded after the last outside Rc was dropped is the sign of a leak.
+// The callback is taken out afterwards to break the cycle again, so the demo itself leaks nothing.
+fn check_for_leak(button: Rc<Button>) -> LeakEvidence {
+    button.click();
+    let strong_count_with_callback = Rc::strong_count(&button);
+    let weak = Rc::downgrade(&button);
+    drop(button);
+
+    let leaked = match weak.upgrade() {
+        Some(button) => {
+            button.on_click.borrow_mut().take();
+            true
+        }
+        None => false,
+    };
+    LeakEvidence {
+        strong_count_with_callback,
+        leaked,
+    }
+}
+
+pub fn callback_cycle() -> LeakEvidence {
+    let button = Rc::new(Button::default());
+    let captured = Rc::clone(&button);
+    *button.on_click.borrow_mut() = Some(Box::new(move || {
+        captured.clicks.set(captured.clicks.get() + 1);
+    }));
+    check_for_leak(button)
+}
+
+pub fn callback_cycle_fixed() -> LeakEvidence {
+    let button = Rc::new(Button::default());
+    let captured: Weak<Button> = Rc::downgrade(&button);
+    *button.on_click.borrow_mut() = Some(Box::new(move || {
+        if let Some(button) = captured.upgrade() {
+            button.clicks.set(button.clicks.get() + 1);
+        }
+    }));
+    check_for_leak(button)
+}
+
+//3. Cloning an Rc where a borrow would have done. Each clone that gets stored somewhere keeps the
+// count, and the data, around for longer than intended.
+#[derive(Debug)]
+pub struct CountEvidence {
+    pub strong_counts: Vec<usize>,
+    pub totals: Vec<i32>,
+}
+
+pub fn clone_instead_of_borrow() -> CountEvidence {
+    let data = Rc::new(vec![1, 2, 3]);
+    let mut summed: Vec<Rc<Vec<i32>>> = Vec::new();
+    let mut evidence = CountEvidence {
+        strong_counts: Vec::new(),
+        totals: Vec::new(),
+    };
+
+    for _ in 0..4 {
+        let copy = Rc::clone(&data);
+        evidence.totals.push(copy.iter().sum());
+        summed.push(copy);
+        evidence.strong_counts.push(Rc::strong_count(&data));
+    }
+    evidence
+}
+
+pub fn clone_instead_of_borrow_fixed() -> CountEvidence {
+    let data = Rc::new(vec![1, 2, 3]);
+    let mut evidence = CountEvidence {
+        strong_counts: Vec::new(),
+        totals: Vec::new(),
+    };
+
+    for _ in 0..4 {
+        evidence.totals.push(data.iter().sum());
+        evidence.strong_counts.push(Rc::strong_count(&data));
+    }
+    evidence
+}
+
+//4. Upgrading a Weak on every pass through a hot loop. Each upgrade checks the count and bumps it,
+// and the Rc it returns has to decrement it again when it is dropped.
+#[derive(Debug)]
+pub struct TimingEvidence {
+    pub upgrades: usize,
+    pub total: u64,
+    pub elapsed: Duration,
+}
+
+const HOT_LOOP_PASSES: u64 = 200_000;
+
+pub fn upgrade_in_hot_loop() -> TimingEvidence {
+    let owner = Rc::new(3u64);
+    let weak = Rc::downgrade(&owner);
+    let mut upgrades = 0;
+    let mut total = 0;
+
+    let start = Instant::now();
+    for _ in 0..HOT_LOOP_PASSES {
+        if let Some(step) = weak.upgrade() {
+            upgrades += 1;
+            total += *step;
+        }
+    }
+    TimingEvidence {
+        upgrades,
+        total,
+        elapsed: start.elapsed(),
+    }
+}
+
+pub fn upgrade_in_hot_loop_fixed() -> TimingEvidence {
+    let owner = Rc::new(3u64);
+    let weak = Rc::downgrade(&owner);
+    let mut upgrades = 0;
+    let mut total = 0;
+
+    let start = Instant::now();
+    if let Some(step) = weak.upgrade() {
+        upgrades += 1;
+        for _ in 0..HOT_LOOP_PASSES {
+            total += *step;
+        }
+    }
+    TimingEvidence {
+        upgrades,
+        total,
+        elapsed: start.elapsed(),
+    }
+}
+
+//5. Expecting drop(rc_clone) to drop the value. It only drops that one handle, the value lives on
+// for as long as any other Rc to it does.
+#[derive(Debug)]
+pub struct DropEvidence {
+    pub strong_count_after: usize,
+    pub events: Vec<String>,
+}
+
+pub fn dropping_a_clone() -> DropEvidence {
+    let log = DropLog::new();
+    let original = Rc::new(Logged::new(&log, "value"));
+    let clone = Rc::clone(&original);
+    drop(clone);
+    log.record("dropped the clone");
+
+    DropEvidence {
+        strong_count_after: Rc::strong_count(&original),
+        events: log.events(),
+    }
+}
+
+//The value goes away together with its last handle, so every handle has to be dropped. A Weak is
+// kept to read the count afterwards without keeping the value alive.
+pub fn dropping_a_clone_fixed() -> DropEvidence {
+    let log = DropLog::new();
+    let original = Rc::new(Logged::new(&log, "value"));
+    let clone = Rc::clone(&original);
+    let weak = Rc::downgrade(&original);
+    drop(clone);
+    drop(original);
+    log.record("dropped every handle");
+
+    DropEvidence {
+        strong_count_after: weak.strong_count(),
+        events: log.events(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holding_a_ref_panics_and_copying_out_does_not() {
+        let message = ref_across_borrow_mut().panic_message.unwrap();
+        assert!(message.contains("borrowed"), "{}", message);
+        assert_eq!(ref_across_borrow_mut_fixed().panic_message, None);
+    }
+
+    #[test]
+    fn a_strong_capture_leaks_and_a_weak_one_does_not() {
+        let mistake = callback_cycle();
+        assert!(mistake.leaked);
+        assert_eq!(mistake.strong_count_with_callback, 2);
+
+        let fixed = callback_cycle_fixed();
+        assert!(!fixed.leaked);
+        assert_eq!(fixed.strong_count_with_callback, 1);
+    }
+
+    #[test]
+    fn stored_clones_make_the_count_drift() {
+        let mistake = clone_instead_of_borrow();
+        let fixed = clone_instead_of_borrow_fixed();
+        assert_eq!(mistake.strong_counts, [2, 3, 4, 5]);
+        assert_eq!(fixed.strong_counts, [1, 1, 1, 1]);
+        assert_eq!(mistake.totals, fixed.totals);
+    }
+
+    //Timings are too noisy to compare in a test, so only the number of upgrades is checked.
+    #[test]
+    fn caching_the_rc_upgrades_once() {
+        let mistake = upgrade_in_hot_loop();
+        let fixed = upgrade_in_hot_loop_fixed();
+        assert_eq!(mistake.upgrades as u64, HOT_LOOP_PASSES);
+        assert_eq!(fixed.upgrades, 1);
+        assert_eq!(mistake.total, fixed.total);
+    }
+
+    #[test]
+    fn only_dropping_every_handle_drops_the_value() {
+        let mistake = dropping_a_clone();
+        assert_eq!(mistake.strong_count_after, 1);
+        assert_eq!(mistake.events, ["dropped the clone"]);
+
+        let fixed = dropping_a_clone_fixed();
+        assert_eq!(fixed.strong_count_after, 0);
+        assert_eq!(fixed.events, ["drop:value", "dropped every handle"]);
+    }
+}
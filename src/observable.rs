@@ -0,0 +1,157 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+type Observer<T> = Rc<dyn Fn(&T)>;
+
+//Leaves a batch when dropped. If `f` panics and the panic is caught further up, the depth still goes
+// back down, otherwise every later set() would think it was inside a batch and never notify again.
+struct BatchDepth<'a>(&'a Cell<usize>);
+
+impl Drop for BatchDepth<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+//A value that tells its observers whenever it is set. The observers are Rc<dyn Fn(&T)> so the list
+// can be cloned before they are called, which lets an observer subscribe or set the value again
+// without running into an already borrowed RefCell.
+pub struct Observable<T: Clone + PartialEq> {
+    value: RefCell<T>,
+    observers: RefCell<Vec<Observer<T>>>,
+    batch_depth: Cell<usize>,
+    //The value from before the outermost batch started, used to tell whether the batch changed it.
+    batch_start: RefCell<Option<T>>,
+}
+
+impl<T: Clone + PartialEq> Observable<T> {
+    pub fn new(value: T) -> Observable<T> {
+        Observable {
+            value: RefCell::new(value),
+            observers: RefCell::new(Vec::new()),
+            batch_depth: Cell::new(0),
+            batch_start: RefCell::new(None),
+        }
+    }
+
+    pub fn get(&self) -> T {
+        self.value.borrow().clone()
+    }
+
+    pub fn subscribe(&self, observer: impl Fn(&T) + 'static) {
+        self.observers.borrow_mut().push(Rc::new(observer));
+    }
+
+    //Outside of a batch every set notifies the observers straight away.
+    pub fn set(&self, value: T) {
+        *self.value.borrow_mut() = value;
+        if self.batch_depth.get() == 0 {
+            self.notify();
+        }
+    }
+
+    //Runs `f` without notifying anyone about the sets it makes. When the outermost batch finishes
+    // the observers are told once, and only if the value ended up different from where it started.
+    // Sets that cancel each other out produce no notification at all.
+    pub fn batch(&self, f: impl FnOnce()) {
+        if self.batch_depth.get() == 0 {
+            *self.batch_start.borrow_mut() = Some(self.get());
+        }
+        self.batch_depth.set(self.batch_depth.get() + 1);
+        let depth = BatchDepth(&self.batch_depth);
+        f();
+        drop(depth);
+
+        if self.batch_depth.get() == 0 {
+            let start = self.batch_start.borrow_mut().take();
+            if start.as_ref() != Some(&*self.value.borrow()) {
+                self.notify();
+            }
+        }
+    }
+
+    fn notify(&self) {
+        let value = self.get();
+        let observers = self.observers.borrow().clone();
+        for observer in &observers {
+            observer(&value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    fn recorded(observable: &Observable<i32>) -> Rc<RefCell<Vec<i32>>> {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let recorder = Rc::clone(&seen);
+        observable.subscribe(move |value| recorder.borrow_mut().push(*value));
+        seen
+    }
+
+    #[test]
+    fn every_set_outside_a_batch_notifies() {
+        let observable = Observable::new(0);
+        let seen = recorded(&observable);
+        observable.set(1);
+        observable.set(1);
+        assert_eq!(*seen.borrow(), [1, 1]);
+    }
+
+    #[test]
+    fn a_batch_notifies_once_with_the_final_value() {
+        let observable = Observable::new(0);
+        let seen = recorded(&observable);
+        observable.batch(|| {
+            observable.set(1);
+            observable.set(2);
+            observable.set(3);
+        });
+        assert_eq!(*seen.borrow(), [3]);
+    }
+
+    #[test]
+    fn a_batch_that_ends_where_it_started_does_not_notify() {
+        let observable = Observable::new(0);
+        let seen = recorded(&observable);
+        observable.batch(|| {
+            observable.set(5);
+            observable.set(0);
+        });
+        observable.batch(|| {});
+        assert!(seen.borrow().is_empty());
+    }
+
+    #[test]
+    fn nested_batches_notify_once_at_the_outermost_end() {
+        let observable = Observable::new(0);
+        let seen = recorded(&observable);
+        observable.batch(|| {
+            observable.set(1);
+            observable.batch(|| observable.set(2));
+            assert!(seen.borrow().is_empty());
+        });
+        assert_eq!(*seen.borrow(), [2]);
+    }
+
+    #[test]
+    fn a_panicking_batch_does_not_silence_later_sets() {
+        let observable = Observable::new(0);
+        let seen = recorded(&observable);
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            observable.batch(|| {
+                observable.set(1);
+                panic!("inside the batch");
+            })
+        }));
+        assert!(result.is_err());
+
+        //The panicking batch never got to notify, but the next set is back to normal.
+        assert!(seen.borrow().is_empty());
+        observable.set(2);
+        assert_eq!(*seen.borrow(), [2]);
+    }
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+use std::rc::Rc;
+
+//Rust drops the fields of a struct in the order they are declared. Sometimes that is the wrong
+// order, for example a cache that flushes into a connection must be dropped before the
+// connection. Wrapping the fields in ManuallyDrop<T> turns off the automatic drop so that the
+// struct's own Drop impl can choose the order.
+pub struct Resource {
+    name: &'static str,
+    recorder: Rc<RefCell<Vec<&'static str>>>,
+}
+
+impl Resource {
+    pub fn new(name: &'static str, recorder: &Rc<RefCell<Vec<&'static str>>>) -> Resource {
+        Resource {
+            name,
+            recorder: Rc::clone(recorder),
+        }
+    }
+}
+
+impl Drop for Resource {
+    fn drop(&mut self) {
+        self.recorder.borrow_mut().push(self.name);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Part {
+    Connection,
+    Cache,
+    Logger,
+}
+
+pub struct Service {
+    connection: ManuallyDrop<Resource>,
+    cache: ManuallyDrop<Resource>,
+    logger: ManuallyDrop<Resource>,
+    drop_order: Vec<Part>,
+}
+
+impl Service {
+    pub fn new(recorder: &Rc<RefCell<Vec<&'static str>>>, drop_order: Vec<Part>) -> Service {
+        Service {
+            connection: ManuallyDrop::new(Resource::new("connection", recorder)),
+            cache: ManuallyDrop::new(Resource::new("cache", recorder)),
+            logger: ManuallyDrop::new(Resource::new("logger", recorder)),
+            drop_order,
+        }
+    }
+
+    fn part_mut(&mut self, part: Part) -> &mut ManuallyDrop<Resource> {
+        match part {
+            Part::Connection => &mut self.connection,
+            Part::Cache => &mut self.cache,
+            Part::Logger => &mut self.logger,
+        }
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        //Every part is dropped exactly once. Parts listed more than once are skipped after the
+        // first time, and parts missing from the order are dropped afterwards in declaration order.
+        let mut order = std::mem::take(&mut self.drop_order);
+        order.extend([Part::Connection, Part::Cache, Part::Logger]);
+
+        let mut dropped = Vec::new();
+        for part in order {
+            if dropped.contains(&part) {
+                continue;
+            }
+            dropped.push(part);
+            //Safe because `dropped` guarantees each field is only dropped once, and the fields are
+            // never used again after this point.
+            unsafe { ManuallyDrop::drop(self.part_mut(part)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dropped_with(order: Vec<Part>) -> Vec<&'static str> {
+        let recorder = Rc::new(RefCell::new(Vec::new()));
+        drop(Service::new(&recorder, order));
+        let recorded = recorder.borrow().clone();
+        recorded
+    }
+
+    #[test]
+    fn the_custom_order_is_followed() {
+        assert_eq!(
+            dropped_with(vec![Part::Cache, Part::Logger, Part::Connection]),
+            ["cache", "logger", "connection"]
+        );
+    }
+
+    #[test]
+    fn missing_parts_follow_in_declaration_order() {
+        assert_eq!(dropped_with(vec![Part::Logger]), ["logger", "connection", "cache"]);
+        assert_eq!(dropped_with(Vec::new()), ["connection", "cache", "logger"]);
+    }
+
+    #[test]
+    fn repeated_parts_are_only_dropped_once() {
+        assert_eq!(
+            dropped_with(vec![Part::Cache, Part::Cache, Part::Connection, Part::Cache]),
+            ["cache", "connection", "logger"]
+        );
+    }
+
+    #[test]
+    fn nothing_is_dropped_before_the_service() {
+        let recorder = Rc::new(RefCell::new(Vec::new()));
+        let service = Service::new(&recorder, vec![Part::Logger]);
+        assert!(recorder.borrow().is_empty());
+        drop(service);
+        assert_eq!(recorder.borrow().len(), 3);
+        assert_eq!(Rc::strong_count(&recorder), 1);
+    }
+}
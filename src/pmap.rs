@@ -0,0 +1,265 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+//A map that never changes once it is built. insert() returns a new map instead, and the new map
+// shares every subtree the insert did not touch with the old one through Rc::clone. Only the nodes
+// on the path from the root down to the new key are copied, so an insert costs O(log n) new nodes
+// and the old map keeps working exactly as before.
+//The tree is kept balanced the same way an AVL tree is, so that path stays short.
+pub struct PMap<K, V> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+type Link<K, V> = Option<Rc<PNode<K, V>>>;
+
+struct PNode<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+    height: usize,
+}
+
+impl<K, V> Clone for PMap<K, V> {
+    //Cloning a map only clones the Rc to its root.
+    fn clone(&self) -> Self {
+        PMap {
+            root: self.root.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<K, V> Default for PMap<K, V> {
+    fn default() -> Self {
+        PMap::new()
+    }
+}
+
+impl<K, V> PMap<K, V> {
+    pub fn new() -> PMap<K, V> {
+        PMap { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    //Counted in nodes, so an empty map has height 0 and a single key has height 1.
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    //The keys in sorted order.
+    pub fn keys(&self) -> Vec<&K> {
+        let mut keys = Vec::new();
+        let mut pending: Vec<&PNode<K, V>> = Vec::new();
+        let mut current = self.root.as_deref();
+
+        while current.is_some() || !pending.is_empty() {
+            while let Some(node) = current {
+                pending.push(node);
+                current = node.left.as_deref();
+            }
+            if let Some(node) = pending.pop() {
+                keys.push(&node.key);
+                current = node.right.as_deref();
+            }
+        }
+        keys
+    }
+
+    //How many of this map's nodes are the very same allocations as nodes in `other`. Two maps
+    // where one came from the other by a single insert share all but a handful of nodes.
+    pub fn shared_nodes(&self, other: &PMap<K, V>) -> usize {
+        let theirs: HashSet<*const PNode<K, V>> = other.nodes().into_iter().map(Rc::as_ptr).collect();
+        self.nodes()
+            .into_iter()
+            .filter(|node| theirs.contains(&Rc::as_ptr(node)))
+            .count()
+    }
+
+    fn nodes(&self) -> Vec<&Rc<PNode<K, V>>> {
+        let mut nodes = Vec::new();
+        let mut pending: Vec<&Rc<PNode<K, V>>> = self.root.iter().collect();
+        while let Some(node) = pending.pop() {
+            nodes.push(node);
+            pending.extend(node.left.iter().chain(node.right.iter()));
+        }
+        nodes
+    }
+}
+
+impl<K: Ord, V> PMap<K, V> {
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            current = match key.cmp(&node.key) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Greater => node.right.as_deref(),
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> PMap<K, V> {
+    //Inserting a key that is already there replaces its value in the new map only.
+    pub fn insert(&self, key: K, value: V) -> PMap<K, V> {
+        let (root, added) = insert_into(&self.root, key, value);
+        PMap {
+            root: Some(root),
+            len: self.len + usize::from(added),
+        }
+    }
+}
+
+fn height<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+fn make<K, V>(key: K, value: V, left: Link<K, V>, right: Link<K, V>) -> Rc<PNode<K, V>> {
+    let height = 1 + height(&left).max(height(&right));
+    Rc::new(PNode {
+        key,
+        value,
+        left,
+        right,
+        height,
+    })
+}
+
+//Returns the new subtree and whether the key was new. The subtree on the side the key did not go
+// down is reused as it is.
+fn insert_into<K: Ord + Clone, V: Clone>(link: &Link<K, V>, key: K, value: V) -> (Rc<PNode<K, V>>, bool) {
+    let node = match link {
+        Some(node) => node,
+        None => return (make(key, value, None, None), true),
+    };
+
+    match key.cmp(&node.key) {
+        Ordering::Less => {
+            let (left, added) = insert_into(&node.left, key, value);
+            let key = node.key.clone();
+            (balance(key, node.value.clone(), Some(left), node.right.clone()), added)
+        }
+        Ordering::Greater => {
+            let (right, added) = insert_into(&node.right, key, value);
+            let key = node.key.clone();
+            (balance(key, node.value.clone(), node.left.clone(), Some(right)), added)
+        }
+        Ordering::Equal => (make(key, value, node.left.clone(), node.right.clone()), false),
+    }
+}
+
+//Builds a node from its parts, rotating when one side has become two levels taller than the other.
+// A rotation makes new copies of the two or three nodes it moves around, since the old ones may
+// still be part of an older map. Their grandchildren are shared as they are.
+fn balance<K: Clone, V: Clone>(key: K, value: V, left: Link<K, V>, right: Link<K, V>) -> Rc<PNode<K, V>> {
+    let (left_height, right_height) = (height(&left), height(&right));
+
+    if left_height > right_height + 1 {
+        let l = left.expect("the taller side has a node");
+        if height(&l.left) >= height(&l.right) {
+            let new_right = make(key, value, l.right.clone(), right);
+            return make(l.key.clone(), l.value.clone(), l.left.clone(), Some(new_right));
+        }
+        let lr = l.right.as_ref().expect("the taller side has a node");
+        let new_left = make(l.key.clone(), l.value.clone(), l.left.clone(), lr.left.clone());
+        let new_right = make(key, value, lr.right.clone(), right);
+        return make(lr.key.clone(), lr.value.clone(), Some(new_left), Some(new_right));
+    }
+
+    if right_height > left_height + 1 {
+        let r = right.expect("the taller side has a node");
+        if height(&r.right) >= height(&r.left) {
+            let new_left = make(key, value, left, r.left.clone());
+            return make(r.key.clone(), r.value.clone(), Some(new_left), r.right.clone());
+        }
+        let rl = r.left.as_ref().expect("the taller side has a node");
+        let new_left = make(key, value, left, rl.left.clone());
+        let new_right = make(r.key.clone(), r.value.clone(), rl.right.clone(), r.right.clone());
+        return make(rl.key.clone(), rl.value.clone(), Some(new_left), Some(new_right));
+    }
+
+    make(key, value, left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_keys(keys: impl IntoIterator<Item = i32>) -> PMap<i32, i32> {
+        keys.into_iter().fold(PMap::new(), |map, key| map.insert(key, key * 10))
+    }
+
+    //Checks every node's stored height and that no node has one side more than a level taller
+    // than the other.
+    fn assert_avl<K, V>(map: &PMap<K, V>) {
+        for node in map.nodes() {
+            let (left, right) = (height(&node.left), height(&node.right));
+            assert_eq!(node.height, 1 + left.max(right));
+            assert!(left.abs_diff(right) <= 1);
+        }
+    }
+
+    #[test]
+    fn older_maps_are_not_changed_by_later_inserts() {
+        let empty = PMap::new();
+        let one = empty.insert(1, "one");
+        let two = one.insert(2, "two");
+        let replaced = two.insert(1, "uno");
+
+        assert!(empty.is_empty());
+        assert_eq!((one.len(), one.get(&2)), (1, None));
+        assert_eq!(two.get(&1), Some(&"one"));
+        assert_eq!(replaced.get(&1), Some(&"uno"));
+        assert_eq!(replaced.len(), 2);
+    }
+
+    #[test]
+    fn an_untouched_branch_is_shared() {
+        let before = from_keys([4, 2, 6, 1, 3, 5, 7]);
+        let after = before.insert(8, 80);
+
+        let (old_root, new_root) = (before.root.as_ref().unwrap(), after.root.as_ref().unwrap());
+        assert!(!Rc::ptr_eq(old_root, new_root));
+        assert!(Rc::ptr_eq(old_root.left.as_ref().unwrap(), new_root.left.as_ref().unwrap()));
+        //Only the root, 6, 7 and the new 8 are not shared.
+        assert_eq!(after.shared_nodes(&before), 4);
+        assert_eq!(before.get(&8), None);
+    }
+
+    #[test]
+    fn sequential_inserts_stay_balanced() {
+        let mut map = PMap::new();
+        for key in 0..1000 {
+            map = map.insert(key, key);
+            assert_avl(&map);
+        }
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.height(), 10);
+        assert_eq!(map.keys().into_iter().copied().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn every_rotation_keeps_the_tree_balanced() {
+        //Left-left, right-right, left-right and right-left each come out as a tree of height 2.
+        for keys in [[3, 2, 1], [1, 2, 3], [3, 1, 2], [1, 3, 2]] {
+            let map = from_keys(keys);
+            assert_avl(&map);
+            assert_eq!(map.height(), 2);
+            assert_eq!(map.keys(), [&1, &2, &3]);
+        }
+        let descending = from_keys((0..500).rev());
+        assert_avl(&descending);
+        assert_eq!(descending.get(&123), Some(&1230));
+    }
+}
@@ -0,0 +1,79 @@
+use std::cell::RefCell;
+use std::collections::BinaryHeap;
+use std::rc::Rc;
+
+//A priority queue that several owners can push to and pop from. It is the same Rc<RefCell<T>>
+// pattern as SharedBitSet and SparseMatrix, with a BinaryHeap inside so the largest item always
+// comes out first.
+#[derive(Clone)]
+pub struct SharedPQueue<T: Ord> {
+    heap: Rc<RefCell<BinaryHeap<T>>>,
+}
+
+impl<T: Ord> SharedPQueue<T> {
+    pub fn new() -> SharedPQueue<T> {
+        SharedPQueue {
+            heap: Rc::new(RefCell::new(BinaryHeap::new())),
+        }
+    }
+
+    pub fn push(&self, item: T) {
+        self.heap.borrow_mut().push(item);
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.heap.borrow_mut().pop()
+    }
+}
+
+impl<T: Ord + Clone> SharedPQueue<T> {
+    //BinaryHeap::peek returns a reference into the heap, and that reference cannot outlive the
+    // borrow of the RefCell. Returning a clone means the borrow can end before this returns.
+    pub fn peek(&self) -> Option<T> {
+        self.heap.borrow().peek().cloned()
+    }
+}
+
+impl<T: Ord> Default for SharedPQueue<T> {
+    fn default() -> Self {
+        SharedPQueue::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_pushed_through_one_handle_pop_from_another_in_priority_order() {
+        let producer = SharedPQueue::new();
+        let consumer = producer.clone();
+        for item in [3, 9, 1, 7] {
+            producer.push(item);
+        }
+        let popped: Vec<i32> = std::iter::from_fn(|| consumer.pop()).collect();
+        assert_eq!(popped, [9, 7, 3, 1]);
+        assert_eq!(producer.pop(), None);
+    }
+
+    #[test]
+    fn peek_returns_the_top_without_removing_it() {
+        let queue = SharedPQueue::default();
+        assert_eq!(queue.peek(), None);
+        queue.push(String::from("a"));
+        queue.push(String::from("c"));
+        assert_eq!(queue.peek().as_deref(), Some("c"));
+        assert_eq!(queue.pop().as_deref(), Some("c"));
+        assert_eq!(queue.peek().as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn a_peeked_value_can_be_held_while_pushing() {
+        let queue = SharedPQueue::new();
+        queue.push(1);
+        let top = queue.peek();
+        queue.clone().push(2);
+        assert_eq!(top, Some(1));
+        assert_eq!(queue.peek(), Some(2));
+    }
+}
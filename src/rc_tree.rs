@@ -0,0 +1,422 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::mem::size_of;
+use std::rc::{Rc, Weak};
+
+use crate::heap_size::HeapSize;
+use crate::zip_shared::{structural_zip, EitherOrBoth};
+
+//Returned by Node::reorder_children() when the order is not a permutation of the child indices.
+#[derive(Debug, PartialEq)]
+pub enum BadPermutation {
+    WrongLength { expected: usize, found: usize },
+    OutOfRange(usize),
+    Duplicate(usize),
+}
+
+//The tree from the end of the chapter. Children are owned through Rc<Node<T>> and each node points
+// back at its parent through a Weak<Node<T>>, so a parent and child do not keep each other alive.
+pub struct Node<T> {
+    pub value: T,
+    parent: RefCell<Weak<Node<T>>>,
+    children: RefCell<Vec<Rc<Node<T>>>>,
+    cached_path: RefCell<Option<Rc<str>>>,
+}
+
+impl<T> Node<T> {
+    pub fn new(value: T) -> Rc<Node<T>> {
+        Rc::new(Node {
+            value,
+            parent: RefCell::new(Weak::new()),
+            children: RefCell::new(Vec::new()),
+            cached_path: RefCell::new(None),
+        })
+    }
+
+    pub fn parent(&self) -> Option<Rc<Node<T>>> {
+        self.parent.borrow().upgrade()
+    }
+
+    pub fn children(&self) -> Vec<Rc<Node<T>>> {
+        self.children.borrow().clone()
+    }
+
+    //Adds `child` under `parent`. If the child already had a parent it is moved, which means it is
+    // removed from the old parent's children and every cached path under it becomes stale.
+    pub fn add_child(parent: &Rc<Node<T>>, child: &Rc<Node<T>>) {
+        if let Some(old_parent) = child.parent() {
+            old_parent
+                .children
+                .borrow_mut()
+                .retain(|sibling| !Rc::ptr_eq(sibling, child));
+        }
+
+        *child.parent.borrow_mut() = Rc::downgrade(parent);
+        parent.children.borrow_mut().push(Rc::clone(child));
+        child.invalidate_paths();
+    }
+
+    //Finds `child` among the children of `parent` by pointer, so two children that happen to hold
+    // the same value are still told apart.
+    pub fn index_of_child(parent: &Rc<Node<T>>, child: &Rc<Node<T>>) -> Option<usize> {
+        parent
+            .children
+            .borrow()
+            .iter()
+            .position(|sibling| Rc::ptr_eq(sibling, child))
+    }
+
+    //Moves the child at `from` so it ends up at `to`, shifting the children in between. Like
+    // Vec::remove and Vec::insert this panics if either index is out of range. Only the order
+    // changes, so the cached paths all stay valid.
+    pub fn move_child(parent: &Rc<Node<T>>, from: usize, to: usize) {
+        let mut children = parent.children.borrow_mut();
+        let child = children.remove(from);
+        children.insert(to, child);
+    }
+
+    //Rearranges the children so that the child at `order[i]` ends up at position i. The order has
+    // to mention every current index exactly once, otherwise nothing is changed.
+    pub fn reorder_children(parent: &Rc<Node<T>>, order: &[usize]) -> Result<(), BadPermutation> {
+        let mut children = parent.children.borrow_mut();
+        if order.len() != children.len() {
+            return Err(BadPermutation::WrongLength {
+                expected: children.len(),
+                found: order.len(),
+            });
+        }
+
+        let mut seen = vec![false; children.len()];
+        for &index in order {
+            match seen.get_mut(index) {
+                None => return Err(BadPermutation::OutOfRange(index)),
+                Some(true) => return Err(BadPermutation::Duplicate(index)),
+                Some(seen) => *seen = true,
+            }
+        }
+
+        let reordered = order.iter().map(|&index| Rc::clone(&children[index])).collect();
+        *children = reordered;
+        Ok(())
+    }
+
+    //The first node in pre-order, `root` included, that the predicate accepts. Children are cloned
+    // out before the predicate runs, so no borrow is held while it runs and the predicate is free
+    // to look at the children of any node, this one included.
+    pub fn find_first(root: &Rc<Node<T>>, pred: impl Fn(&Node<T>) -> bool) -> Option<Rc<Node<T>>> {
+        let mut pending = vec![Rc::clone(root)];
+        while let Some(node) = pending.pop() {
+            if pred(&node) {
+                return Some(node);
+            }
+            pending.extend(node.children().into_iter().rev());
+        }
+        None
+    }
+
+    //Every node the predicate accepts, in pre-order.
+    pub fn find_all(root: &Rc<Node<T>>, pred: impl Fn(&Node<T>) -> bool) -> Vec<Rc<Node<T>>> {
+        let mut found = Vec::new();
+        let mut pending = vec![Rc::clone(root)];
+        while let Some(node) = pending.pop() {
+            pending.extend(node.children().into_iter().rev());
+            if pred(&node) {
+                found.push(node);
+            }
+        }
+        found
+    }
+
+    pub fn any_in_subtree(root: &Rc<Node<T>>, pred: impl Fn(&Node<T>) -> bool) -> bool {
+        Node::find_first(root, pred).is_some()
+    }
+
+    //The nodes from `root` down to the first match, both ends included. The path is found by
+    // following the Weak parent links back up from the match until `root` is reached.
+    pub fn find_path(root: &Rc<Node<T>>, pred: impl Fn(&Node<T>) -> bool) -> Option<Vec<Rc<Node<T>>>> {
+        let found = Node::find_first(root, pred)?;
+        let mut path = vec![found];
+        while let Some(last) = path.last() {
+            if Rc::ptr_eq(last, root) {
+                break;
+            }
+            let parent = last.parent().expect("every node below root has a parent");
+            path.push(parent);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    fn invalidate_paths(&self) {
+        self.cached_path.borrow_mut().take();
+        let mut pending = self.children();
+        while let Some(node) = pending.pop() {
+            node.cached_path.borrow_mut().take();
+            pending.extend(node.children());
+        }
+    }
+}
+
+impl<T: Ord> Node<T> {
+    //Sorts the children of `parent` by their values. The sort is stable, so children with equal
+    // values keep the order they were in.
+    pub fn sort_children_by_value(parent: &Rc<Node<T>>) {
+        parent
+            .children
+            .borrow_mut()
+            .sort_by(|a, b| a.value.cmp(&b.value));
+    }
+}
+
+impl<T: Clone + PartialEq> Node<T> {
+    //Two trees are structurally equal when they have the same shape and the same value in every
+    // position. The structural zip stops at the first difference, so no copy of either tree is
+    // ever made.
+    pub fn structural_eq(a: &Rc<Node<T>>, b: &Rc<Node<T>>) -> bool {
+        structural_zip(a, b).all(|entry| matches!(entry, EitherOrBoth::Both(x, y) if x == y))
+    }
+}
+
+impl<T: Display> Node<T> {
+    //The slash separated path from the root down to this node. It is built by following the Weak
+    // parent links the first time it is asked for, and after that the cached Rc<str> is handed
+    // out until the node is moved to a different parent.
+    pub fn cached_path(&self) -> Rc<str> {
+        if let Some(path) = self.cached_path.borrow().as_ref() {
+            return Rc::clone(path);
+        }
+
+        let path: Rc<str> = match self.parent() {
+            Some(parent) => Rc::from(format!("{}/{}", parent.cached_path(), self.value)),
+            None => Rc::from(self.value.to_string()),
+        };
+        *self.cached_path.borrow_mut() = Some(Rc::clone(&path));
+        path
+    }
+}
+
+//The children are counted the same way as a Vec<Rc<Node<T>>>. The Weak parent link does not own
+// anything, so it adds nothing. A cached path is an Rc<str> holding the counts and the text.
+impl<T: HeapSize> HeapSize for Node<T> {
+    fn owned_bytes(&self) -> usize {
+        let path = self.cached_path.borrow().as_ref().map_or(0, |path| {
+            (size_of::<(usize, usize)>() + path.len()) / Rc::strong_count(path)
+        });
+        self.value.owned_bytes() + self.children.borrow().owned_bytes() + path
+    }
+
+    fn reachable_bytes(&self, seen: &mut HashSet<usize>) -> usize {
+        let path = match self.cached_path.borrow().as_ref() {
+            Some(path) if seen.insert(Rc::as_ptr(path) as *const u8 as usize) => {
+                size_of::<(usize, usize)>() + path.len()
+            }
+            _ => 0,
+        };
+        self.value.reachable_bytes(seen) + self.children.borrow().reachable_bytes(seen) + path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_path_is_built_once_and_then_shared() {
+        let root = tree!("usr" => ["lib" => ["rust"]]);
+        let rust = Rc::clone(&root.children()[0].children()[0]);
+        let first = rust.cached_path();
+        let second = rust.cached_path();
+        assert_eq!(&*first, "usr/lib/rust");
+        assert!(Rc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn moving_a_node_rebuilds_the_paths_under_it() {
+        let root = tree!("root" => ["a" => ["child" => ["leaf"]], "b"]);
+        let child = Rc::clone(&root.children()[0].children()[0]);
+        let leaf = Rc::clone(&child.children()[0]);
+        let before = leaf.cached_path();
+        assert_eq!(&*before, "root/a/child/leaf");
+
+        let b = Rc::clone(&root.children()[1]);
+        Node::add_child(&b, &child);
+        assert_eq!(&*child.cached_path(), "root/b/child");
+        assert_eq!(&*leaf.cached_path(), "root/b/child/leaf");
+        assert!(!Rc::ptr_eq(&before, &leaf.cached_path()));
+    }
+
+    #[test]
+    fn a_root_path_is_just_its_value() {
+        assert_eq!(&*Node::new(7).cached_path(), "7");
+    }
+
+    #[test]
+    fn structural_eq_compares_values_and_shape() {
+        assert!(Node::structural_eq(&tree!(1 => [2, 3]), &tree!(1 => [2, 3])));
+        assert!(!Node::structural_eq(&tree!(1 => [2, 3]), &tree!(1 => [2, 4])));
+        assert!(!Node::structural_eq(&tree!(1 => [2, 3]), &tree!(1 => [2])));
+        assert!(!Node::structural_eq(&tree!(1 => [2]), &tree!(1 => [2 => [3]])));
+    }
+
+    //Renders a tree as value(children...), for example 1(2 3(4)).
+    fn render<T: Display>(node: &Rc<Node<T>>) -> String {
+        let children = node.children();
+        if children.is_empty() {
+            return node.value.to_string();
+        }
+        let inner: Vec<String> = children.iter().map(render).collect();
+        format!("{}({})", node.value, inner.join(" "))
+    }
+
+    //Rearranging children must never break the Weak links back up to the parent.
+    fn assert_parent_links<T>(root: &Rc<Node<T>>) {
+        let mut pending = vec![Rc::clone(root)];
+        while let Some(node) = pending.pop() {
+            for child in node.children() {
+                assert!(Rc::ptr_eq(&child.parent().unwrap(), &node));
+                pending.push(child);
+            }
+        }
+    }
+
+    #[test]
+    fn index_of_child_tells_equal_values_apart() {
+        let root = tree!(0 => [1, 1, 2]);
+        let children = root.children();
+        assert_eq!(Node::index_of_child(&root, &children[1]), Some(1));
+        assert_eq!(Node::index_of_child(&root, &Node::new(1)), None);
+        assert_eq!(Node::index_of_child(&children[0], &root), None);
+    }
+
+    #[test]
+    fn move_child_shifts_the_children_in_between() {
+        let root = tree!(0 => [1, 2, 3, 4]);
+        Node::move_child(&root, 0, 2);
+        assert_eq!(render(&root), "0(2 3 1 4)");
+        Node::move_child(&root, 3, 0);
+        assert_eq!(render(&root), "0(4 2 3 1)");
+        assert_parent_links(&root);
+    }
+
+    #[test]
+    fn moving_a_child_to_its_own_index_is_a_no_op() {
+        let root = tree!(0 => [1, 2, 3]);
+        let before = root.children();
+        Node::move_child(&root, 1, 1);
+        let after = root.children();
+        assert!(before.iter().zip(&after).all(|(a, b)| Rc::ptr_eq(a, b)));
+    }
+
+    #[test]
+    fn reorder_children_rejects_anything_but_a_permutation() {
+        let root = tree!(0 => [1, 2, 3]);
+        assert_eq!(
+            Node::reorder_children(&root, &[0, 1]),
+            Err(BadPermutation::WrongLength { expected: 3, found: 2 })
+        );
+        assert_eq!(Node::reorder_children(&root, &[0, 3, 1]), Err(BadPermutation::OutOfRange(3)));
+        assert_eq!(Node::reorder_children(&root, &[2, 0, 2]), Err(BadPermutation::Duplicate(2)));
+        assert_eq!(render(&root), "0(1 2 3)");
+
+        assert_eq!(Node::reorder_children(&root, &[2, 0, 1]), Ok(()));
+        assert_eq!(render(&root), "0(3 1 2)");
+        assert_parent_links(&root);
+    }
+
+    #[test]
+    fn sorting_each_level_matches_the_fixture() {
+        let root = tree!(5 => [9 => [3, 1, 2], 4, 7 => [8, 6]]);
+        for node in Node::find_all(&root, |_| true) {
+            Node::sort_children_by_value(&node);
+        }
+        assert_eq!(render(&root), "5(4 7(6 8) 9(1 2 3))");
+        assert_parent_links(&root);
+        assert_eq!(&*root.children()[2].children()[0].cached_path(), "5/9/1");
+    }
+
+    #[test]
+    fn sorting_keeps_equal_values_in_their_order() {
+        let root = tree!(0 => [2, 1, 2, 1]);
+        let before = root.children();
+        Node::sort_children_by_value(&root);
+        let after = root.children();
+        for (position, original) in [(0, 1), (1, 3), (2, 0), (3, 2)] {
+            assert!(Rc::ptr_eq(&after[position], &before[original]));
+        }
+    }
+
+    #[test]
+    fn find_first_returns_the_first_match_in_pre_order() {
+        let root = tree!(1 => [2 => [12], 11, 3 => [13]]);
+        let found = Node::find_first(&root, |node| node.value > 10).unwrap();
+        assert!(Rc::ptr_eq(&found, &root.children()[0].children()[0]));
+        assert!(Node::find_first(&root, |node| node.value == 5).is_none());
+        assert!(Rc::ptr_eq(&Node::find_first(&root, |_| true).unwrap(), &root));
+    }
+
+    #[test]
+    fn find_all_and_any_in_subtree() {
+        let root = tree!(1 => [2 => [4, 5], 3 => [6, 7]]);
+        let even: Vec<i32> = Node::find_all(&root, |node| node.value % 2 == 0)
+            .iter()
+            .map(|node| node.value)
+            .collect();
+        assert_eq!(even, [2, 4, 6]);
+        assert_eq!(Node::find_all(&root, |_| true).len(), 7);
+        assert!(Node::any_in_subtree(&root.children()[1], |node| node.value == 7));
+        assert!(!Node::any_in_subtree(&root.children()[1], |node| node.value == 4));
+    }
+
+    #[test]
+    fn a_predicate_can_read_other_nodes_while_searching() {
+        //Every node looks at its parent's children, which includes itself.
+        let root = tree!(1 => [2 => [3], 4]);
+        let found = Node::find_all(&root, |node| {
+            node.parent()
+                .is_some_and(|parent| parent.children().iter().any(|sibling| sibling.value == 4))
+        });
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn find_path_matches_walking_up_the_parents() {
+        let root = tree!(1 => [2 => [3 => [4]], 5]);
+        let path = Node::find_path(&root, |node| node.value == 4).unwrap();
+
+        let mut walked = vec![Node::find_first(&root, |node| node.value == 4).unwrap()];
+        while let Some(parent) = walked.last().unwrap().parent() {
+            walked.push(parent);
+        }
+        walked.reverse();
+
+        assert_eq!(path.len(), 4);
+        assert!(path.iter().zip(&walked).all(|(a, b)| Rc::ptr_eq(a, b)));
+        assert!(Node::find_path(&root, |node| node.value == 9).is_none());
+    }
+
+    #[test]
+    fn a_path_from_a_subtree_stops_at_that_subtree() {
+        let root = tree!(1 => [2 => [3]]);
+        let two = Rc::clone(&root.children()[0]);
+        let path: Vec<i32> = Node::find_path(&two, |node| node.value == 3)
+            .unwrap()
+            .iter()
+            .map(|node| node.value)
+            .collect();
+        assert_eq!(path, [2, 3]);
+    }
+
+    #[test]
+    fn searching_fifty_thousand_deep_stays_iterative() {
+        //The Vec holds the root first, so dropping it frees the chain one node at a time.
+        let nodes: Vec<_> = (0..50_000).map(Node::new).collect();
+        for pair in nodes.windows(2) {
+            Node::add_child(&pair[0], &pair[1]);
+        }
+        let last = Node::find_first(&nodes[0], |node| node.value == 49_999).unwrap();
+        assert!(Rc::ptr_eq(&last, &nodes[49_999]));
+        assert_eq!(Node::find_all(&nodes[0], |node| node.value % 1000 == 0).len(), 50);
+        assert_eq!(Node::find_path(&nodes[0], |node| node.value == 49_999).unwrap().len(), 50_000);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::rc::{Rc, Weak};
+
+//Returned by downgrade_limited() when the allocation already has as many Weak pointers as allowed.
+#[derive(Debug, PartialEq)]
+pub struct WeakLimitReached {
+    pub max_weak: usize,
+}
+
+//Like Rc::downgrade, except it refuses to create another Weak once Rc::weak_count has reached
+// `max_weak`. Observer lists that keep adding Weak pointers without ever cleaning them up show
+// up as an error here instead of growing forever.
+pub fn downgrade_limited<T>(rc: &Rc<T>, max_weak: usize) -> Result<Weak<T>, WeakLimitReached> {
+    if Rc::weak_count(rc) >= max_weak {
+        return Err(WeakLimitReached { max_weak });
+    }
+    Ok(Rc::downgrade(rc))
+}
+
+//Like Weak::upgrade, except a value that is already gone comes back as an error carrying `msg`.
+// That way the caller can use ? and the error still says what was being looked for.
+pub fn require_alive<T>(weak: &Weak<T>, msg: &str) -> Result<Rc<T>, String> {
+    weak.upgrade()
+        .ok_or_else(|| format!("{}: the value has already been dropped", msg))
+}
+
+//Drops every handle and returns how many values were actually freed, meaning how many handles were
+// the last strong owner of their value. Rc::into_inner only hands back the value when the strong
+// count was 1, and the value is dropped right away.
+pub fn drop_and_count<T>(handles: Vec<Rc<T>>) -> usize {
+    handles
+        .into_iter()
+        .filter_map(Rc::into_inner)
+        .count()
+}
+
+//How many different values the handles point at. Clones of the same Rc all share one allocation,
+// so they have the same Rc::as_ptr address and only count once.
+pub fn distinct_allocations<T>(items: &[Rc<T>]) -> usize {
+    items.iter().map(Rc::as_ptr).collect::<HashSet<_>>().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrading_stops_at_the_limit() {
+        let value = Rc::new(1);
+        let weaks: Vec<Weak<i32>> = (0..3).map(|_| downgrade_limited(&value, 3).unwrap()).collect();
+        assert_eq!(downgrade_limited(&value, 3).err(), Some(WeakLimitReached { max_weak: 3 }));
+        assert_eq!(Rc::weak_count(&value), 3);
+
+        //Dropping one makes room for exactly one more.
+        drop(weaks);
+        assert!(downgrade_limited(&value, 3).is_ok());
+    }
+
+    #[test]
+    fn a_limit_of_zero_never_downgrades() {
+        let value = Rc::new(1);
+        assert!(downgrade_limited(&value, 0).is_err());
+        assert_eq!(Rc::weak_count(&value), 0);
+    }
+
+    #[test]
+    fn weak_pointers_made_elsewhere_count_towards_the_limit() {
+        let value = Rc::new(1);
+        let _outside = Rc::downgrade(&value);
+        assert!(downgrade_limited(&value, 1).is_err());
+        let limited = downgrade_limited(&value, 2).unwrap();
+        assert_eq!(limited.upgrade().as_deref(), Some(&1));
+    }
+
+    #[test]
+    fn sole_owners_are_all_freed() {
+        let handles: Vec<Rc<i32>> = (0..4).map(Rc::new).collect();
+        assert_eq!(drop_and_count(handles), 4);
+        assert_eq!(drop_and_count(Vec::<Rc<i32>>::new()), 0);
+    }
+
+    #[test]
+    fn values_shared_outside_are_not_freed() {
+        let kept = Rc::new(1);
+        let handles = vec![Rc::clone(&kept), Rc::new(2), Rc::new(3)];
+        assert_eq!(drop_and_count(handles), 2);
+        assert_eq!(Rc::strong_count(&kept), 1);
+    }
+
+    #[test]
+    fn two_handles_to_one_value_free_it_once() {
+        let value = Rc::new(1);
+        let handles = vec![Rc::clone(&value), value];
+        assert_eq!(drop_and_count(handles), 1);
+    }
+
+    #[test]
+    fn require_alive_upgrades_a_live_value() {
+        let value = Rc::new(3);
+        let weak = Rc::downgrade(&value);
+        let upgraded = require_alive(&weak, "looking up the leaf").unwrap();
+        assert!(Rc::ptr_eq(&upgraded, &value));
+        assert_eq!(Rc::strong_count(&value), 2);
+    }
+
+    #[test]
+    fn require_alive_reports_a_dropped_value_with_the_message() {
+        let weak = Rc::downgrade(&Rc::new(3));
+        assert_eq!(
+            require_alive(&weak, "looking up the leaf"),
+            Err(String::from("looking up the leaf: the value has already been dropped"))
+        );
+        assert!(require_alive(&Weak::<i32>::new(), "never set").is_err());
+    }
+
+    #[test]
+    fn distinct_allocations_ignores_clones() {
+        let a = Rc::new(1);
+        let b = Rc::new(1);
+        let items = vec![Rc::clone(&a), Rc::clone(&b), Rc::clone(&a), Rc::clone(&a), b];
+        assert_eq!(distinct_allocations(&items), 2);
+        assert_eq!(distinct_allocations(&items[..1]), 1);
+        assert_eq!(distinct_allocations::<i32>(&[]), 0);
+    }
+
+    #[test]
+    fn equal_values_in_separate_allocations_are_all_counted() {
+        let items: Vec<Rc<&str>> = (0..3).map(|_| Rc::new("same")).collect();
+        assert_eq!(distinct_allocations(&items), 3);
+    }
+}
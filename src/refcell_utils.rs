@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+
+//Takes the contents of the cell, leaving T::default() behind, but only if `pred` holds for the
+// current contents. The check and the swap happen under one borrow_mut, so nothing can change the
+// value in between and there is no second borrow that could panic.
+pub fn take_if<T: Default>(cell: &RefCell<T>, pred: impl Fn(&T) -> bool) -> Option<T> {
+    let mut contents = cell.borrow_mut();
+    if pred(&contents) {
+        Some(std::mem::take(&mut *contents))
+    } else {
+        None
+    }
+}
+
+//Tries to borrow the cell mutably up to `attempts` times and runs `f` on the first success. Gives
+// up with None if the cell stayed borrowed for every attempt.
+//In single threaded code this is of limited use. Nothing else runs between two attempts, so a
+// borrow that is held on the first attempt is still held on the last one. It only helps when the
+// caller gets a chance to run something in between, see with_retry_yielding().
+pub fn with_retry<T, R>(cell: &RefCell<T>, attempts: usize, f: impl Fn(&mut T) -> R) -> Option<R> {
+    with_retry_yielding(cell, attempts, |_| {}, f)
+}
+
+//Same as with_retry(), except `yield_now` is called with the attempt number after every failed
+// attempt. This is where cooperative code can let whoever holds the borrow finish up.
+pub fn with_retry_yielding<T, R>(
+    cell: &RefCell<T>,
+    attempts: usize,
+    mut yield_now: impl FnMut(usize),
+    f: impl Fn(&mut T) -> R,
+) -> Option<R> {
+    for attempt in 1..=attempts {
+        if let Ok(mut contents) = cell.try_borrow_mut() {
+            return Some(f(&mut contents));
+        }
+        yield_now(attempt);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_if_takes_when_the_predicate_holds() {
+        let cell = RefCell::new(vec![1, 2, 3]);
+        assert_eq!(take_if(&cell, |items| items.len() == 3), Some(vec![1, 2, 3]));
+        assert!(cell.borrow().is_empty());
+    }
+
+    #[test]
+    fn take_if_leaves_the_cell_alone_otherwise() {
+        let cell = RefCell::new(String::from("keep"));
+        assert_eq!(take_if(&cell, |text| text.is_empty()), None);
+        assert_eq!(*cell.borrow(), "keep");
+    }
+
+    #[test]
+    fn take_if_does_not_leave_the_cell_borrowed() {
+        let cell = RefCell::new(5);
+        take_if(&cell, |_| false);
+        take_if(&cell, |&value| value == 5);
+        assert_eq!(*cell.borrow_mut(), 0);
+    }
+
+    #[test]
+    fn with_retry_runs_on_the_first_attempt_when_the_cell_is_free() {
+        let cell = RefCell::new(1);
+        assert_eq!(with_retry(&cell, 1, |value| *value + 1), Some(2));
+        assert_eq!(with_retry(&cell, 0, |value| *value), None);
+    }
+
+    #[test]
+    fn with_retry_gives_up_while_the_borrow_is_held() {
+        let cell = RefCell::new(1);
+        let held = cell.borrow();
+        assert_eq!(with_retry(&cell, 5, |value| *value), None);
+        drop(held);
+    }
+
+    #[test]
+    fn a_borrow_released_between_attempts_lets_a_later_attempt_succeed() {
+        let cell = RefCell::new(1);
+        let mut held = Some(cell.borrow_mut());
+        let mut failed = Vec::new();
+        let result = with_retry_yielding(
+            &cell,
+            5,
+            |attempt| {
+                failed.push(attempt);
+                if attempt == 2 {
+                    held.take();
+                }
+            },
+            |value| *value * 10,
+        );
+        assert_eq!(result, Some(10));
+        assert_eq!(failed, [1, 2]);
+    }
+}
@@ -0,0 +1,160 @@
+use std::rc::Rc;
+
+//A rope stores a long string as a tree of smaller pieces. Concatenating two ropes only allocates a
+// new Concat node instead of copying both strings, and the leaves are Rc<str> so the same piece of
+// text can be shared by several ropes. Lengths are counted in chars, and every Concat caches the
+// length of everything below it so lookups can skip whole subtrees.
+#[derive(Debug, Clone)]
+pub enum Rope {
+    Leaf(Rc<str>),
+    Concat(Box<Rope>, Box<Rope>, usize),
+}
+
+impl Rope {
+    pub fn leaf(text: &str) -> Rope {
+        Rope::Leaf(Rc::from(text))
+    }
+
+    pub fn concat(self, other: Rope) -> Rope {
+        let len = self.len() + other.len();
+        Rope::Concat(Box::new(self), Box::new(other), len)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Rope::Leaf(text) => text.chars().count(),
+            Rope::Concat(_, _, len) => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //Walks down from the root, going left when the index falls inside the left side's length and
+    // going right with the left length taken off otherwise.
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        let mut node = self;
+        let mut index = index;
+        loop {
+            match node {
+                Rope::Leaf(text) => return text.chars().nth(index),
+                Rope::Concat(left, right, _) => {
+                    let left_len = left.len();
+                    if index < left_len {
+                        node = left;
+                    } else {
+                        index -= left_len;
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    //The chars from `start` up to but not including `end`. An `end` past the end of the rope is
+    // cut down to the length, and a range that ends up empty gives an empty string. Subtrees that
+    // lie completely outside the range are skipped without being visited.
+    pub fn substring(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.len());
+        let mut result = String::new();
+        if start >= end {
+            return result;
+        }
+
+        //Each entry is a node together with the index of its first char within the whole rope.
+        let mut pending = vec![(self, 0)];
+        while let Some((node, offset)) = pending.pop() {
+            let node_end = offset + node.len();
+            if node_end <= start || offset >= end {
+                continue;
+            }
+
+            match node {
+                Rope::Leaf(text) => {
+                    let skip = start.saturating_sub(offset);
+                    let take = end.min(node_end) - offset - skip;
+                    result.extend(text.chars().skip(skip).take(take));
+                }
+                Rope::Concat(left, right, _) => {
+                    //Right first so the left side is popped and appended first.
+                    pending.push((right, offset + left.len()));
+                    pending.push((left, offset));
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hello_world() -> Rope {
+        Rope::leaf("Hel").concat(Rope::leaf("lo, ")).concat(Rope::leaf("wor").concat(Rope::leaf("ld")))
+    }
+
+    #[test]
+    fn len_adds_up_every_leaf() {
+        let rope = hello_world();
+        assert_eq!(rope.len(), "Hello, world".len());
+        assert!(!rope.is_empty());
+        assert!(Rope::leaf("").is_empty());
+    }
+
+    #[test]
+    fn char_at_works_across_concat_boundaries() {
+        let rope = hello_world();
+        let chars: Vec<char> = (0..rope.len()).map(|index| rope.char_at(index).unwrap()).collect();
+        assert_eq!(chars.into_iter().collect::<String>(), "Hello, world");
+        assert_eq!(rope.char_at(2), Some('l'));
+        assert_eq!(rope.char_at(3), Some('l'));
+        assert_eq!(rope.char_at(7), Some('w'));
+        assert_eq!(rope.char_at(12), None);
+    }
+
+    #[test]
+    fn lengths_count_chars_not_bytes() {
+        let rope = Rope::leaf("héllo").concat(Rope::leaf("→x"));
+        assert_eq!(rope.len(), 7);
+        assert_eq!(rope.char_at(1), Some('é'));
+        assert_eq!(rope.char_at(5), Some('→'));
+        assert_eq!(rope.char_at(6), Some('x'));
+    }
+
+    #[test]
+    fn clones_share_their_leaves() {
+        let piece = Rope::leaf("shared");
+        let rope = piece.clone().concat(piece.clone());
+        assert_eq!(rope.len(), 12);
+        match &piece {
+            Rope::Leaf(text) => assert_eq!(Rc::strong_count(text), 3),
+            Rope::Concat(..) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn substring_within_one_leaf() {
+        let rope = hello_world();
+        assert_eq!(rope.substring(0, 3), "Hel");
+        assert_eq!(rope.substring(8, 10), "or");
+    }
+
+    #[test]
+    fn substring_across_concat_boundaries() {
+        let rope = hello_world();
+        assert_eq!(rope.substring(2, 9), "llo, wo");
+        assert_eq!(rope.substring(0, rope.len()), "Hello, world");
+        assert_eq!(Rope::leaf("héllo").concat(Rope::leaf("→x")).substring(1, 6), "éllo→");
+    }
+
+    #[test]
+    fn empty_and_out_of_range_substrings() {
+        let rope = hello_world();
+        assert_eq!(rope.substring(4, 4), "");
+        assert_eq!(rope.substring(6, 2), "");
+        assert_eq!(rope.substring(7, 100), "world");
+        assert_eq!(rope.substring(50, 100), "");
+    }
+}
@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+//An iterator over data that is shared through an Rc<Vec<T>>. Cloning a SharedIter only clones
+// the Rc and copies the position, so every clone is an independent cursor over the same vec and
+// the vec itself is never copied.
+#[derive(Clone)]
+pub struct SharedIter<T: Clone> {
+    data: Rc<Vec<T>>,
+    position: usize,
+}
+
+impl<T: Clone> SharedIter<T> {
+    pub fn new(data: Vec<T>) -> SharedIter<T> {
+        SharedIter {
+            data: Rc::new(data),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn shares_data_with(&self, other: &SharedIter<T>) -> bool {
+        Rc::ptr_eq(&self.data, &other.data)
+    }
+
+    pub fn owners(&self) -> usize {
+        Rc::strong_count(&self.data)
+    }
+}
+
+impl<T: Clone> Iterator for SharedIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.data.get(self.position).cloned()?;
+        self.position += 1;
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_advance_independently_over_the_same_data() {
+        let mut first = SharedIter::new(vec![1, 2, 3]);
+        assert_eq!(first.next(), Some(1));
+        let mut second = first.clone();
+
+        assert_eq!(first.next(), Some(2));
+        assert_eq!(first.next(), Some(3));
+        assert_eq!(second.position(), 1);
+        assert_eq!(second.next(), Some(2));
+        assert!(first.shares_data_with(&second));
+        assert_eq!(first.owners(), 2);
+    }
+
+    #[test]
+    fn a_finished_cursor_stays_finished() {
+        let mut iter = SharedIter::new(vec!["only"]);
+        assert_eq!(iter.next(), Some("only"));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.position(), 1);
+    }
+
+    #[test]
+    fn separately_built_iters_do_not_share() {
+        let a = SharedIter::new(vec![1]);
+        let b = SharedIter::new(vec![1]);
+        assert!(!a.shares_data_with(&b));
+        let clones: Vec<SharedIter<i32>> = (0..3).map(|_| a.clone()).collect();
+        assert_eq!(a.owners(), 4);
+        drop(clones);
+        assert_eq!(a.owners(), 1);
+    }
+}
@@ -0,0 +1,204 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+//Why at() or at_mut() could not hand out a guard. Indexing a RefCell<Vec<T>> directly can panic for
+// two different reasons, a bad index or a conflicting borrow, and this keeps them apart.
+#[derive(Debug, PartialEq)]
+pub enum IndexOrBorrowError {
+    OutOfBounds { index: usize, len: usize },
+    BorrowConflict,
+}
+
+impl fmt::Display for IndexOrBorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexOrBorrowError::OutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for a length of {}", index, len)
+            }
+            IndexOrBorrowError::BorrowConflict => {
+                write!(f, "the vector is already borrowed in a conflicting way")
+            }
+        }
+    }
+}
+
+//A Vec shared between several owners. Cloning a SharedVec shares the same Vec, like the
+// Rc<RefCell<_>> values from the chapter.
+#[derive(Clone, Default)]
+pub struct SharedVec<T> {
+    items: Rc<RefCell<Vec<T>>>,
+}
+
+//Derefs to a single element while keeping the whole Vec borrowed. Ref::map narrows the Ref down to
+// the element, so the guard is only as big as a Ref.
+pub struct ElemGuard<'a, T>(Ref<'a, T>);
+
+pub struct ElemGuardMut<'a, T>(RefMut<'a, T>);
+
+impl<T> Deref for ElemGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for ElemGuardMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for ElemGuardMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> SharedVec<T> {
+    pub fn new(items: Vec<T>) -> SharedVec<T> {
+        SharedVec {
+            items: Rc::new(RefCell::new(items)),
+        }
+    }
+
+    //Any number of at() guards can be alive at the same time, just like Refs.
+    pub fn at(&self, index: usize) -> Result<ElemGuard<'_, T>, IndexOrBorrowError> {
+        let items = self
+            .items
+            .try_borrow()
+            .map_err(|_| IndexOrBorrowError::BorrowConflict)?;
+        check_index(index, items.len())?;
+        Ok(ElemGuard(Ref::map(items, |items| &items[index])))
+    }
+
+    //Fails with BorrowConflict instead of panicking while any other guard is still alive.
+    pub fn at_mut(&self, index: usize) -> Result<ElemGuardMut<'_, T>, IndexOrBorrowError> {
+        let items = self
+            .items
+            .try_borrow_mut()
+            .map_err(|_| IndexOrBorrowError::BorrowConflict)?;
+        check_index(index, items.len())?;
+        Ok(ElemGuardMut(RefMut::map(items, |items| &mut items[index])))
+    }
+
+    pub fn first(&self) -> Result<ElemGuard<'_, T>, IndexOrBorrowError> {
+        self.at(0)
+    }
+
+    pub fn last(&self) -> Result<ElemGuard<'_, T>, IndexOrBorrowError> {
+        let len = self.len()?;
+        self.at(len.checked_sub(1).ok_or(IndexOrBorrowError::OutOfBounds { index: 0, len })?)
+    }
+
+    //Two at_mut() guards can never be alive at once, so the swap takes one borrow of the whole Vec.
+    // Swapping an element with itself changes nothing.
+    pub fn swap(&self, a: usize, b: usize) -> Result<(), IndexOrBorrowError> {
+        let mut items = self
+            .items
+            .try_borrow_mut()
+            .map_err(|_| IndexOrBorrowError::BorrowConflict)?;
+        check_index(a, items.len())?;
+        check_index(b, items.len())?;
+        items.swap(a, b);
+        Ok(())
+    }
+
+    pub fn len(&self) -> Result<usize, IndexOrBorrowError> {
+        self.items
+            .try_borrow()
+            .map(|items| items.len())
+            .map_err(|_| IndexOrBorrowError::BorrowConflict)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, IndexOrBorrowError> {
+        self.len().map(|len| len == 0)
+    }
+}
+
+fn check_index(index: usize, len: usize) -> Result<(), IndexOrBorrowError> {
+    if index >= len {
+        return Err(IndexOrBorrowError::OutOfBounds { index, len });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents<T: Clone>(vec: &SharedVec<T>) -> Vec<T> {
+        vec.items.borrow().clone()
+    }
+
+    #[test]
+    fn shared_guards_can_coexist() {
+        let vec = SharedVec::new(vec![1, 2, 3]);
+        let first = vec.at(0).unwrap();
+        let other_handle = vec.clone();
+        let last = other_handle.last().unwrap();
+        assert_eq!((*first, *last), (1, 3));
+    }
+
+    #[test]
+    fn at_mut_while_a_guard_lives_is_a_conflict() {
+        let vec = SharedVec::new(vec![1, 2, 3]);
+        let guard = vec.at(1).unwrap();
+        assert_eq!(vec.at_mut(2).err(), Some(IndexOrBorrowError::BorrowConflict));
+        assert_eq!(vec.swap(0, 1), Err(IndexOrBorrowError::BorrowConflict));
+        drop(guard);
+
+        let mut_guard = vec.at_mut(2).unwrap();
+        assert_eq!(vec.at(0).err(), Some(IndexOrBorrowError::BorrowConflict));
+        assert_eq!(vec.len(), Err(IndexOrBorrowError::BorrowConflict));
+        drop(mut_guard);
+    }
+
+    #[test]
+    fn out_of_bounds_carries_the_index_and_length() {
+        let vec = SharedVec::new(vec!['a', 'b']);
+        assert_eq!(vec.at(2).err(), Some(IndexOrBorrowError::OutOfBounds { index: 2, len: 2 }));
+        assert_eq!(vec.at_mut(7).err(), Some(IndexOrBorrowError::OutOfBounds { index: 7, len: 2 }));
+        assert_eq!(vec.swap(0, 5), Err(IndexOrBorrowError::OutOfBounds { index: 5, len: 2 }));
+
+        let empty: SharedVec<char> = SharedVec::default();
+        assert_eq!(empty.first().err(), Some(IndexOrBorrowError::OutOfBounds { index: 0, len: 0 }));
+        assert_eq!(empty.last().err(), Some(IndexOrBorrowError::OutOfBounds { index: 0, len: 0 }));
+        assert_eq!(empty.is_empty(), Ok(true));
+    }
+
+    #[test]
+    fn swapping_an_element_with_itself_changes_nothing() {
+        let vec = SharedVec::new(vec![1, 2, 3]);
+        vec.swap(1, 1).unwrap();
+        assert_eq!(contents(&vec), [1, 2, 3]);
+        vec.swap(0, 2).unwrap();
+        assert_eq!(contents(&vec), [3, 2, 1]);
+    }
+
+    #[test]
+    fn a_guard_used_in_a_comparison_is_released_straight_away() {
+        let vec = SharedVec::new(vec![4, 1]);
+        if *vec.at(0).unwrap() > *vec.at(1).unwrap() {
+            vec.swap(0, 1).unwrap();
+        }
+        *vec.at_mut(1).unwrap() += 10;
+        assert_eq!(contents(&vec), [1, 14]);
+    }
+
+    #[test]
+    fn errors_have_readable_messages() {
+        assert_eq!(
+            IndexOrBorrowError::OutOfBounds { index: 3, len: 2 }.to_string(),
+            "index 3 is out of bounds for a length of 2"
+        );
+        assert_eq!(
+            IndexOrBorrowError::BorrowConflict.to_string(),
+            "the vector is already borrowed in a conflicting way"
+        );
+    }
+}
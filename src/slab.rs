@@ -0,0 +1,132 @@
+use std::rc::{Rc, Weak};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandleId(usize);
+
+//Hands out a stable id for every Rc put into it, so a specific node can be referred to even when
+// several nodes hold the same value. Only Weak pointers are stored, which means the slab never
+// keeps anything alive by itself. Ids are never reused, so an old id can only ever point at the
+// value it was given for, or at nothing.
+pub struct HandleSlab<T> {
+    entries: Vec<Option<Weak<T>>>,
+}
+
+impl<T> HandleSlab<T> {
+    pub fn new() -> HandleSlab<T> {
+        HandleSlab {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: &Rc<T>) -> HandleId {
+        self.entries.push(Some(Rc::downgrade(value)));
+        HandleId(self.entries.len() - 1)
+    }
+
+    //None if the id was removed or if the value has been dropped since it was inserted.
+    pub fn get(&self, id: HandleId) -> Option<Rc<T>> {
+        self.entries.get(id.0)?.as_ref()?.upgrade()
+    }
+
+    //Returns whether the id was still in the slab.
+    pub fn remove(&mut self, id: HandleId) -> bool {
+        match self.entries.get_mut(id.0) {
+            Some(entry) => entry.take().is_some(),
+            None => false,
+        }
+    }
+
+    pub fn find_by(&self, pred: impl Fn(&T) -> bool) -> Vec<HandleId> {
+        (0..self.entries.len())
+            .map(HandleId)
+            .filter(|&id| self.get(id).is_some_and(|value| pred(&value)))
+            .collect()
+    }
+
+    //Forgets every entry whose value has been dropped and returns their ids. The Weak pointers
+    // are released here, which is what finally frees the memory their Rc allocations kept around.
+    pub fn compact(&mut self) -> Vec<HandleId> {
+        let mut dead = Vec::new();
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            if entry.as_ref().is_some_and(|weak| weak.strong_count() == 0) {
+                *entry = None;
+                dead.push(HandleId(index));
+            }
+        }
+        dead
+    }
+}
+
+impl<T> Default for HandleSlab<T> {
+    fn default() -> Self {
+        HandleSlab::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc_tree::Node;
+
+    #[test]
+    fn ids_stay_valid_after_unrelated_removals() {
+        let values: Vec<Rc<i32>> = (0..4).map(Rc::new).collect();
+        let mut slab = HandleSlab::new();
+        let ids: Vec<HandleId> = values.iter().map(|value| slab.insert(value)).collect();
+
+        assert!(slab.remove(ids[1]));
+        assert!(!slab.remove(ids[1]));
+        assert!(slab.get(ids[1]).is_none());
+        for index in [0, 2, 3] {
+            assert!(Rc::ptr_eq(&slab.get(ids[index]).unwrap(), &values[index]));
+        }
+
+        //Ids are not reused, so the next insert gets a fresh one.
+        let next = slab.insert(&Rc::new(9));
+        assert!(!ids.contains(&next));
+    }
+
+    #[test]
+    fn dropped_values_read_as_none_and_are_compacted() {
+        let mut slab = HandleSlab::new();
+        let kept = Rc::new("kept");
+        let kept_id = slab.insert(&kept);
+        let dropped = Rc::new("dropped");
+        let dropped_id = slab.insert(&dropped);
+        assert_eq!(Rc::strong_count(&dropped), 1);
+
+        drop(dropped);
+        assert!(slab.get(dropped_id).is_none());
+        assert_eq!(slab.compact(), [dropped_id]);
+        assert!(slab.compact().is_empty());
+        assert_eq!(slab.get(kept_id).as_deref(), Some(&"kept"));
+    }
+
+    #[test]
+    fn find_by_returns_every_match() {
+        let values: Vec<Rc<i32>> = [2, 1, 2, 3, 2].into_iter().map(Rc::new).collect();
+        let mut slab = HandleSlab::default();
+        let ids: Vec<HandleId> = values.iter().map(|value| slab.insert(value)).collect();
+        slab.remove(ids[4]);
+
+        assert_eq!(slab.find_by(|&value| value == 2), [ids[0], ids[2]]);
+        assert!(slab.find_by(|&value| value > 5).is_empty());
+    }
+
+    #[test]
+    fn ids_point_at_the_intended_nodes() {
+        let root = tree!(1 => [2 => [3], 2 => [3]]);
+        let mut slab = HandleSlab::new();
+        let ids: Vec<HandleId> = Node::find_all(&root, |_| true)
+            .iter()
+            .map(|node| slab.insert(node))
+            .collect();
+
+        //The second 3 is found under the second 2, even though both 3s look the same.
+        let second_two = Rc::clone(&root.children()[1]);
+        let second_three = Rc::clone(&second_two.children()[0]);
+        assert!(Rc::ptr_eq(&slab.get(ids[3]).unwrap(), &second_two));
+        assert!(Rc::ptr_eq(&slab.get(ids[4]).unwrap(), &second_three));
+        assert_eq!(slab.find_by(|node| node.value == 3), [ids[2], ids[4]]);
+    }
+}
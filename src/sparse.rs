@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//A sparse matrix that only stores its non-zero entries. Like SharedBitSet, every clone is another
+// handle onto the same Rc<RefCell<HashMap<...>>>, so a value set through one handle can be read
+// through all of them.
+#[derive(Clone)]
+pub struct SparseMatrix {
+    entries: Rc<RefCell<HashMap<(usize, usize), f64>>>,
+}
+
+impl SparseMatrix {
+    pub fn new() -> SparseMatrix {
+        SparseMatrix {
+            entries: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    //Anything that was never set is zero.
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        self.entries
+            .borrow()
+            .get(&(row, column))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    //Setting an entry to zero removes it instead of storing the zero.
+    pub fn set(&self, row: usize, column: usize, value: f64) {
+        let mut entries = self.entries.borrow_mut();
+        if value == 0.0 {
+            entries.remove(&(row, column));
+        } else {
+            entries.insert((row, column), value);
+        }
+    }
+
+    //The number of non-zero entries.
+    pub fn nnz(&self) -> usize {
+        self.entries.borrow().len()
+    }
+}
+
+impl Default for SparseMatrix {
+    fn default() -> Self {
+        SparseMatrix::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_set_through_one_handle_are_read_through_another() {
+        let matrix = SparseMatrix::new();
+        let other = matrix.clone();
+        matrix.set(2, 3, 1.5);
+        assert_eq!(other.get(2, 3), 1.5);
+        assert_eq!(other.get(3, 2), 0.0);
+        assert_eq!(other.nnz(), 1);
+    }
+
+    #[test]
+    fn setting_zero_removes_the_entry() {
+        let matrix = SparseMatrix::new();
+        matrix.set(0, 0, 4.0);
+        matrix.set(1, 1, 5.0);
+        assert_eq!(matrix.nnz(), 2);
+
+        matrix.clone().set(0, 0, 0.0);
+        assert_eq!(matrix.nnz(), 1);
+        assert_eq!(matrix.get(0, 0), 0.0);
+
+        matrix.set(7, 7, 0.0);
+        assert_eq!(matrix.nnz(), 1);
+    }
+
+    #[test]
+    fn overwriting_an_entry_keeps_the_count() {
+        let matrix = SparseMatrix::default();
+        matrix.set(0, 1, 1.0);
+        matrix.set(0, 1, -2.0);
+        assert_eq!(matrix.get(0, 1), -2.0);
+        assert_eq!(matrix.nnz(), 1);
+    }
+}
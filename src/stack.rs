@@ -0,0 +1,115 @@
+//A stack where every element is stored in its own Box. The top of the stack is the head of the
+// chain, so pushing and popping never have to walk the list.
+pub struct Stack<T> {
+    head: Option<Box<StackNode<T>>>,
+}
+
+struct StackNode<T> {
+    value: T,
+    next: Option<Box<StackNode<T>>>,
+}
+
+impl<T> Stack<T> {
+    pub fn new() -> Stack<T> {
+        Stack { head: None }
+    }
+
+    pub fn push(&mut self, value: T) {
+        let next = self.head.take();
+        self.head = Some(Box::new(StackNode { value, next }));
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.head.take().map(|node| {
+            self.head = node.next;
+            node.value
+        })
+    }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Stack::new()
+    }
+}
+
+//Dropping the head would otherwise drop the next Box, which drops the next Box and so on. For a
+// large stack that recursion can overflow the stack, so the nodes are unlinked one at a time.
+impl<T> Drop for Stack<T> {
+    fn drop(&mut self) {
+        let mut current = self.head.take();
+        while let Some(mut node) = current {
+            current = node.next.take();
+        }
+    }
+}
+
+//Consuming the stack in a for loop pops the elements, so they come out top first.
+pub struct IntoIter<T>(Stack<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+}
+
+impl<T> IntoIterator for Stack<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack_of(values: impl IntoIterator<Item = i32>) -> Stack<i32> {
+        let mut stack = Stack::new();
+        for value in values {
+            stack.push(value);
+        }
+        stack
+    }
+
+    #[test]
+    fn draining_matches_successive_pops() {
+        let mut popped = stack_of(1..=5);
+        let by_pop: Vec<i32> = std::iter::from_fn(|| popped.pop()).collect();
+        let drained: Vec<i32> = stack_of(1..=5).into_iter().collect();
+        assert_eq!(drained, by_pop);
+        assert_eq!(drained, [5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn a_for_loop_consumes_the_stack_and_owns_the_values() {
+        let mut stack = Stack::new();
+        stack.push(String::from("bottom"));
+        stack.push(String::from("top"));
+        let mut seen = Vec::new();
+        for value in stack {
+            seen.push(value);
+        }
+        assert_eq!(seen, ["top", "bottom"]);
+    }
+
+    #[test]
+    fn an_empty_stack_pops_and_drains_nothing() {
+        let mut stack: Stack<i32> = Stack::default();
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.into_iter().next(), None);
+    }
+
+    #[test]
+    fn a_large_stack_drains_and_drops_without_overflowing() {
+        assert_eq!(stack_of(0..1_000_000).into_iter().count(), 1_000_000);
+        let mut partly_drained = stack_of(0..1_000_000).into_iter();
+        assert_eq!(partly_drained.next(), Some(999_999));
+        drop(partly_drained);
+        drop(stack_of(0..1_000_000));
+    }
+}
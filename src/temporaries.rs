@@ -0,0 +1,112 @@
+use crate::drop_log::{DropLog, Logged};
+
+//Each scenario builds its own DropLog and returns it, so the exact order the temporaries were
+// dropped in can be printed or checked afterwards.
+
+//Temporaries created while evaluating a statement live until the end of that statement, and are
+// then dropped in the reverse order they were created.
+pub fn chained_method_temporaries() -> DropLog {
+    let log = DropLog::new();
+    let joined = Logged::new(&log, "a").and(&Logged::new(&log, "b"));
+    log.record(format!("statement done:{}", joined));
+    log
+}
+
+//Taking a reference to a field of a temporary in a let binding extends the temporary's lifetime
+// to the end of the enclosing block. Copying the field out does not.
+pub fn let_binding_borrowing_a_temporary() -> DropLog {
+    let log = DropLog::new();
+    {
+        let copied = Logged::new(&log, "copied").name;
+        let borrowed = &Logged::new(&log, "borrowed").name;
+        log.record(format!("using:{}:{}", copied, borrowed));
+    }
+    log.record("block done");
+    log
+}
+
+//The scrutinee of an `if let` lives until the end of the whole if let expression in the 2021
+// edition, so the temporary is still alive inside the body.
+pub fn if_let_scrutinee() -> DropLog {
+    let log = DropLog::new();
+    if let Some(name) = Logged::new(&log, "scrutinee").peek() {
+        log.record(format!("body:{}", name));
+    }
+    log.record("if let done");
+    log
+}
+
+//Temporaries in an argument list are not dropped when the call starts or when it returns, they
+// are dropped at the end of the statement that contains the call.
+pub fn temporary_in_argument_list() -> DropLog {
+    fn record_call(log: &DropLog, first: &str, second: &Logged) -> usize {
+        log.record(format!("in call:{}:{}", first, second.name));
+        first.len()
+    }
+
+    let log = DropLog::new();
+    let length = record_call(&log, Logged::new(&log, "p").name, &Logged::new(&log, "q"));
+    log.record(format!("statement done:{}", length));
+    log
+}
+
+//With `let else` the temporaries of the initializer are dropped at the end of the let statement,
+// whether or not the pattern matched. That is why the borrowed &str has to be turned into an owned
+// value first. Binding `found` as a &str like the below does not compile.
+// let Some(found) = Logged::new(log, name).peek() else { return None; };
+// log.record(found); //error[E0716]: temporary value dropped while borrowed
+pub fn let_else() -> DropLog {
+    fn name_length(log: &DropLog, name: &'static str, wanted: &str) -> Option<usize> {
+        let Some(length) = Logged::new(log, name)
+            .peek()
+            .filter(|found| *found == wanted)
+            .map(str::len)
+        else {
+            log.record(format!("else:{}", name));
+            return None;
+        };
+        log.record(format!("matched:{}", length));
+        Some(length)
+    }
+
+    let log = DropLog::new();
+    name_length(&log, "hit", "hit");
+    name_length(&log, "miss", "other");
+    log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chained_temporaries_drop_in_reverse_at_the_end_of_the_statement() {
+        assert_eq!(chained_method_temporaries().events(), ["drop:b", "drop:a", "statement done:ab"]);
+    }
+
+    #[test]
+    fn only_the_borrowed_temporary_lives_to_the_end_of_the_block() {
+        assert_eq!(
+            let_binding_borrowing_a_temporary().events(),
+            ["drop:copied", "using:copied:borrowed", "drop:borrowed", "block done"]
+        );
+    }
+
+    #[test]
+    fn the_if_let_scrutinee_outlives_the_body() {
+        assert_eq!(if_let_scrutinee().events(), ["body:scrutinee", "drop:scrutinee", "if let done"]);
+    }
+
+    #[test]
+    fn argument_temporaries_outlive_the_call() {
+        assert_eq!(
+            temporary_in_argument_list().events(),
+            ["in call:p:q", "drop:q", "drop:p", "statement done:1"]
+        );
+    }
+
+    #[test]
+    fn let_else_drops_its_temporaries_before_either_branch() {
+        assert_eq!(let_else().events(), ["drop:hit", "matched:3", "drop:miss", "else:miss"]);
+    }
+}
@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::time::Instant;
+
+thread_local! {
+    //Every TimedDrop on this thread records into the same log.
+    static DROP_LOG: RefCell<Vec<(usize, Instant)>> = const { RefCell::new(Vec::new()) };
+}
+
+//Wraps a value so that the moment it is dropped is recorded together with its id. This is the
+// drop order demo from running_code_on_cleanup_with_the_drop_trait() with timing added, which helps
+// when working out the order a bigger structure is torn down in.
+pub struct TimedDrop<T> {
+    id: usize,
+    value: T,
+}
+
+impl<T> TimedDrop<T> {
+    pub fn new(id: usize, value: T) -> TimedDrop<T> {
+        TimedDrop { id, value }
+    }
+}
+
+impl<T> Deref for TimedDrop<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> Drop for TimedDrop<T> {
+    fn drop(&mut self) {
+        let now = Instant::now();
+        DROP_LOG.with(|log| log.borrow_mut().push((self.id, now)));
+    }
+}
+
+//Returns the recorded (id, Instant) pairs in the order the values were dropped.
+pub fn drop_log() -> Vec<(usize, Instant)> {
+    DROP_LOG.with(|log| log.borrow().clone())
+}
+
+pub fn clear_drop_log() {
+    DROP_LOG.with(|log| log.borrow_mut().clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Tests run on their own threads, so each one starts with an empty log of its own.
+    fn logged_ids() -> Vec<usize> {
+        drop_log().into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn the_log_follows_the_drop_order() {
+        let first = TimedDrop::new(1, "first");
+        let second = TimedDrop::new(2, "second");
+        {
+            let _third = TimedDrop::new(3, "third");
+        }
+        drop(first);
+        assert_eq!(*second, "second");
+        drop(second);
+        assert_eq!(logged_ids(), [3, 1, 2]);
+    }
+
+    #[test]
+    fn timestamps_never_go_backwards() {
+        let values: Vec<TimedDrop<usize>> = (0..5).map(|id| TimedDrop::new(id, id)).collect();
+        drop(values);
+        let log = drop_log();
+        assert_eq!(logged_ids(), [0, 1, 2, 3, 4]);
+        assert!(log.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+
+    #[test]
+    fn clearing_the_log_forgets_earlier_drops() {
+        drop(TimedDrop::new(7, ()));
+        clear_drop_log();
+        assert!(drop_log().is_empty());
+        drop(TimedDrop::new(8, ()));
+        assert_eq!(logged_ids(), [8]);
+    }
+}
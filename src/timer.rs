@@ -0,0 +1,152 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+//Each entry records the tick it is due on next to the callback itself.
+type Slot = Vec<(usize, Rc<dyn Fn()>)>;
+
+//A hashed timer wheel. Every callback is placed in the slot matching the tick it is due on, so a
+// tick only has to look through a single slot. The callbacks are stored as Rc<dyn Fn()> so the
+// same callback can be scheduled more than once without being copied.
+pub struct TimerWheel {
+    slots: RefCell<Vec<Slot>>,
+    current_tick: Cell<usize>,
+    //The wheel keeps a Weak pointer to itself. It is handed out to callbacks so they can schedule
+    // more work without creating a reference cycle between the wheel and its callbacks.
+    self_ref: Weak<TimerWheel>,
+}
+
+impl TimerWheel {
+    pub fn new(slot_count: usize) -> Rc<TimerWheel> {
+        assert!(slot_count > 0, "a timer wheel needs at least one slot");
+        Rc::new_cyclic(|self_ref| TimerWheel {
+            slots: RefCell::new((0..slot_count).map(|_| Vec::new()).collect()),
+            current_tick: Cell::new(0),
+            self_ref: self_ref.clone(),
+        })
+    }
+
+    pub fn handle(&self) -> Weak<TimerWheel> {
+        self.self_ref.clone()
+    }
+
+    pub fn current_tick(&self) -> usize {
+        self.current_tick.get()
+    }
+
+    //Schedules the callback to fire `delay` ticks from now. A delay of 0 is treated as 1 because
+    // the current tick has already been processed.
+    pub fn schedule(&self, delay: usize, callback: Rc<dyn Fn()>) {
+        let due = self.current_tick.get() + delay.max(1);
+        let mut slots = self.slots.borrow_mut();
+        let slot_count = slots.len();
+        slots[due % slot_count].push((due, callback));
+    }
+
+    //Advances the wheel by one tick and fires everything that is due, returning how many callbacks
+    // ran. The due callbacks are removed from the slot before any of them run so that a callback
+    // is free to call schedule() without hitting an already borrowed RefCell.
+    pub fn tick(&self) -> usize {
+        let now = self.current_tick.get() + 1;
+        self.current_tick.set(now);
+
+        let due: Vec<Rc<dyn Fn()>> = {
+            let mut slots = self.slots.borrow_mut();
+            let slot_count = slots.len();
+            let slot = &mut slots[now % slot_count];
+            let (due, waiting): (Vec<_>, Vec<_>) = slot.drain(..).partition(|(at, _)| *at == now);
+            *slot = waiting;
+            due.into_iter().map(|(_, callback)| callback).collect()
+        };
+
+        for callback in &due {
+            callback();
+        }
+
+        due.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter() -> (Rc<Cell<usize>>, Rc<dyn Fn()>) {
+        let fired = Rc::new(Cell::new(0));
+        let handle = Rc::clone(&fired);
+        (fired, Rc::new(move || handle.set(handle.get() + 1)))
+    }
+
+    #[test]
+    fn a_callback_fires_exactly_on_its_tick() {
+        let wheel = TimerWheel::new(8);
+        let (fired, callback) = counter();
+        wheel.schedule(3, callback);
+
+        assert_eq!(wheel.tick(), 0);
+        assert_eq!(wheel.tick(), 0);
+        assert_eq!(fired.get(), 0);
+        assert_eq!(wheel.tick(), 1);
+        assert_eq!(fired.get(), 1);
+        assert_eq!(wheel.current_tick(), 3);
+        for _ in 0..10 {
+            wheel.tick();
+        }
+        assert_eq!(fired.get(), 1);
+    }
+
+    #[test]
+    fn a_delay_longer_than_the_wheel_waits_for_the_right_lap() {
+        let wheel = TimerWheel::new(4);
+        let (fired, callback) = counter();
+        wheel.schedule(6, callback);
+        for _ in 0..5 {
+            wheel.tick();
+        }
+        assert_eq!(fired.get(), 0);
+        wheel.tick();
+        assert_eq!(fired.get(), 1);
+    }
+
+    #[test]
+    fn a_zero_delay_fires_on_the_next_tick() {
+        let wheel = TimerWheel::new(2);
+        let (fired, callback) = counter();
+        wheel.schedule(0, callback);
+        assert_eq!(wheel.tick(), 1);
+        assert_eq!(fired.get(), 1);
+    }
+
+    //Each run schedules a fresh closure through the Weak handle, until it has fired three times.
+    fn schedule_repeating(wheel: &Weak<TimerWheel>, fired: Rc<Cell<usize>>) {
+        let handle = wheel.clone();
+        let callback: Rc<dyn Fn()> = Rc::new(move || {
+            fired.set(fired.get() + 1);
+            if fired.get() < 3 {
+                schedule_repeating(&handle, Rc::clone(&fired));
+            }
+        });
+        if let Some(wheel) = wheel.upgrade() {
+            wheel.schedule(2, callback);
+        }
+    }
+
+    #[test]
+    fn a_callback_can_reschedule_itself_through_the_weak_handle() {
+        let wheel = TimerWheel::new(4);
+        let fired = Rc::new(Cell::new(0));
+        schedule_repeating(&wheel.handle(), Rc::clone(&fired));
+
+        let fired_on: Vec<usize> = (1..=10).filter(|_| wheel.tick() > 0).collect();
+        assert_eq!(fired_on, [2, 4, 6]);
+        assert_eq!(fired.get(), 3);
+    }
+
+    #[test]
+    fn the_handle_does_not_keep_the_wheel_alive() {
+        let wheel = TimerWheel::new(1);
+        let handle = wheel.handle();
+        assert_eq!(Rc::strong_count(&wheel), 1);
+        drop(wheel);
+        assert!(handle.upgrade().is_none());
+    }
+}
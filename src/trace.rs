@@ -0,0 +1,298 @@
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use crate::logger::{Level, Logger};
+
+//The demos that can be re-run with `--trace <name>`.
+pub const TRACEABLE_DEMOS: [&str; 2] = ["rc", "refcell"];
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownDemo(pub String);
+
+impl fmt::Display for UnknownDemo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown demo '{}', expected one of: {}", self.0, TRACEABLE_DEMOS.join(", "))
+    }
+}
+
+//Numbers every traced operation and sends it to a Logger, so a whole trace can be read back with
+// Logger::messages() and compared in a test. Clones share the same sequence.
+#[derive(Clone)]
+pub struct TraceSession {
+    logger: Logger,
+    sequence: Rc<Cell<usize>>,
+}
+
+impl TraceSession {
+    pub fn new(logger: &Logger) -> TraceSession {
+        TraceSession {
+            logger: logger.clone(),
+            sequence: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn emit(&self, operation: &str, label: &str, detail: String) {
+        let number = self.sequence.get() + 1;
+        self.sequence.set(number);
+        self.logger.log(Level::Debug, &format!("#{} {} {} {}", number, operation, label, detail));
+    }
+}
+
+//An Rc that reports what happens to it. Rust has no way to give a clone its own name, so every
+// handle is labelled at the call site, including the ones made by clone_as().
+pub struct TrackedRc<T> {
+    rc: Rc<T>,
+    label: &'static str,
+    session: TraceSession,
+}
+
+impl<T> TrackedRc<T> {
+    pub fn new(session: &TraceSession, label: &'static str, value: T) -> TrackedRc<T> {
+        let rc = Rc::new(value);
+        session.emit("new", label, format!("strong={}", Rc::strong_count(&rc)));
+        TrackedRc {
+            rc,
+            label,
+            session: session.clone(),
+        }
+    }
+
+    pub fn clone_as(&self, label: &'static str) -> TrackedRc<T> {
+        let rc = Rc::clone(&self.rc);
+        self.session.emit("clone", label, format!("strong={}", Rc::strong_count(&rc)));
+        TrackedRc {
+            rc,
+            label,
+            session: self.session.clone(),
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        let count = Rc::strong_count(&self.rc);
+        self.session.emit("read", self.label, format!("strong={}", count));
+        count
+    }
+}
+
+impl<T> Deref for TrackedRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.rc
+    }
+}
+
+//The count is read before the Rc field goes away, so the trace shows what it will be afterwards.
+impl<T> Drop for TrackedRc<T> {
+    fn drop(&mut self) {
+        let after = Rc::strong_count(&self.rc) - 1;
+        self.session.emit("drop", self.label, format!("strong={}", after));
+    }
+}
+
+//A RefCell that reports every borrow and the matching release. The guards it hands out log the
+// release from their own Drop, which is the only point where a RefCell borrow actually ends.
+pub struct TrackedRefCell<T> {
+    cell: RefCell<T>,
+    label: &'static str,
+    session: TraceSession,
+}
+
+pub struct TracedRef<'a, T> {
+    inner: Ref<'a, T>,
+    label: &'static str,
+    session: &'a TraceSession,
+}
+
+pub struct TracedRefMut<'a, T> {
+    inner: RefMut<'a, T>,
+    label: &'static str,
+    session: &'a TraceSession,
+}
+
+impl<T> TrackedRefCell<T> {
+    pub fn new(session: &TraceSession, label: &'static str, value: T) -> TrackedRefCell<T> {
+        TrackedRefCell {
+            cell: RefCell::new(value),
+            label,
+            session: session.clone(),
+        }
+    }
+
+    pub fn borrow(&self) -> TracedRef<'_, T> {
+        let inner = self.cell.borrow();
+        self.session.emit("borrow", self.label, String::from("shared"));
+        TracedRef {
+            inner,
+            label: self.label,
+            session: &self.session,
+        }
+    }
+
+    pub fn borrow_mut(&self) -> TracedRefMut<'_, T> {
+        let inner = self.cell.borrow_mut();
+        self.session.emit("borrow", self.label, String::from("mut"));
+        TracedRefMut {
+            inner,
+            label: self.label,
+            session: &self.session,
+        }
+    }
+}
+
+impl<T> Deref for TracedRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Drop for TracedRef<'_, T> {
+    fn drop(&mut self) {
+        self.session.emit("release", self.label, String::from("shared"));
+    }
+}
+
+impl<T> Deref for TracedRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for TracedRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T> Drop for TracedRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.session.emit("release", self.label, String::from("mut"));
+    }
+}
+
+//The Rc demo from main.rs with every handle tracked: a, then b and c cloned from it, a read of the
+// count while all three are alive, and the drops in reverse order.
+pub fn rc_demo(session: &TraceSession) {
+    let a = TrackedRc::new(session, "a", String::from("String value"));
+    let b = a.clone_as("b");
+    {
+        let c = a.clone_as("c");
+        a.strong_count();
+        drop(c);
+    }
+    drop(b);
+    drop(a);
+}
+
+//The RefCell demo from main.rs: two pops through mutable borrows, each followed by a shared borrow
+// to look at the result.
+pub fn refcell_demo(session: &TraceSession) {
+    let item = TrackedRefCell::new(session, "item", String::from("Hello world"));
+    for _ in 0..2 {
+        item.borrow_mut().pop();
+        let _ = item.borrow().len();
+    }
+}
+
+//Runs the named demo with tracing and returns the trace lines it logged.
+pub fn run_traced(demo: &str) -> Result<Vec<String>, UnknownDemo> {
+    let logger = Logger::new(Level::Debug);
+    let session = TraceSession::new(&logger);
+    match demo {
+        "rc" => rc_demo(&session),
+        "refcell" => refcell_demo(&session),
+        _ => return Err(UnknownDemo(demo.to_string())),
+    }
+    Ok(logger.messages())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Strips the "[Debug] #n " prefix so a test can look at just the operations.
+    fn operations(trace: &[String]) -> Vec<String> {
+        trace
+            .iter()
+            .map(|line| line.split(' ').skip(2).collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+
+    #[test]
+    fn the_rc_trace_has_the_expected_operations_in_order() {
+        let trace = run_traced("rc").unwrap();
+        assert_eq!(
+            trace,
+            [
+                "[Debug] #1 new a strong=1",
+                "[Debug] #2 clone b strong=2",
+                "[Debug] #3 clone c strong=3",
+                "[Debug] #4 read a strong=3",
+                "[Debug] #5 drop c strong=2",
+                "[Debug] #6 drop b strong=1",
+                "[Debug] #7 drop a strong=0",
+            ]
+        );
+    }
+
+    #[test]
+    fn every_rc_trace_line_names_its_variable() {
+        let trace = run_traced("rc").unwrap();
+        let labels: Vec<String> = operations(&trace)
+            .iter()
+            .map(|operation| operation.split(' ').nth(1).unwrap().to_string())
+            .collect();
+        assert_eq!(labels, ["a", "b", "c", "a", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn the_refcell_trace_releases_every_borrow_it_takes() {
+        let trace = operations(&run_traced("refcell").unwrap());
+        assert_eq!(
+            trace,
+            [
+                "borrow item mut",
+                "release item mut",
+                "borrow item shared",
+                "release item shared",
+                "borrow item mut",
+                "release item mut",
+                "borrow item shared",
+                "release item shared",
+            ]
+        );
+
+        let mut open = 0;
+        for line in &trace {
+            if line.starts_with("borrow") {
+                open += 1;
+            } else {
+                open -= 1;
+            }
+            assert!(open == 0 || open == 1, "{:?}", trace);
+        }
+        assert_eq!(open, 0);
+    }
+
+    #[test]
+    fn tracing_an_unknown_demo_lists_the_valid_ones() {
+        let error = run_traced("box").unwrap_err();
+        assert_eq!(error, UnknownDemo(String::from("box")));
+        assert_eq!(error.to_string(), "unknown demo 'box', expected one of: rc, refcell");
+    }
+
+    #[test]
+    fn two_sessions_number_their_operations_separately() {
+        let first = Logger::new(Level::Debug);
+        let second = Logger::new(Level::Debug);
+        rc_demo(&TraceSession::new(&first));
+        rc_demo(&TraceSession::new(&second));
+        assert_eq!(first.messages(), second.messages());
+    }
+}
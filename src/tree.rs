@@ -0,0 +1,528 @@
+use std::rc::Rc;
+
+use crate::list::List;
+use crate::rc_tree;
+
+//A binary search tree where each child is an Option<Box<Node<T>>>. This is the same trick as the
+// Hello struct in using_box_to_point_to_data_on_the_heap(), the Option ends the recursion and the
+// Box gives the node a known size.
+#[derive(Debug)]
+pub struct Node<T> {
+    pub value: T,
+    pub left: Option<Box<Node<T>>>,
+    pub right: Option<Box<Node<T>>>,
+}
+
+impl<T> Node<T> {
+    pub fn new(value: T) -> Node<T> {
+        Node {
+            value,
+            left: None,
+            right: None,
+        }
+    }
+
+    //A leaf has no children at all.
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_and_internal_counts().0
+    }
+
+    //An internal node has at least one child.
+    pub fn internal_count(&self) -> usize {
+        self.leaf_and_internal_counts().1
+    }
+
+    //Both counts are worked out in the same pass over the tree.
+    fn leaf_and_internal_counts(&self) -> (usize, usize) {
+        let children = [&self.left, &self.right];
+        let mut counts = (0, 0);
+        let mut has_children = false;
+
+        for child in children.into_iter().flatten() {
+            has_children = true;
+            let (leaves, internal) = child.leaf_and_internal_counts();
+            counts.0 += leaves;
+            counts.1 += internal;
+        }
+
+        if has_children {
+            counts.1 += 1;
+        } else {
+            counts.0 += 1;
+        }
+        counts
+    }
+}
+
+impl<T> Node<T> {
+    //The number of nodes on the longest path from this node down to a leaf.
+    pub fn height(&self) -> usize {
+        self.height_and_diameter().0
+    }
+
+    //A tree is balanced when, at every node, the heights of the two subtrees differ by at most one.
+    pub fn is_balanced(&self) -> bool {
+        self.balanced_height().is_some()
+    }
+
+    //Returns the height if the subtree is balanced and None as soon as any part of it is not.
+    fn balanced_height(&self) -> Option<usize> {
+        let left = match &self.left {
+            Some(left) => left.balanced_height()?,
+            None => 0,
+        };
+        let right = match &self.right {
+            Some(right) => right.balanced_height()?,
+            None => 0,
+        };
+
+        if left.abs_diff(right) > 1 {
+            return None;
+        }
+        Some(1 + left.max(right))
+    }
+
+    //The values in sorted order, left subtree first, then the node, then the right subtree.
+    pub fn in_order(&self) -> Vec<&T> {
+        let mut values = Vec::new();
+        let mut pending: Vec<&Node<T>> = Vec::new();
+        let mut current = Some(self);
+
+        while current.is_some() || !pending.is_empty() {
+            while let Some(node) = current {
+                pending.push(node);
+                current = node.left.as_deref();
+            }
+            if let Some(node) = pending.pop() {
+                values.push(&node.value);
+                current = node.right.as_deref();
+            }
+        }
+
+        values
+    }
+
+    //The values exactly `depth` levels below this node, left to right, where this node itself is
+    // depth 0. Goes down one whole level at a time and stops early if the tree runs out of levels.
+    pub fn nodes_at_depth(&self, depth: usize) -> Vec<&T> {
+        let mut level: Vec<&Node<T>> = vec![self];
+        for _ in 0..depth {
+            level = level
+                .into_iter()
+                .flat_map(|node| [node.left.as_deref(), node.right.as_deref()])
+                .flatten()
+                .collect();
+            if level.is_empty() {
+                break;
+            }
+        }
+        level.into_iter().map(|node| &node.value).collect()
+    }
+
+    //The number of edges on the longest path between any two nodes. The path does not have to go
+    // through the root.
+    pub fn diameter(&self) -> usize {
+        self.height_and_diameter().1
+    }
+
+    //Returns the height of this subtree, counted in nodes, and the best diameter found inside it.
+    // The longest path through this node joins the deepest paths of its two subtrees, which is
+    // exactly the sum of their heights.
+    fn height_and_diameter(&self) -> (usize, usize) {
+        let (left_height, left_diameter) = self
+            .left
+            .as_ref()
+            .map_or((0, 0), |left| left.height_and_diameter());
+        let (right_height, right_diameter) = self
+            .right
+            .as_ref()
+            .map_or((0, 0), |right| right.height_and_diameter());
+
+        let through_here = left_height + right_height;
+        let diameter = through_here.max(left_diameter).max(right_diameter);
+        (1 + left_height.max(right_height), diameter)
+    }
+}
+
+impl<T: Clone + PartialEq> Node<T> {
+    //Builds a balanced tree out of values that are already sorted. The middle value becomes the
+    // root and each half becomes one of its subtrees, so the height is as small as it can be.
+    //insert() puts equal values on the right, so when the middle value appears more than once the
+    // first copy becomes the root and the rest go right with it. Lots of duplicates can leave the
+    // tree less balanced, but it stays a valid search tree.
+    pub fn from_sorted(values: &[T]) -> Option<Box<Node<T>>> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut middle = values.len() / 2;
+        while middle > 0 && values[middle - 1] == values[middle] {
+            middle -= 1;
+        }
+        Some(Box::new(Node {
+            value: values[middle].clone(),
+            left: Node::from_sorted(&values[..middle]),
+            right: Node::from_sorted(&values[middle + 1..]),
+        }))
+    }
+}
+
+//Connects the two recursive types, the elements of a cons list end up in a balanced search tree.
+pub fn list_to_balanced_tree<T: Clone + Ord>(list: &List<T>) -> Option<Box<Node<T>>> {
+    let mut values: Vec<T> = list.iter().cloned().collect();
+    values.sort();
+    Node::from_sorted(&values)
+}
+
+impl<T: Ord> Node<T> {
+    //Checks the search tree invariant for the whole tree. Comparing a node with its direct children
+    // is not enough, every value has to fit within the bounds set by all of its ancestors. Going
+    // left sets an exclusive upper bound and going right sets an inclusive lower bound, matching
+    // where insert() puts equal values.
+    pub fn is_bst(&self) -> bool {
+        self.within_bounds(None, None)
+    }
+
+    fn within_bounds(&self, lower: Option<&T>, upper: Option<&T>) -> bool {
+        if lower.is_some_and(|lower| self.value < *lower) {
+            return false;
+        }
+        if upper.is_some_and(|upper| self.value >= *upper) {
+            return false;
+        }
+
+        let left_ok = self
+            .left
+            .as_ref()
+            .is_none_or(|left| left.within_bounds(lower, Some(&self.value)));
+        let right_ok = self
+            .right
+            .as_ref()
+            .is_none_or(|right| right.within_bounds(Some(&self.value), upper));
+        left_ok && right_ok
+    }
+
+    //The subtree whose root holds `value`, found by following the same left and right choices that
+    // insert() makes. The returned reference borrows from self, so the tree cannot be changed
+    // while the subtree is in use.
+    pub fn subtree_containing(&self, value: &T) -> Option<&Node<T>> {
+        let mut current = Some(self);
+        while let Some(node) = current {
+            if *value == node.value {
+                return Some(node);
+            }
+            current = if *value < node.value {
+                node.left.as_deref()
+            } else {
+                node.right.as_deref()
+            };
+        }
+        None
+    }
+
+    //Values smaller than a node go to the left, everything else goes to the right. The insert
+    // walks down the tree in a loop rather than recursing.
+    pub fn insert(&mut self, value: T) {
+        let mut current = self;
+        loop {
+            let next = if value < current.value {
+                &mut current.left
+            } else {
+                &mut current.right
+            };
+
+            match next {
+                Some(child) => current = child,
+                None => {
+                    *next = Some(Box::new(Node::new(value)));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+//Copies the Box tree into a brand new rc_tree::Node tree. Nothing is shared with the original, each
+// node gets its own fresh Rc owned only by its parent, plus a Weak link back up to that parent.
+// An rc_tree node has a list of children instead of a left and a right slot, so the left child
+// comes first and a missing child is simply left out.
+pub fn box_tree_to_rc<T: Clone>(root: &Node<T>) -> Rc<rc_tree::Node<T>> {
+    let rc_root = rc_tree::Node::new(root.value.clone());
+    let mut pending = vec![(root, Rc::clone(&rc_root))];
+
+    while let Some((node, rc_node)) = pending.pop() {
+        for child in [&node.left, &node.right].into_iter().flatten() {
+            let rc_child = rc_tree::Node::new(child.value.clone());
+            rc_tree::Node::add_child(&rc_node, &rc_child);
+            pending.push((child, rc_child));
+        }
+    }
+
+    rc_root
+}
+
+//Returned by BoundedTree::insert() when the new node would end up deeper than the tree allows.
+#[derive(Debug, PartialEq)]
+pub struct DepthLimitReached {
+    pub max_depth: usize,
+}
+
+//A search tree that refuses to grow past `max_depth` levels. The recursive methods on Node, like
+// height() and the drop of the Boxes themselves, use one stack frame per level, so inserting
+// values in sorted order can otherwise build a tree deep enough to overflow the stack.
+pub struct BoundedTree<T: Ord> {
+    root: Option<Box<Node<T>>>,
+    max_depth: usize,
+    height: usize,
+}
+
+impl<T: Ord> BoundedTree<T> {
+    pub fn new(max_depth: usize) -> BoundedTree<T> {
+        BoundedTree {
+            root: None,
+            max_depth,
+            height: 0,
+        }
+    }
+
+    //The depth is counted in nodes, the same as height(), so a tree with only a root has depth 1.
+    // Nothing is changed when the insert is rejected.
+    pub fn insert(&mut self, value: T) -> Result<(), DepthLimitReached> {
+        let depth = self.depth_of_new_node(&value);
+        if depth > self.max_depth {
+            return Err(DepthLimitReached {
+                max_depth: self.max_depth,
+            });
+        }
+
+        match &mut self.root {
+            Some(root) => root.insert(value),
+            None => self.root = Some(Box::new(Node::new(value))),
+        }
+        self.height = self.height.max(depth);
+        Ok(())
+    }
+
+    //Follows the same path insert() would take, without changing anything.
+    fn depth_of_new_node(&self, value: &T) -> usize {
+        let mut depth = 1;
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            depth += 1;
+            current = if *value < node.value {
+                node.left.as_deref()
+            } else {
+                node.right.as_deref()
+            };
+        }
+        depth
+    }
+
+    //Tracked on every insert, so it does not need to walk the tree.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn root(&self) -> Option<&Node<T>> {
+        self.root.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn built_by_insert(values: &[i32]) -> Node<i32> {
+        let mut root = Node::new(values[0]);
+        for &value in &values[1..] {
+            root.insert(value);
+        }
+        root
+    }
+
+    #[test]
+    fn trees_built_by_insert_and_from_sorted_are_bsts() {
+        assert!(built_by_insert(&[5, 3, 8, 1, 4, 7, 9, 5, 3]).is_bst());
+        assert!(Node::from_sorted(&[1, 2, 3, 4, 5, 6, 7]).unwrap().is_bst());
+        assert!(Node::new(1).is_bst());
+    }
+
+    #[test]
+    fn a_value_outside_an_ancestors_bound_is_caught() {
+        //4 is fine next to its parent 3, but it sits in the right subtree of 5.
+        let mut root = built_by_insert(&[5, 8, 3]);
+        root.right.as_mut().unwrap().left = Some(Box::new(Node::new(4)));
+        assert!(!root.is_bst());
+    }
+
+    #[test]
+    fn equal_values_belong_on_the_right() {
+        let mut root = Node::new(5);
+        root.right = Some(Box::new(Node::new(5)));
+        assert!(root.is_bst());
+        root.left = root.right.take();
+        assert!(!root.is_bst());
+    }
+
+    #[test]
+    fn a_list_becomes_a_balanced_tree_with_every_element() {
+        let list = list![5, 1, 4, 8, 3, 9, 2];
+        let tree = list_to_balanced_tree(&list).unwrap();
+        assert_eq!(tree.in_order(), [&1, &2, &3, &4, &5, &8, &9]);
+        assert!(tree.is_balanced());
+        assert!(tree.is_bst());
+        assert_eq!(tree.height(), 3);
+    }
+
+    #[test]
+    fn duplicates_in_the_list_stay_on_the_right() {
+        let tree = list_to_balanced_tree(&list![5, 1, 4, 1, 3, 9, 1]).unwrap();
+        assert_eq!(tree.in_order(), [&1, &1, &1, &3, &4, &5, &9]);
+        assert!(tree.is_bst());
+        assert!(Node::from_sorted(&[2, 2, 2]).unwrap().is_bst());
+    }
+
+    #[test]
+    fn an_empty_list_gives_no_tree() {
+        assert!(list_to_balanced_tree::<i32>(&list![]).is_none());
+        assert_eq!(list_to_balanced_tree(&list![7]).unwrap().height(), 1);
+    }
+
+    #[test]
+    fn bounded_tree_accepts_inserts_within_the_limit() {
+        let mut tree = BoundedTree::new(3);
+        for value in [4, 2, 6, 1, 3, 5, 7] {
+            assert_eq!(tree.insert(value), Ok(()));
+        }
+        assert_eq!(tree.height(), 3);
+        assert_eq!(tree.root().unwrap().height(), 3);
+    }
+
+    #[test]
+    fn an_ascending_sequence_hits_the_limit() {
+        let mut tree = BoundedTree::new(4);
+        for value in 0..4 {
+            tree.insert(value).unwrap();
+        }
+        assert_eq!(tree.insert(4), Err(DepthLimitReached { max_depth: 4 }));
+        //The rejected value is not in the tree, and a value that fits higher up is still allowed.
+        assert_eq!(tree.root().unwrap().in_order(), [&0, &1, &2, &3]);
+        assert_eq!(tree.height(), 4);
+        assert_eq!(tree.insert(-1), Ok(()));
+    }
+
+    #[test]
+    fn a_zero_depth_tree_takes_nothing() {
+        let mut tree = BoundedTree::new(0);
+        assert_eq!(tree.insert(1), Err(DepthLimitReached { max_depth: 0 }));
+        assert!(tree.root().is_none());
+        assert_eq!(tree.height(), 0);
+    }
+
+    #[test]
+    fn box_tree_to_rc_copies_every_value_in_place() {
+        let box_root = built_by_insert(&[4, 2, 6, 1, 3, 7]);
+        let rc_root = box_tree_to_rc(&box_root);
+        let values: Vec<i32> = rc_tree::Node::find_all(&rc_root, |_| true)
+            .iter()
+            .map(|node| node.value)
+            .collect();
+        assert_eq!(values, [4, 2, 1, 3, 6, 7]);
+        //6 only has a right child, which becomes its one and only child.
+        assert_eq!(rc_root.children()[1].children()[0].value, 7);
+        assert!(rc_root.children()[0].parent().is_some_and(|parent| Rc::ptr_eq(&parent, &rc_root)));
+    }
+
+    #[test]
+    fn every_converted_node_has_a_single_owner() {
+        let rc_root = box_tree_to_rc(&built_by_insert(&[4, 2, 6, 1, 3, 5, 7]));
+        assert_eq!(Rc::strong_count(&rc_root), 1);
+
+        //Each node sits in the stack once, so its owners are the stack and its parent. The Weak
+        // count is one per child pointing back up.
+        let mut pending = rc_root.children();
+        while let Some(node) = pending.pop() {
+            let children = node.children();
+            assert_eq!(Rc::strong_count(&node), 2);
+            assert_eq!(Rc::weak_count(&node), children.len());
+            pending.extend(children);
+        }
+        assert_eq!(Rc::weak_count(&rc_root), 2);
+    }
+
+    #[test]
+    fn nodes_at_depth_lists_each_level_left_to_right() {
+        let root = built_by_insert(&[5, 3, 8, 1, 4, 9]);
+        assert_eq!(root.nodes_at_depth(0), [&5]);
+        assert_eq!(root.nodes_at_depth(1), [&3, &8]);
+        assert_eq!(root.nodes_at_depth(2), [&1, &4, &9]);
+    }
+
+    #[test]
+    fn nodes_at_depth_past_the_bottom_is_empty() {
+        let root = built_by_insert(&[5, 3, 8]);
+        assert!(root.nodes_at_depth(2).is_empty());
+        assert!(root.nodes_at_depth(usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn subtree_containing_returns_the_borrowed_subtree() {
+        let root = built_by_insert(&[5, 3, 8, 1, 4, 9]);
+        let three = root.subtree_containing(&3).unwrap();
+        assert!(std::ptr::eq(three, root.left.as_deref().unwrap()));
+        assert_eq!(three.in_order(), [&1, &3, &4]);
+        assert!(std::ptr::eq(root.subtree_containing(&5).unwrap(), &root));
+        assert_eq!(root.subtree_containing(&9).unwrap().height(), 1);
+    }
+
+    #[test]
+    fn subtree_containing_an_absent_value_is_none() {
+        let root = built_by_insert(&[5, 3, 8]);
+        assert!(root.subtree_containing(&4).is_none());
+        assert!(root.subtree_containing(&100).is_none());
+    }
+
+    #[test]
+    fn diameter_of_a_single_node_is_zero() {
+        assert_eq!(Node::new(1).diameter(), 0);
+    }
+
+    #[test]
+    fn diameter_of_a_balanced_tree_goes_through_the_root() {
+        //From a bottom leaf on the left, up to 4 and down to a bottom leaf on the right.
+        let root = built_by_insert(&[4, 2, 6, 1, 3, 5, 7]);
+        assert_eq!(root.diameter(), 4);
+        assert_eq!(built_by_insert(&[2, 1, 3]).diameter(), 2);
+    }
+
+    #[test]
+    fn diameter_of_a_skewed_tree_can_miss_the_root() {
+        //100 only has a left child. Below it, 50 has a chain of three on either side, so the longest
+        // path runs 20 - 30 - 40 - 50 - 60 - 70 - 80 and never reaches 100.
+        let root = built_by_insert(&[100, 50, 40, 30, 20, 60, 70, 80]);
+        assert_eq!(root.diameter(), 6);
+        assert_eq!(root.height(), 5);
+    }
+
+    #[test]
+    fn a_single_node_is_one_leaf() {
+        let root = Node::new(1);
+        assert_eq!((root.leaf_count(), root.internal_count()), (1, 0));
+    }
+
+    #[test]
+    fn leaf_and_internal_counts_of_a_mixed_tree() {
+        //5 and 3 have children, 8 has only a right child, and 1, 4 and 9 are leaves.
+        let root = built_by_insert(&[5, 3, 8, 1, 4, 9]);
+        assert_eq!(root.leaf_count(), 3);
+        assert_eq!(root.internal_count(), 3);
+    }
+
+    #[test]
+    fn leaves_and_internal_nodes_add_up_to_every_node() {
+        for values in [&[1][..], &[5, 3, 8, 1, 4, 9], &[1, 2, 3, 4, 5], &[4, 2, 6, 1, 3, 5, 7, 4]] {
+            let root = built_by_insert(values);
+            assert_eq!(root.leaf_count() + root.internal_count(), root.in_order().len());
+        }
+    }
+}
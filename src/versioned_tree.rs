@@ -0,0 +1,233 @@
+use std::rc::Rc;
+
+use crate::freeze::FrozenNode;
+use crate::rc_tree::Node;
+
+//Undo for an rc_tree::Node tree. The live tree is edited as usual, and commit() stores a frozen
+// snapshot of it. Any snapshot can be read again with checkout(), or thawed back into the live tree
+// with restore().
+//Freezing from scratch would copy the whole tree on every commit. Instead each commit is frozen
+// against the snapshot the live tree came from, and a subtree that has not changed since then is
+// reused as the very same Rc. Only the nodes on the path to an edit are allocated again.
+pub struct VersionedTree<T> {
+    current: Rc<Node<T>>,
+    versions: Vec<Rc<FrozenNode<T>>>,
+    base: Option<VersionId>,
+}
+
+//Only handed out by commit(), so it always names a snapshot of the tree that made it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionId(usize);
+
+//Returned by restore() for a VersionId that came from a different VersionedTree.
+#[derive(Debug, PartialEq)]
+pub struct UnknownVersion;
+
+impl<T: Clone + PartialEq> VersionedTree<T> {
+    pub fn new(root: Rc<Node<T>>) -> VersionedTree<T> {
+        VersionedTree {
+            current: root,
+            versions: Vec::new(),
+            base: None,
+        }
+    }
+
+    //The live tree. Edits made through it show up in the next commit and in no earlier snapshot.
+    pub fn current(&self) -> &Rc<Node<T>> {
+        &self.current
+    }
+
+    //Committing without any edits since the last commit or restore gives back a version whose root
+    // is the same allocation as before.
+    pub fn commit(&mut self) -> VersionId {
+        let previous = self.base.map(|VersionId(index)| &self.versions[index]);
+        let snapshot = freeze_against(&self.current, previous);
+        let id = VersionId(self.versions.len());
+        self.versions.push(snapshot);
+        self.base = Some(id);
+        id
+    }
+
+    pub fn checkout(&self, id: VersionId) -> Option<Rc<FrozenNode<T>>> {
+        self.versions.get(id.0).cloned()
+    }
+
+    //Throws away any uncommitted edits and makes the live tree a fresh copy of the snapshot. The
+    // next commit is frozen against that snapshot, so whatever is left untouched is shared with it.
+    pub fn restore(&mut self, id: VersionId) -> Result<(), UnknownVersion> {
+        let snapshot = self.versions.get(id.0).ok_or(UnknownVersion)?;
+        self.current = FrozenNode::thaw(snapshot);
+        self.base = Some(id);
+        Ok(())
+    }
+
+    pub fn version_count(&self) -> usize {
+        self.versions.len()
+    }
+}
+
+//Works like Node::freeze(), with an explicit stack and children frozen before their parent. Each
+// live node is paired with the node in the same position of `previous`, and when the value matches
+// and every child came out as the previous child itself, the previous node is reused.
+fn freeze_against<T: Clone + PartialEq>(
+    root: &Rc<Node<T>>,
+    previous: Option<&Rc<FrozenNode<T>>>,
+) -> Rc<FrozenNode<T>> {
+    let mut pending = vec![(Rc::clone(root), previous.cloned(), None)];
+    let mut frozen: Vec<Rc<FrozenNode<T>>> = Vec::new();
+
+    while let Some((node, previous, child_count)) = pending.pop() {
+        match child_count {
+            Some(count) => {
+                let children = frozen.split_off(frozen.len() - count);
+                let unchanged = previous.as_ref().filter(|previous| {
+                    previous.value == node.value
+                        && previous.children.len() == children.len()
+                        && previous
+                            .children
+                            .iter()
+                            .zip(&children)
+                            .all(|(before, after)| Rc::ptr_eq(before, after))
+                });
+                match unchanged {
+                    Some(previous) => frozen.push(Rc::clone(previous)),
+                    None => frozen.push(Rc::new(FrozenNode {
+                        value: node.value.clone(),
+                        children,
+                    })),
+                }
+            }
+            None => {
+                let children = node.children();
+                let previous_children = previous
+                    .as_ref()
+                    .map(|previous| previous.children.clone())
+                    .unwrap_or_default();
+                pending.push((node, previous, Some(children.len())));
+                for (index, child) in children.into_iter().enumerate().rev() {
+                    pending.push((child, previous_children.get(index).cloned(), None));
+                }
+            }
+        }
+    }
+
+    frozen.pop().expect("the root is always frozen last")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn allocations<T>(root: &Rc<FrozenNode<T>>) -> HashSet<*const FrozenNode<T>> {
+        let mut seen = HashSet::new();
+        let mut pending = vec![Rc::clone(root)];
+        while let Some(node) = pending.pop() {
+            if seen.insert(Rc::as_ptr(&node)) {
+                pending.extend(node.children.iter().cloned());
+            }
+        }
+        seen
+    }
+
+    fn commit_and_checkout<T: Clone + PartialEq>(versions: &mut VersionedTree<T>) -> Rc<FrozenNode<T>> {
+        let id = versions.commit();
+        versions.checkout(id).unwrap()
+    }
+
+    #[test]
+    fn checkouts_do_not_see_later_edits() {
+        let mut versions = VersionedTree::new(tree!(1 => [2, 3]));
+        let first = versions.commit();
+        let snapshot = versions.checkout(first).unwrap();
+
+        Node::add_child(versions.current(), &Node::new(4));
+        versions.commit();
+
+        assert_eq!(snapshot.children.len(), 2);
+        assert_eq!(versions.checkout(first).unwrap().children.len(), 2);
+        assert!(Rc::ptr_eq(&snapshot, &versions.checkout(first).unwrap()));
+    }
+
+    #[test]
+    fn committing_without_edits_shares_the_whole_root() {
+        let mut versions = VersionedTree::new(tree!(1 => [2 => [4], 3]));
+        let first = versions.commit();
+        let second = versions.commit();
+        assert_ne!(first, second);
+        assert!(Rc::ptr_eq(&versions.checkout(first).unwrap(), &versions.checkout(second).unwrap()));
+    }
+
+    #[test]
+    fn an_edit_only_copies_the_path_to_it() {
+        let root = tree!(1 => [2 => [4, 5], 3 => [6, 7]]);
+        let four = Rc::clone(&root.children()[0].children()[0]);
+        let mut versions = VersionedTree::new(root);
+        let before = commit_and_checkout(&mut versions);
+
+        Node::add_child(&four, &Node::new(8));
+        let after = commit_and_checkout(&mut versions);
+
+        //The root, 2 and 4 are new, and so is 8, which did not exist before.
+        let old = allocations(&before);
+        let new_nodes = allocations(&after).difference(&old).count();
+        assert_eq!(new_nodes, 4);
+        assert!(Rc::ptr_eq(&before.children[1], &after.children[1]));
+        assert!(Rc::ptr_eq(&before.children[0].children[1], &after.children[0].children[1]));
+        assert_eq!(after.children[0].children[0].children[0].value, 8);
+    }
+
+    #[test]
+    fn restore_brings_back_an_old_version_and_shares_with_it() {
+        let mut versions = VersionedTree::new(tree!(1 => [2, 3]));
+        let first = versions.commit();
+        Node::add_child(versions.current(), &Node::new(4));
+        versions.commit();
+
+        versions.restore(first).unwrap();
+        assert_eq!(versions.current().children().len(), 2);
+        assert!(versions.current().children()[0].parent().is_some());
+
+        let again = versions.commit();
+        assert!(Rc::ptr_eq(&versions.checkout(first).unwrap(), &versions.checkout(again).unwrap()));
+        assert_eq!(versions.version_count(), 3);
+    }
+
+    #[test]
+    fn ids_from_another_tree_are_rejected() {
+        let mut big = VersionedTree::new(Node::new(0));
+        big.commit();
+        let foreign = big.commit();
+        let mut small = VersionedTree::new(Node::new(0));
+
+        assert!(small.checkout(foreign).is_none());
+        assert_eq!(small.restore(foreign), Err(UnknownVersion));
+    }
+
+    #[test]
+    fn a_hundred_commits_on_a_thousand_nodes_stay_small() {
+        //Nine branches of 110 leaves each, so every node is at most two levels down.
+        let root = Node::new(0);
+        let mut branches = Vec::new();
+        for branch in 0..9 {
+            let node = Node::new(branch);
+            Node::add_child(&root, &node);
+            for leaf in 0..110 {
+                Node::add_child(&node, &Node::new(leaf));
+            }
+            branches.push(node);
+        }
+
+        let mut versions = VersionedTree::new(root);
+        let mut all = allocations(&commit_and_checkout(&mut versions));
+        assert_eq!(all.len(), 1_000);
+        for commit in 0..100 {
+            Node::add_child(&branches[commit % 9], &Node::new(commit));
+            all.extend(allocations(&commit_and_checkout(&mut versions)));
+        }
+
+        //Each commit allocates the new leaf, its branch and the root.
+        assert_eq!(all.len(), 1_000 + 100 * 3);
+    }
+}
@@ -0,0 +1,72 @@
+use std::rc::{Rc, Weak};
+
+//A Weak<T> with a friendlier surface. It never keeps the value alive, and once the last Rc<T> is
+// dropped it stops handing the value out.
+pub struct WeakHandle<T> {
+    weak: Weak<T>,
+}
+
+impl<T> WeakHandle<T> {
+    pub fn from_rc(rc: &Rc<T>) -> WeakHandle<T> {
+        WeakHandle {
+            weak: Rc::downgrade(rc),
+        }
+    }
+
+    pub fn get(&self) -> Option<Rc<T>> {
+        self.weak.upgrade()
+    }
+
+    //Checks whether the value is still alive without creating a temporary Rc<T>, which get()
+    // would have to do. Weak::strong_count reads the count directly.
+    pub fn is_valid(&self) -> bool {
+        self.weak.strong_count() > 0
+    }
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        WeakHandle {
+            weak: Weak::clone(&self.weak),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_handle_stops_working_once_the_source_is_dropped() {
+        let source = Rc::new(String::from("value"));
+        let handle = WeakHandle::from_rc(&source);
+        assert!(handle.is_valid());
+        assert_eq!(handle.get().as_deref().map(String::as_str), Some("value"));
+
+        drop(source);
+        assert!(!handle.is_valid());
+        assert!(handle.get().is_none());
+    }
+
+    #[test]
+    fn checking_a_handle_does_not_keep_the_value_alive() {
+        let source = Rc::new(1);
+        let handle = WeakHandle::from_rc(&source);
+        assert!(handle.is_valid());
+        assert_eq!(Rc::strong_count(&source), 1);
+        assert_eq!(Rc::weak_count(&source), 1);
+    }
+
+    #[test]
+    fn an_upgraded_value_outlives_the_source_until_it_is_dropped() {
+        let source = Rc::new(1);
+        let handle = WeakHandle::from_rc(&source);
+        let copy = handle.clone();
+        let upgraded = handle.get().unwrap();
+
+        drop(source);
+        assert!(copy.is_valid());
+        drop(upgraded);
+        assert!(!copy.is_valid());
+    }
+}
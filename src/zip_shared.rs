@@ -0,0 +1,168 @@
+use std::rc::Rc;
+
+use crate::rc_tree::Node;
+
+type NodePair<T> = (Rc<Node<T>>, Rc<Node<T>>);
+type NodeEntry<T> = EitherOrBoth<Rc<Node<T>>, Rc<Node<T>>>;
+
+//One position in a structural zip. `Both` means the two trees have a node in the same place,
+// `Left` and `Right` mean only one of them does.
+#[derive(Debug, PartialEq)]
+pub enum EitherOrBoth<A, B> {
+    Both(A, B),
+    Left(A),
+    Right(B),
+}
+
+//Walks two trees in lock step, pre-order, and yields the values found at the same position in
+// both. Positions that only exist in one of the trees are skipped along with everything below
+// them.
+//The iterator only holds Rc clones of the nodes it still has to visit. The children of a node
+// are read with a short borrow inside next(), so no Ref is ever held between calls and the trees
+// can be changed while they are being zipped. Changes show up for any node not visited yet.
+pub struct ZipTrees<T> {
+    pending: Vec<NodePair<T>>,
+}
+
+pub fn zip_trees<T: Clone>(a: &Rc<Node<T>>, b: &Rc<Node<T>>) -> ZipTrees<T> {
+    ZipTrees {
+        pending: vec![(Rc::clone(a), Rc::clone(b))],
+    }
+}
+
+impl<T: Clone> Iterator for ZipTrees<T> {
+    type Item = (T, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (a, b) = self.pending.pop()?;
+        //Pushed in reverse so the first child is visited first.
+        let pairs: Vec<_> = a.children().into_iter().zip(b.children()).collect();
+        self.pending.extend(pairs.into_iter().rev());
+        Some((a.value.clone(), b.value.clone()))
+    }
+}
+
+//Like zip_trees(), except positions that only exist in one tree are reported as well, so the
+// places where the shapes differ can be found.
+pub struct StructuralZip<T> {
+    pending: Vec<NodeEntry<T>>,
+}
+
+pub fn structural_zip<T: Clone>(a: &Rc<Node<T>>, b: &Rc<Node<T>>) -> StructuralZip<T> {
+    StructuralZip {
+        pending: vec![EitherOrBoth::Both(Rc::clone(a), Rc::clone(b))],
+    }
+}
+
+impl<T: Clone> Iterator for StructuralZip<T> {
+    type Item = EitherOrBoth<T, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (item, children) = match self.pending.pop()? {
+            EitherOrBoth::Both(a, b) => {
+                let left = a.children();
+                let right = b.children();
+                let longest = left.len().max(right.len());
+                let children: Vec<_> = (0..longest)
+                    .map(|index| match (left.get(index), right.get(index)) {
+                        (Some(x), Some(y)) => EitherOrBoth::Both(Rc::clone(x), Rc::clone(y)),
+                        (Some(x), None) => EitherOrBoth::Left(Rc::clone(x)),
+                        (None, Some(y)) => EitherOrBoth::Right(Rc::clone(y)),
+                        (None, None) => unreachable!("index is below the longer length"),
+                    })
+                    .collect();
+                (EitherOrBoth::Both(a.value.clone(), b.value.clone()), children)
+            }
+            EitherOrBoth::Left(a) => {
+                let children = a.children().into_iter().map(EitherOrBoth::Left).collect();
+                (EitherOrBoth::Left(a.value.clone()), children)
+            }
+            EitherOrBoth::Right(b) => {
+                let children = b.children().into_iter().map(EitherOrBoth::Right).collect();
+                (EitherOrBoth::Right(b.value.clone()), children)
+            }
+        };
+
+        self.pending.extend(children.into_iter().rev());
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use EitherOrBoth::{Both, Left, Right};
+
+    #[test]
+    fn identical_trees_zip_fully_in_pre_order() {
+        let a = tree!(1 => [2 => [4], 3]);
+        let b = tree!(1 => [2 => [4], 3]);
+        let pairs: Vec<_> = zip_trees(&a, &b).collect();
+        assert_eq!(pairs, [(1, 1), (2, 2), (4, 4), (3, 3)]);
+    }
+
+    #[test]
+    fn zip_trees_stops_at_the_smaller_shape() {
+        let a = tree!(1 => [2 => [4, 5], 3]);
+        let b = tree!(10 => [20]);
+        let pairs: Vec<_> = zip_trees(&a, &b).collect();
+        assert_eq!(pairs, [(1, 10), (2, 20)]);
+    }
+
+    #[test]
+    fn structural_zip_reports_where_the_shapes_differ() {
+        let a = tree!(1 => [2 => [4, 5], 3]);
+        let b = tree!(1 => [2 => [4], 3 => [6]]);
+        let entries: Vec<_> = structural_zip(&a, &b).collect();
+        assert_eq!(entries, [Both(1, 1), Both(2, 2), Both(4, 4), Left(5), Both(3, 3), Right(6)]);
+    }
+
+    #[test]
+    fn a_missing_subtree_is_reported_whole() {
+        let a = tree!(1);
+        let b = tree!(1 => [2 => [3]]);
+        let entries: Vec<_> = structural_zip(&a, &b).collect();
+        assert_eq!(entries, [Both(1, 1), Right(2), Right(3)]);
+    }
+
+    #[test]
+    fn changes_to_nodes_not_visited_yet_show_up() {
+        let a = tree!(1 => [2 => [4], 3]);
+        let b = tree!(1 => [2, 3]);
+        let mut zip = zip_trees(&a, &b);
+        assert_eq!(zip.next(), Some((1, 1)));
+
+        //2 is already waiting in the iterator but its children have not been read yet.
+        Node::add_child(&b.children()[0], &Node::new(40));
+        let rest: Vec<_> = zip.collect();
+        assert_eq!(rest, [(2, 2), (4, 40), (3, 3)]);
+    }
+
+    #[test]
+    fn holding_node_clones_while_zipping_does_not_panic() {
+        let a = tree!(1 => [2, 3]);
+        let b = tree!(1 => [2, 3]);
+        let held = a.children();
+        for (x, y) in zip_trees(&a, &b) {
+            Node::add_child(&held[1], &Node::new(x + y));
+        }
+        assert_eq!(a.children()[1].children().len(), 3);
+    }
+
+    #[test]
+    fn ten_thousand_deep_trees_zip_without_overflowing() {
+        //Every node stays in a Vec so the chains are freed one node at a time.
+        let chain = || {
+            let nodes: Vec<_> = (0..10_000).map(Node::new).collect();
+            for pair in nodes.windows(2) {
+                Node::add_child(&pair[0], &pair[1]);
+            }
+            nodes
+        };
+        let a = chain();
+        let b = chain();
+        assert_eq!(zip_trees(&a[0], &b[0]).count(), 10_000);
+        assert!(Node::structural_eq(&a[0], &b[0]));
+    }
+}
@@ -0,0 +1,23 @@
+use std::path::Path;
+
+use rust_book_chapter_15::drop_edge_cases::run_crash_demo_in_subprocess;
+
+//Cargo builds the binary before running integration tests and tells them where it is. That makes
+// this the place to start the real binary with the hidden --crash-demo flag.
+#[test]
+fn unguarded_panic_in_drop_aborts_the_process() {
+    let output = run_crash_demo_in_subprocess(Path::new(env!("CARGO_BIN_EXE_rust_book_chapter_15")))
+        .expect("the binary can be started");
+
+    assert!(!output.status.success());
+    //A normal panic exits with code 101. An abort has no exit code at all on Unix, it is ended by
+    // SIGABRT instead.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(output.status.signal(), Some(6), "{:?}", output.status);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("original panic"), "{}", stderr);
+    assert!(stderr.contains("panic inside drop"), "{}", stderr);
+}